@@ -245,6 +245,49 @@ impl Aabb2 {
             && rhs.min.y < self.max.y
     }
 
+    /// Returns the smallest AABB containing both `self` and `rhs`.
+    #[inline]
+    pub fn union(&self, rhs: Aabb2) -> Self {
+        Self {
+            min: self.min.min(rhs.min),
+            max: self.max.max(rhs.max),
+        }
+    }
+
+    /// Returns the time of impact in `[0, 1]` when `self` moves by `delta` towards `rhs`.
+    ///
+    /// Returns `Some(0.0)` if they already intersect, and `None` if they do not intersect
+    /// during the movement. Touching edges are not regarded as intersecting like [`Self::intersects`].
+    pub fn sweep(&self, delta: Vec2, rhs: Aabb2) -> Option<f32> {
+        if self.intersects(rhs) {
+            return Some(0.0);
+        }
+
+        let mut entry = 0.0f32;
+        let mut exit = 1.0f32;
+        for axis in 0..2 {
+            let (min, max, delta) = (self.min[axis], self.max[axis], delta[axis]);
+            let (rhs_min, rhs_max) = (rhs.min[axis], rhs.max[axis]);
+
+            if delta == 0.0 {
+                if max <= rhs_min || rhs_max <= min {
+                    return None;
+                }
+                continue;
+            }
+
+            let t0 = (rhs_min - max) / delta;
+            let t1 = (rhs_max - min) / delta;
+            entry = entry.max(t0.min(t1));
+            exit = exit.min(t0.max(t1));
+            if entry >= exit {
+                return None;
+            }
+        }
+
+        Some(entry)
+    }
+
     /// Casts into `IAabb2`.
     #[inline]
     pub fn as_iaabb2(&self) -> IAabb2 {
@@ -7,11 +7,39 @@ use glam::*;
 
 use crate::aabb::*;
 
+/// ベースが配置される層 (描写順)
+#[derive(Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Hash, Debug)]
+pub enum BaseLayer {
+    Ground,
+    Overlay,
+    Decal,
+}
+
+impl BaseLayer {
+    /// 全ての層 (描写順)
+    pub const ALL: [BaseLayer; 3] = [BaseLayer::Ground, BaseLayer::Overlay, BaseLayer::Decal];
+}
+
 pub struct BaseSpec {
     pub id: usize,
     pub label: String,
+    pub layer: BaseLayer,
     pub texture_path: PathBuf,
     pub texture_mip_option: image_atlas::AtlasEntryMipOption,
+    /// 地面が濡れている時のテクスチャ
+    pub wet_texture_path: Option<PathBuf>,
+}
+
+/// ブロックが個別に持つ状態の仕様
+pub enum BlockStateSpec {
+    /// 耐久値 (`max`で破壊されていない状態)
+    Durability { max: u32 },
+    /// 成長段階 (`0`から`stages - 1`まで)
+    Growth { stages: u32 },
+    /// 開閉状態
+    Toggle { open: bool },
+    /// 内容物の識別子
+    Container,
 }
 
 pub struct BlockSpec {
@@ -22,6 +50,47 @@ pub struct BlockSpec {
     pub z_along_y: bool,
     pub texture_path: PathBuf,
     pub texture_mip_option: image_atlas::AtlasEntryMipOption,
+    pub state_spec: Option<BlockStateSpec>,
+    pub state_texture_paths: Vec<PathBuf>,
+    /// 破壊に必要な時間 (秒)
+    pub hardness: f32,
+    /// 破壊した時に落とすアイテム
+    pub loot: Vec<LootSpec>,
+    /// 経路探索で障害物として扱うか
+    pub solid: bool,
+    /// 時間経過による別のブロックへの遷移
+    pub transition: Option<BlockTransitionSpec>,
+    /// 光源 (松明など)
+    pub light: Option<LightSpec>,
+    /// プレイヤーが働きかけた時に呼び出すスクリプトの関数名
+    pub interaction: Option<String>,
+}
+
+/// 時間経過によるブロックの遷移
+pub struct BlockTransitionSpec {
+    /// 遷移後のブロックの仕様の識別子
+    pub block_spec_id: usize,
+    /// 遷移するまでの時間 (秒)
+    pub duration: f32,
+}
+
+/// 光源の仕様
+pub struct LightSpec {
+    pub color: Vec3,
+    /// 光が届く半径
+    pub radius: f32,
+    /// 明るさの揺らぎの大きさ (`0.0`で揺らがない)
+    pub flicker: f32,
+    /// 固体のブロックで光を遮るか
+    pub occlusion: bool,
+}
+
+/// ブロックを破壊した時に落とすアイテムの候補
+pub struct LootSpec {
+    pub item_spec_id: usize,
+    pub min: u32,
+    pub max: u32,
+    pub probability: f32,
 }
 
 pub struct EntitySpec {
@@ -32,6 +101,73 @@ pub struct EntitySpec {
     pub z_along_y: bool,
     pub texture_path: PathBuf,
     pub texture_mip_option: image_atlas::AtlasEntryMipOption,
+    /// 他の固体のエンティティと重ならないか
+    pub solid: bool,
+    /// 振る舞いの仕様の識別子
+    pub behaviour_spec_id: Option<usize>,
+    /// 最大体力 (`None`の場合はダメージを受けない)
+    pub health: Option<f32>,
+    /// 倒された時に落とすアイテム
+    pub loot: Vec<LootSpec>,
+    /// 光源 (光るエンティティ)
+    pub light: Option<LightSpec>,
+}
+
+/// 振る舞いの状態における行動
+pub enum BehaviourActionSpec {
+    /// その場で停止する。
+    Idle,
+    /// 起点の周囲のランダムな位置へ移動する。
+    Wander { speed: f32, radius: i32 },
+    /// プレイヤーから離れる方向へ移動する。
+    Flee { speed: f32, distance: f32 },
+    /// プレイヤーへ一定の距離まで近づく。
+    Follow { speed: f32, distance: f32 },
+    /// 近くのブロックへ移動し、一定時間後に取り除く。
+    Graze {
+        block_spec_id: usize,
+        speed: f32,
+        radius: i32,
+        duration: f32,
+    },
+    /// 起点からの相対位置を順に巡回する。
+    Patrol { speed: f32, points: Vec<Vec2> },
+    /// スクリプトの関数を毎ティック呼び出す。(`true`を返すと完了する)
+    Script { function: String },
+}
+
+/// 状態が遷移する条件
+pub enum BehaviourConditionSpec {
+    /// プレイヤーが指定した距離以内にいる。
+    PlayerWithin { distance: f32 },
+    /// プレイヤーが指定した距離より遠くにいる。
+    PlayerBeyond { distance: f32 },
+    /// 状態に入ってから指定した時間が経過した。
+    Elapsed { seconds: f32 },
+    /// 行動が完了した。
+    Done,
+}
+
+/// 状態の遷移
+pub struct BehaviourTransitionSpec {
+    pub condition: BehaviourConditionSpec,
+    pub state_id: usize,
+}
+
+/// 振る舞いの状態
+pub struct BehaviourStateSpec {
+    pub label: String,
+    pub action: BehaviourActionSpec,
+    /// 先頭から順に評価され、最初に満たされた遷移のみが実行される。
+    pub transitions: Vec<BehaviourTransitionSpec>,
+}
+
+/// エンティティの振る舞いを表す状態機械
+pub struct BehaviourSpec {
+    pub id: usize,
+    pub label: String,
+    pub states: Vec<BehaviourStateSpec>,
+    pub initial_state_id: usize,
 }
 
 pub enum GenerationSpec {
@@ -49,6 +185,16 @@ pub enum GenerationSpec {
         block_spec_id: usize,
         probability: f32,
     },
+    RandomEntity {
+        id: usize,
+        entity_spec_id: usize,
+        probability: f32,
+    },
+    /// 生成したグリッド毎にスクリプトの関数を呼び出す。
+    Script {
+        id: usize,
+        function: String,
+    },
 }
 
 pub struct PlayerSpec {
@@ -56,14 +202,163 @@ pub struct PlayerSpec {
     pub label: String,
     pub entity_spec_id: usize,
     pub texture_path: PathBuf,
+    /// 遠距離攻撃で発射する投射物の仕様の識別子
+    pub projectile_spec_id: Option<usize>,
+    /// プレイヤーが持つランタンの光源
+    pub lantern: Option<LightSpec>,
+}
+
+/// 投射物が命中した時の効果
+pub enum ProjectileEffectSpec {
+    /// 命中したエンティティにダメージを与える。
+    Damage { amount: f32 },
+    /// 命中した位置に一定時間留まる。
+    Stick { duration: f32 },
+    /// 命中したブロックを破壊する。
+    BreakBlock,
+}
+
+pub struct ProjectileSpec {
+    pub id: usize,
+    pub label: String,
+    pub entity_spec_id: usize,
+    pub speed: f32,
+    /// 消滅するまでの時間 (秒)
+    pub lifetime: f32,
+    pub effects: Vec<ProjectileEffectSpec>,
+}
+
+pub struct ItemSpec {
+    pub id: usize,
+    pub label: String,
+    pub icon_texture_path: PathBuf,
+    /// 落ちている状態を表すエンティティの仕様の識別子
+    pub drop_entity_spec_id: usize,
+    /// 1つのスロットに重ねられる最大数
+    pub max_stack: u32,
+    /// 設置するブロックの仕様の識別子
+    pub block_spec_id: Option<usize>,
+}
+
+/// レシピが消費・生成するアイテム
+pub struct RecipeItem {
+    pub item_spec_id: usize,
+    pub count: u32,
+}
+
+pub struct RecipeSpec {
+    pub id: usize,
+    pub label: String,
+    pub inputs: Vec<RecipeItem>,
+    pub outputs: Vec<RecipeItem>,
+    /// 近くに必要なブロックの仕様の識別子 (作業台など)
+    pub required_block_spec_id: Option<usize>,
+}
+
+/// 環境光の色の制御点
+pub struct AmbientKeySpec {
+    /// 時刻 (`0.0`から`1.0`、`0.5`が正午)
+    pub time_of_day: f32,
+    pub color: Vec3,
+}
+
+/// ワールドの時計の仕様
+pub struct ClockSpec {
+    /// 1日の長さ (秒)
+    pub day_length: f32,
+    /// ワールドを作成した時の時刻
+    pub start_time_of_day: f32,
+    /// 時刻の昇順に並んだ環境光の色の制御点 (空の場合は常に白)
+    pub ambient_keys: Vec<AmbientKeySpec>,
+}
+
+impl Default for ClockSpec {
+    #[inline]
+    fn default() -> Self {
+        Self {
+            day_length: 600.0,
+            start_time_of_day: 0.5,
+            ambient_keys: vec![],
+        }
+    }
+}
+
+/// 天候で降る粒子の仕様
+pub struct ParticleSpec {
+    /// 色 (アルファ値を含む)
+    pub color: Vec4,
+    /// 幅と進行方向の長さ
+    pub size: Vec2,
+    pub velocity: Vec2,
+    /// 横方向の揺れの大きさ
+    pub sway: f32,
+    /// 1タイルあたりの粒子の数
+    pub density: f32,
+}
+
+/// 画面を覆う霧の仕様
+pub struct FogSpec {
+    pub color: Vec3,
+    /// 濃さ (`0.0`から`1.0`)
+    pub density: f32,
+}
+
+pub struct WeatherSpec {
+    pub id: usize,
+    pub label: String,
+    /// 選ばれる重み
+    pub weight: f32,
+    /// 発生する時間帯 (開始と終了の時刻)
+    pub time_of_day: Option<(f32, f32)>,
+    pub particle: Option<ParticleSpec>,
+    pub fog: Option<FogSpec>,
+    /// 地面を濡らすか
+    pub wet: bool,
+    /// 地上を移動するエンティティの速度の倍率
+    pub speed_multiplier: f32,
+}
+
+/// 天候の移り変わりの仕様
+pub struct ClimateSpec {
+    /// 天候が決まる地域の一辺の大きさ
+    pub region_size: i32,
+    /// 天候が続く時間 (秒)
+    pub duration: f32,
+    /// 天候が切り替わる時の遷移にかかる時間 (秒)
+    pub fade: f32,
+}
+
+impl Default for ClimateSpec {
+    #[inline]
+    fn default() -> Self {
+        Self {
+            region_size: 64,
+            duration: 120.0,
+            fade: 10.0,
+        }
+    }
 }
 
 pub struct Assets {
     pub base_specs: Vec<BaseSpec>,
     pub entity_specs: Vec<EntitySpec>,
     pub block_specs: Vec<BlockSpec>,
+    pub behaviour_specs: Vec<BehaviourSpec>,
+    pub projectile_specs: Vec<ProjectileSpec>,
     pub generation_specs: Vec<GenerationSpec>,
     pub player_specs: Vec<PlayerSpec>,
+    pub item_specs: Vec<ItemSpec>,
+    pub recipe_specs: Vec<RecipeSpec>,
+    pub clock_spec: ClockSpec,
+    pub weather_specs: Vec<WeatherSpec>,
+    pub climate_spec: ClimateSpec,
+    /// 読み込む順に並んだスクリプトのパス
+    pub script_paths: Vec<PathBuf>,
+    /// 文字の描写に使用するテクスチャのパス
+    ///
+    /// ASCIIの`0x20`から`0x7F`までの文字を横16文字・縦6文字の格子に並べる。
+    /// `0x7F`の位置は塗りつぶされている必要がある。
+    pub font_texture_path: PathBuf,
 }
 
 impl Assets {
@@ -93,8 +388,25 @@ impl Assets {
         #[serde(rename_all = "camelCase")]
         struct BaseSpecIn {
             label: String,
+            #[serde(default)]
+            layer: Option<String>,
             texture_path: String,
             texture_mip_option: String,
+            #[serde(default)]
+            wet_texture_path: Option<String>,
+        }
+
+        #[derive(serde::Deserialize)]
+        #[serde(tag = "kind", rename_all = "camelCase")]
+        enum BlockStateSpecIn {
+            #[serde(rename_all = "camelCase")]
+            Durability { max: u32 },
+            #[serde(rename_all = "camelCase")]
+            Growth { stages: u32 },
+            #[serde(rename_all = "camelCase")]
+            Toggle { open: bool },
+            #[serde(rename_all = "camelCase")]
+            Container,
         }
 
         #[derive(serde::Deserialize)]
@@ -106,6 +418,38 @@ impl Assets {
             z_along_y: bool,
             texture_path: String,
             texture_mip_option: String,
+            #[serde(default)]
+            state: Option<BlockStateSpecIn>,
+            #[serde(default)]
+            state_texture_paths: Vec<String>,
+            #[serde(default)]
+            hardness: f32,
+            #[serde(default)]
+            loot: Option<Vec<LootSpecIn>>,
+            #[serde(default)]
+            solid: Option<bool>,
+            #[serde(default)]
+            transition: Option<BlockTransitionSpecIn>,
+            #[serde(default)]
+            light: Option<LightSpecIn>,
+            #[serde(default)]
+            interaction: Option<String>,
+        }
+
+        #[derive(serde::Deserialize)]
+        #[serde(rename_all = "camelCase")]
+        struct BlockTransitionSpecIn {
+            block_spec_label: String,
+            duration: f32,
+        }
+
+        #[derive(serde::Deserialize)]
+        #[serde(rename_all = "camelCase")]
+        struct LootSpecIn {
+            item_spec_label: String,
+            min: u32,
+            max: u32,
+            probability: f32,
         }
 
         #[derive(serde::Deserialize)]
@@ -117,6 +461,77 @@ impl Assets {
             z_along_y: bool,
             texture_path: String,
             texture_mip_option: String,
+            #[serde(default)]
+            solid: Option<bool>,
+            #[serde(default)]
+            behaviour_spec_label: Option<String>,
+            #[serde(default)]
+            health: Option<f32>,
+            #[serde(default)]
+            loot: Vec<LootSpecIn>,
+            #[serde(default)]
+            light: Option<LightSpecIn>,
+        }
+
+        #[derive(serde::Deserialize)]
+        #[serde(tag = "mode", rename_all = "camelCase")]
+        enum BehaviourActionSpecIn {
+            #[serde(rename_all = "camelCase")]
+            Idle,
+            #[serde(rename_all = "camelCase")]
+            Wander { speed: f32, radius: i32 },
+            #[serde(rename_all = "camelCase")]
+            Flee { speed: f32, distance: f32 },
+            #[serde(rename_all = "camelCase")]
+            Follow { speed: f32, distance: f32 },
+            #[serde(rename_all = "camelCase")]
+            Graze {
+                block_spec_label: String,
+                speed: f32,
+                radius: i32,
+                duration: f32,
+            },
+            #[serde(rename_all = "camelCase")]
+            Patrol { speed: f32, points: Vec<Vec2In> },
+            #[serde(rename_all = "camelCase")]
+            Script { function: String },
+        }
+
+        #[derive(serde::Deserialize)]
+        #[serde(tag = "mode", rename_all = "camelCase")]
+        enum BehaviourConditionSpecIn {
+            #[serde(rename_all = "camelCase")]
+            PlayerWithin { distance: f32 },
+            #[serde(rename_all = "camelCase")]
+            PlayerBeyond { distance: f32 },
+            #[serde(rename_all = "camelCase")]
+            Elapsed { seconds: f32 },
+            #[serde(rename_all = "camelCase")]
+            Done,
+        }
+
+        #[derive(serde::Deserialize)]
+        #[serde(rename_all = "camelCase")]
+        struct BehaviourTransitionSpecIn {
+            condition: BehaviourConditionSpecIn,
+            state_label: String,
+        }
+
+        #[derive(serde::Deserialize)]
+        #[serde(rename_all = "camelCase")]
+        struct BehaviourStateSpecIn {
+            label: String,
+            action: BehaviourActionSpecIn,
+            #[serde(default)]
+            transitions: Vec<BehaviourTransitionSpecIn>,
+        }
+
+        #[derive(serde::Deserialize)]
+        #[serde(rename_all = "camelCase")]
+        struct BehaviourSpecIn {
+            label: String,
+            initial_state_label: String,
+            states: Vec<BehaviourStateSpecIn>,
         }
 
         #[derive(serde::Deserialize)]
@@ -134,6 +549,13 @@ impl Assets {
                 block_spec_label: String,
                 probability: f32,
             },
+            #[serde(rename_all = "camelCase")]
+            RandomEntity {
+                entity_spec_label: String,
+                probability: f32,
+            },
+            #[serde(rename_all = "camelCase")]
+            Script { function: String },
         }
 
         #[derive(serde::Deserialize)]
@@ -142,6 +564,154 @@ impl Assets {
             label: String,
             entity_spec_label: String,
             texture_path: String,
+            #[serde(default)]
+            projectile_spec_label: Option<String>,
+            #[serde(default)]
+            lantern: Option<LightSpecIn>,
+        }
+
+        #[derive(serde::Deserialize)]
+        #[serde(tag = "mode", rename_all = "camelCase")]
+        enum ProjectileEffectSpecIn {
+            #[serde(rename_all = "camelCase")]
+            Damage { amount: f32 },
+            #[serde(rename_all = "camelCase")]
+            Stick { duration: f32 },
+            #[serde(rename_all = "camelCase")]
+            BreakBlock,
+        }
+
+        #[derive(serde::Deserialize)]
+        #[serde(rename_all = "camelCase")]
+        struct ProjectileSpecIn {
+            label: String,
+            entity_spec_label: String,
+            speed: f32,
+            lifetime: f32,
+            #[serde(default)]
+            effects: Vec<ProjectileEffectSpecIn>,
+        }
+
+        #[derive(serde::Deserialize)]
+        #[serde(rename_all = "camelCase")]
+        struct ItemSpecIn {
+            label: String,
+            icon_texture_path: String,
+            drop_entity_spec_label: String,
+            max_stack: u32,
+            #[serde(default)]
+            block_spec_label: Option<String>,
+        }
+
+        #[derive(serde::Deserialize)]
+        #[serde(rename_all = "camelCase")]
+        struct RecipeItemIn {
+            item_spec_label: String,
+            count: u32,
+        }
+
+        #[derive(serde::Deserialize)]
+        #[serde(rename_all = "camelCase")]
+        struct RecipeSpecIn {
+            label: String,
+            inputs: Vec<RecipeItemIn>,
+            outputs: Vec<RecipeItemIn>,
+            #[serde(default)]
+            required_block_spec_label: Option<String>,
+        }
+
+        #[derive(serde::Deserialize)]
+        #[serde(rename_all = "camelCase")]
+        struct ColorIn {
+            r: f32,
+            g: f32,
+            b: f32,
+        }
+
+        #[derive(serde::Deserialize)]
+        #[serde(rename_all = "camelCase")]
+        struct LightSpecIn {
+            color: ColorIn,
+            radius: f32,
+            #[serde(default)]
+            flicker: f32,
+            #[serde(default)]
+            occlusion: bool,
+        }
+
+        #[derive(serde::Deserialize)]
+        #[serde(rename_all = "camelCase")]
+        struct AmbientKeySpecIn {
+            time_of_day: f32,
+            color: ColorIn,
+        }
+
+        #[derive(serde::Deserialize)]
+        #[serde(rename_all = "camelCase")]
+        struct ClockSpecIn {
+            day_length: f32,
+            start_time_of_day: f32,
+            #[serde(default)]
+            ambient_keys: Vec<AmbientKeySpecIn>,
+        }
+
+        #[derive(serde::Deserialize)]
+        #[serde(rename_all = "camelCase")]
+        struct ColorAlphaIn {
+            r: f32,
+            g: f32,
+            b: f32,
+            a: f32,
+        }
+
+        #[derive(serde::Deserialize)]
+        #[serde(rename_all = "camelCase")]
+        struct ParticleSpecIn {
+            color: ColorAlphaIn,
+            size: Vec2In,
+            velocity: Vec2In,
+            #[serde(default)]
+            sway: f32,
+            density: f32,
+        }
+
+        #[derive(serde::Deserialize)]
+        #[serde(rename_all = "camelCase")]
+        struct FogSpecIn {
+            color: ColorIn,
+            density: f32,
+        }
+
+        #[derive(serde::Deserialize)]
+        #[serde(rename_all = "camelCase")]
+        struct TimeOfDayIn {
+            start: f32,
+            end: f32,
+        }
+
+        #[derive(serde::Deserialize)]
+        #[serde(rename_all = "camelCase")]
+        struct WeatherSpecIn {
+            label: String,
+            weight: f32,
+            #[serde(default)]
+            time_of_day: Option<TimeOfDayIn>,
+            #[serde(default)]
+            particle: Option<ParticleSpecIn>,
+            #[serde(default)]
+            fog: Option<FogSpecIn>,
+            #[serde(default)]
+            wet: bool,
+            #[serde(default)]
+            speed_multiplier: Option<f32>,
+        }
+
+        #[derive(serde::Deserialize)]
+        #[serde(rename_all = "camelCase")]
+        struct ClimateSpecIn {
+            region_size: i32,
+            duration: f32,
+            fade: f32,
         }
 
         #[derive(serde::Deserialize)]
@@ -149,20 +719,51 @@ impl Assets {
         struct AssetsIn {
             base_specs: Vec<BaseSpecIn>,
             block_specs: Vec<BlockSpecIn>,
+            #[serde(default)]
+            behaviour_specs: Vec<BehaviourSpecIn>,
             entity_specs: Vec<EntitySpecIn>,
+            #[serde(default)]
+            projectile_specs: Vec<ProjectileSpecIn>,
             generation_specs: Vec<GenerationSpecIn>,
             player_specs: Vec<PlayerSpecIn>,
+            item_specs: Vec<ItemSpecIn>,
+            recipe_specs: Vec<RecipeSpecIn>,
+            #[serde(default)]
+            clock_spec: Option<ClockSpecIn>,
+            #[serde(default)]
+            weather_specs: Vec<WeatherSpecIn>,
+            #[serde(default)]
+            climate_spec: Option<ClimateSpecIn>,
+            #[serde(default)]
+            script_paths: Vec<String>,
+            font_texture_path: String,
         }
 
         let reader = File::open(path).unwrap();
         let AssetsIn {
             base_specs,
             block_specs,
+            behaviour_specs,
             entity_specs,
+            projectile_specs,
             generation_specs,
             player_specs,
+            item_specs,
+            recipe_specs,
+            clock_spec,
+            weather_specs,
+            climate_spec,
+            script_paths,
+            font_texture_path,
         } = serde_json::from_reader(reader).unwrap();
 
+        // NOTE: ベースの仕様はチャンク内で`u16`として保持され、最大値は空を表す。
+        assert!(
+            base_specs.len() < u16::MAX as usize,
+            "too many base specs: {}",
+            base_specs.len()
+        );
+
         let base_specs = base_specs
             .into_iter()
             .enumerate()
@@ -171,10 +772,18 @@ impl Assets {
                     id,
                     BaseSpecIn {
                         label,
+                        layer,
                         texture_path,
                         texture_mip_option,
+                        wet_texture_path,
                     },
                 )| {
+                    let layer = match layer.as_deref() {
+                        None | Some("ground") => BaseLayer::Ground,
+                        Some("overlay") => BaseLayer::Overlay,
+                        Some("decal") => BaseLayer::Decal,
+                        _ => unreachable!(),
+                    };
                     let texture_mip_option = match texture_mip_option.as_str() {
                         "clamp" => image_atlas::AtlasEntryMipOption::Clamp,
                         "repeat" => image_atlas::AtlasEntryMipOption::Repeat,
@@ -185,14 +794,37 @@ impl Assets {
                     BaseSpec {
                         id,
                         label,
+                        layer,
                         texture_path: texture_path.into(),
                         texture_mip_option,
+                        wet_texture_path: wet_texture_path.map(Into::into),
                     }
                 },
             )
             .collect::<Vec<_>>();
 
-        let block_specs = block_specs
+        // NOTE: 落とすアイテムはアイテムの仕様を読み込んだ後に解決する。
+        let (block_specs, block_loots): (Vec<_>, Vec<_>) = block_specs
+            .into_iter()
+            .map(|mut block_spec| {
+                let loot = block_spec.loot.take();
+                (block_spec, loot)
+            })
+            .unzip();
+
+        let light_spec = |light: LightSpecIn| LightSpec {
+            color: vec3(light.color.r, light.color.g, light.color.b),
+            radius: light.radius,
+            flicker: light.flicker,
+            occlusion: light.occlusion,
+        };
+
+        let block_spec_labels = block_specs
+            .iter()
+            .map(|block_spec| block_spec.label.clone())
+            .collect::<Vec<_>>();
+
+        let mut block_specs = block_specs
             .into_iter()
             .enumerate()
             .map(
@@ -205,6 +837,14 @@ impl Assets {
                         z_along_y,
                         texture_path,
                         texture_mip_option,
+                        state,
+                        state_texture_paths,
+                        hardness,
+                        loot: _,
+                        solid,
+                        transition,
+                        light,
+                        interaction,
                     },
                 )| {
                     let internal_size = ivec2(internal_size.x, internal_size.y);
@@ -220,6 +860,28 @@ impl Assets {
                         _ => unreachable!(),
                     };
 
+                    let state_spec = state.map(|state| match state {
+                        BlockStateSpecIn::Durability { max } => BlockStateSpec::Durability { max },
+                        BlockStateSpecIn::Growth { stages } => BlockStateSpec::Growth { stages },
+                        BlockStateSpecIn::Toggle { open } => BlockStateSpec::Toggle { open },
+                        BlockStateSpecIn::Container => BlockStateSpec::Container,
+                    });
+                    let state_texture_paths = state_texture_paths
+                        .into_iter()
+                        .map(|texture_path| texture_path.into())
+                        .collect();
+
+                    let transition = transition.map(|transition| {
+                        let block_spec_id = block_spec_labels
+                            .iter()
+                            .position(|label| *label == transition.block_spec_label)
+                            .unwrap();
+                        BlockTransitionSpec {
+                            block_spec_id,
+                            duration: transition.duration,
+                        }
+                    });
+
                     BlockSpec {
                         id,
                         label,
@@ -228,12 +890,151 @@ impl Assets {
                         z_along_y,
                         texture_path,
                         texture_mip_option,
+                        state_spec,
+                        state_texture_paths,
+                        hardness,
+                        loot: vec![],
+                        solid: solid.unwrap_or(true),
+                        transition,
+                        light: light.map(light_spec),
+                        interaction,
                     }
                 },
             )
             .collect::<Vec<_>>();
 
-        let entity_specs = entity_specs
+        let behaviour_specs = behaviour_specs
+            .into_iter()
+            .enumerate()
+            .map(
+                |(
+                    id,
+                    BehaviourSpecIn {
+                        label,
+                        initial_state_label,
+                        states,
+                    },
+                )| {
+                    let state_id = |state_label: &str| {
+                        states
+                            .iter()
+                            .position(|state| state.label == state_label)
+                            .unwrap()
+                    };
+                    let initial_state_id = state_id(&initial_state_label);
+
+                    let states = states
+                        .iter()
+                        .map(|state| {
+                            let action = match &state.action {
+                                BehaviourActionSpecIn::Idle => BehaviourActionSpec::Idle,
+                                BehaviourActionSpecIn::Wander { speed, radius } => {
+                                    BehaviourActionSpec::Wander {
+                                        speed: *speed,
+                                        radius: *radius,
+                                    }
+                                }
+                                BehaviourActionSpecIn::Flee { speed, distance } => {
+                                    BehaviourActionSpec::Flee {
+                                        speed: *speed,
+                                        distance: *distance,
+                                    }
+                                }
+                                BehaviourActionSpecIn::Follow { speed, distance } => {
+                                    BehaviourActionSpec::Follow {
+                                        speed: *speed,
+                                        distance: *distance,
+                                    }
+                                }
+                                BehaviourActionSpecIn::Graze {
+                                    block_spec_label,
+                                    speed,
+                                    radius,
+                                    duration,
+                                } => {
+                                    let block_spec_id = block_specs
+                                        .iter()
+                                        .find(|block_spec| &block_spec.label == block_spec_label)
+                                        .unwrap()
+                                        .id;
+
+                                    BehaviourActionSpec::Graze {
+                                        block_spec_id,
+                                        speed: *speed,
+                                        radius: *radius,
+                                        duration: *duration,
+                                    }
+                                }
+                                BehaviourActionSpecIn::Patrol { speed, points } => {
+                                    BehaviourActionSpec::Patrol {
+                                        speed: *speed,
+                                        points: points
+                                            .iter()
+                                            .map(|point| vec2(point.x, point.y))
+                                            .collect(),
+                                    }
+                                }
+                                BehaviourActionSpecIn::Script { function } => {
+                                    BehaviourActionSpec::Script {
+                                        function: function.clone(),
+                                    }
+                                }
+                            };
+
+                            let transitions = state
+                                .transitions
+                                .iter()
+                                .map(|transition| {
+                                    let condition = match transition.condition {
+                                        BehaviourConditionSpecIn::PlayerWithin { distance } => {
+                                            BehaviourConditionSpec::PlayerWithin { distance }
+                                        }
+                                        BehaviourConditionSpecIn::PlayerBeyond { distance } => {
+                                            BehaviourConditionSpec::PlayerBeyond { distance }
+                                        }
+                                        BehaviourConditionSpecIn::Elapsed { seconds } => {
+                                            BehaviourConditionSpec::Elapsed { seconds }
+                                        }
+                                        BehaviourConditionSpecIn::Done => {
+                                            BehaviourConditionSpec::Done
+                                        }
+                                    };
+
+                                    BehaviourTransitionSpec {
+                                        condition,
+                                        state_id: state_id(&transition.state_label),
+                                    }
+                                })
+                                .collect();
+
+                            BehaviourStateSpec {
+                                label: state.label.clone(),
+                                action,
+                                transitions,
+                            }
+                        })
+                        .collect();
+
+                    BehaviourSpec {
+                        id,
+                        label,
+                        states,
+                        initial_state_id,
+                    }
+                },
+            )
+            .collect::<Vec<_>>();
+
+        // NOTE: 落とすアイテムはアイテムの仕様を読み込んだ後に解決する。
+        let (entity_specs, entity_loots): (Vec<_>, Vec<_>) = entity_specs
+            .into_iter()
+            .map(|mut entity_spec| {
+                let loot = std::mem::take(&mut entity_spec.loot);
+                (entity_spec, loot)
+            })
+            .unzip();
+
+        let mut entity_specs = entity_specs
             .into_iter()
             .enumerate()
             .map(
@@ -246,6 +1047,11 @@ impl Assets {
                         z_along_y,
                         texture_path,
                         texture_mip_option,
+                        solid,
+                        behaviour_spec_label,
+                        health,
+                        loot: _,
+                        light,
                     },
                 )| {
                     let internal_size = vec2(internal_size.x, internal_size.y);
@@ -260,6 +1066,13 @@ impl Assets {
                         "mirror" => image_atlas::AtlasEntryMipOption::Mirror,
                         _ => unreachable!(),
                     };
+                    let behaviour_spec_id = behaviour_spec_label.map(|behaviour_spec_label| {
+                        behaviour_specs
+                            .iter()
+                            .find(|behaviour_spec| behaviour_spec.label == behaviour_spec_label)
+                            .unwrap()
+                            .id
+                    });
 
                     EntitySpec {
                         id,
@@ -269,6 +1082,11 @@ impl Assets {
                         z_along_y,
                         texture_path,
                         texture_mip_option,
+                        solid: solid.unwrap_or(true),
+                        behaviour_spec_id,
+                        health,
+                        loot: vec![],
+                        light: light.map(light_spec),
                     }
                 },
             )
@@ -319,9 +1137,70 @@ impl Assets {
                         probability,
                     }
                 }
+                GenerationSpecIn::RandomEntity {
+                    entity_spec_label,
+                    probability,
+                } => {
+                    let entity_spec_id = entity_specs
+                        .iter()
+                        .find(|entity_spec| entity_spec.label == entity_spec_label)
+                        .unwrap()
+                        .id;
+
+                    GenerationSpec::RandomEntity {
+                        id,
+                        entity_spec_id,
+                        probability,
+                    }
+                }
+                GenerationSpecIn::Script { function } => GenerationSpec::Script { id, function },
             })
             .collect::<Vec<_>>();
 
+        let projectile_specs = projectile_specs
+            .into_iter()
+            .enumerate()
+            .map(
+                |(
+                    id,
+                    ProjectileSpecIn {
+                        label,
+                        entity_spec_label,
+                        speed,
+                        lifetime,
+                        effects,
+                    },
+                )| {
+                    let entity_spec_id = entity_specs
+                        .iter()
+                        .find(|entity_spec| entity_spec.label == entity_spec_label)
+                        .unwrap()
+                        .id;
+                    let effects = effects
+                        .into_iter()
+                        .map(|effect| match effect {
+                            ProjectileEffectSpecIn::Damage { amount } => {
+                                ProjectileEffectSpec::Damage { amount }
+                            }
+                            ProjectileEffectSpecIn::Stick { duration } => {
+                                ProjectileEffectSpec::Stick { duration }
+                            }
+                            ProjectileEffectSpecIn::BreakBlock => ProjectileEffectSpec::BreakBlock,
+                        })
+                        .collect();
+
+                    ProjectileSpec {
+                        id,
+                        label,
+                        entity_spec_id,
+                        speed,
+                        lifetime,
+                        effects,
+                    }
+                },
+            )
+            .collect::<Vec<_>>();
+
         let player_specs = player_specs
             .into_iter()
             .enumerate()
@@ -332,6 +1211,8 @@ impl Assets {
                         label,
                         entity_spec_label,
                         texture_path,
+                        projectile_spec_label,
+                        lantern,
                     },
                 )| {
                     let entity_spec_id = entity_specs
@@ -340,23 +1221,278 @@ impl Assets {
                         .unwrap()
                         .id;
                     let texture_path = texture_path.into();
+                    let projectile_spec_id = projectile_spec_label.map(|projectile_spec_label| {
+                        projectile_specs
+                            .iter()
+                            .find(|projectile_spec| projectile_spec.label == projectile_spec_label)
+                            .unwrap()
+                            .id
+                    });
 
                     PlayerSpec {
                         id,
                         label,
                         entity_spec_id,
                         texture_path,
+                        projectile_spec_id,
+                        lantern: lantern.map(light_spec),
+                    }
+                },
+            )
+            .collect::<Vec<_>>();
+
+        let item_specs = item_specs
+            .into_iter()
+            .enumerate()
+            .map(
+                |(
+                    id,
+                    ItemSpecIn {
+                        label,
+                        icon_texture_path,
+                        drop_entity_spec_label,
+                        max_stack,
+                        block_spec_label,
+                    },
+                )| {
+                    let icon_texture_path = icon_texture_path.into();
+                    let drop_entity_spec_id = entity_specs
+                        .iter()
+                        .find(|entity_spec| entity_spec.label == drop_entity_spec_label)
+                        .unwrap()
+                        .id;
+                    let block_spec_id = block_spec_label.map(|block_spec_label| {
+                        block_specs
+                            .iter()
+                            .find(|block_spec| block_spec.label == block_spec_label)
+                            .unwrap()
+                            .id
+                    });
+
+                    ItemSpec {
+                        id,
+                        label,
+                        icon_texture_path,
+                        drop_entity_spec_id,
+                        max_stack,
+                        block_spec_id,
+                    }
+                },
+            )
+            .collect::<Vec<_>>();
+
+        let resolve_loot = |loot: Vec<LootSpecIn>| {
+            loot.into_iter()
+                .map(
+                    |LootSpecIn {
+                         item_spec_label,
+                         min,
+                         max,
+                         probability,
+                     }| {
+                        let item_spec_id = item_specs
+                            .iter()
+                            .find(|item_spec| item_spec.label == item_spec_label)
+                            .unwrap()
+                            .id;
+
+                        LootSpec {
+                            item_spec_id,
+                            min,
+                            max,
+                            probability,
+                        }
+                    },
+                )
+                .collect::<Vec<_>>()
+        };
+
+        // NOTE: 指定がない場合は、ブロックに対応するアイテムを1つ落とす。
+        block_specs
+            .iter_mut()
+            .zip(block_loots)
+            .for_each(|(block_spec, loot)| {
+                block_spec.loot = match loot {
+                    Some(loot) => resolve_loot(loot),
+                    None => item_specs
+                        .iter()
+                        .filter(|item_spec| item_spec.block_spec_id == Some(block_spec.id))
+                        .map(|item_spec| LootSpec {
+                            item_spec_id: item_spec.id,
+                            min: 1,
+                            max: 1,
+                            probability: 1.0,
+                        })
+                        .collect(),
+                };
+            });
+
+        entity_specs
+            .iter_mut()
+            .zip(entity_loots)
+            .for_each(|(entity_spec, loot)| {
+                entity_spec.loot = resolve_loot(loot);
+            });
+
+        let recipe_specs = recipe_specs
+            .into_iter()
+            .enumerate()
+            .map(
+                |(
+                    id,
+                    RecipeSpecIn {
+                        label,
+                        inputs,
+                        outputs,
+                        required_block_spec_label,
+                    },
+                )| {
+                    let recipe_items = |items: Vec<RecipeItemIn>| {
+                        items
+                            .into_iter()
+                            .map(
+                                |RecipeItemIn {
+                                     item_spec_label,
+                                     count,
+                                 }| {
+                                    let item_spec_id = item_specs
+                                        .iter()
+                                        .find(|item_spec| item_spec.label == item_spec_label)
+                                        .unwrap()
+                                        .id;
+
+                                    RecipeItem {
+                                        item_spec_id,
+                                        count,
+                                    }
+                                },
+                            )
+                            .collect::<Vec<_>>()
+                    };
+                    let inputs = recipe_items(inputs);
+                    let outputs = recipe_items(outputs);
+                    let required_block_spec_id =
+                        required_block_spec_label.map(|block_spec_label| {
+                            block_specs
+                                .iter()
+                                .find(|block_spec| block_spec.label == block_spec_label)
+                                .unwrap()
+                                .id
+                        });
+
+                    RecipeSpec {
+                        id,
+                        label,
+                        inputs,
+                        outputs,
+                        required_block_spec_id,
+                    }
+                },
+            )
+            .collect::<Vec<_>>();
+
+        let clock_spec = clock_spec
+            .map(
+                |ClockSpecIn {
+                     day_length,
+                     start_time_of_day,
+                     ambient_keys,
+                 }| {
+                    let mut ambient_keys = ambient_keys
+                        .into_iter()
+                        .map(|AmbientKeySpecIn { time_of_day, color }| AmbientKeySpec {
+                            time_of_day,
+                            color: vec3(color.r, color.g, color.b),
+                        })
+                        .collect::<Vec<_>>();
+                    ambient_keys.sort_by(|a, b| a.time_of_day.total_cmp(&b.time_of_day));
+
+                    ClockSpec {
+                        day_length,
+                        start_time_of_day,
+                        ambient_keys,
+                    }
+                },
+            )
+            .unwrap_or_default();
+
+        let weather_specs = weather_specs
+            .into_iter()
+            .enumerate()
+            .map(
+                |(
+                    id,
+                    WeatherSpecIn {
+                        label,
+                        weight,
+                        time_of_day,
+                        particle,
+                        fog,
+                        wet,
+                        speed_multiplier,
+                    },
+                )| {
+                    let time_of_day = time_of_day.map(|TimeOfDayIn { start, end }| (start, end));
+                    let particle = particle.map(|particle| ParticleSpec {
+                        color: vec4(
+                            particle.color.r,
+                            particle.color.g,
+                            particle.color.b,
+                            particle.color.a,
+                        ),
+                        size: vec2(particle.size.x, particle.size.y),
+                        velocity: vec2(particle.velocity.x, particle.velocity.y),
+                        sway: particle.sway,
+                        density: particle.density,
+                    });
+                    let fog = fog.map(|fog| FogSpec {
+                        color: vec3(fog.color.r, fog.color.g, fog.color.b),
+                        density: fog.density,
+                    });
+
+                    WeatherSpec {
+                        id,
+                        label,
+                        weight,
+                        time_of_day,
+                        particle,
+                        fog,
+                        wet,
+                        speed_multiplier: speed_multiplier.unwrap_or(1.0),
                     }
                 },
             )
             .collect::<Vec<_>>();
 
+        let climate_spec = climate_spec
+            .map(
+                |ClimateSpecIn {
+                     region_size,
+                     duration,
+                     fade,
+                 }| ClimateSpec {
+                    region_size,
+                    duration,
+                    fade,
+                },
+            )
+            .unwrap_or_default();
+
         Self {
             base_specs,
             block_specs,
+            behaviour_specs,
             entity_specs,
+            projectile_specs,
             generation_specs,
             player_specs,
+            item_specs,
+            recipe_specs,
+            clock_spec,
+            weather_specs,
+            climate_spec,
+            script_paths: script_paths.into_iter().map(Into::into).collect(),
+            font_texture_path: font_texture_path.into(),
         }
     }
 }
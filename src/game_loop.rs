@@ -2,89 +2,1176 @@
 
 use glam::*;
 
+use crate::aabb::*;
 use crate::assets;
 
-mod base;
-mod block;
-mod camera;
-mod entity;
-mod generation;
-mod player;
+pub mod base;
+pub mod behaviour;
+pub mod block;
+pub mod camera;
+pub mod clock;
+pub mod combat;
+pub mod component;
+pub mod console;
+pub mod crafting;
+pub mod debug;
+pub mod drop;
+pub mod entity;
+pub mod generation;
+pub mod input;
+pub mod inventory;
+pub mod journal;
+pub mod light;
+pub mod movement;
+pub mod pathfinding;
+pub mod player;
+pub mod projectile;
+pub mod random;
+pub mod replay;
+pub mod save;
+pub mod script;
+pub mod system;
+pub mod transition;
+pub mod weather;
 
 pub struct Extract {
     pub matrix: Mat4,
     pub bases: Vec<base::Base>,
+    /// ベース毎の地面が濡れているか (`bases`と同じ順序)
+    pub base_wets: Vec<bool>,
     pub blocks: Vec<block::Block>,
     pub entities: Vec<entity::Entity>,
+    /// エンティティ毎のダメージを受けた時の点滅の強さ (`entities`と同じ順序)
+    pub entity_flashes: Vec<f32>,
+    /// 時刻に応じた環境光の色
+    pub ambient: Vec3,
+    /// 描写範囲を照らす光源
+    pub lights: Vec<light::Light>,
+    /// 光を遮る固体のブロックの範囲
+    pub occluders: Vec<IAabb2>,
+    /// カメラの位置の天候
+    pub weather: weather::Weather,
+    /// ワールドの経過時間 (秒)
+    pub time: f64,
+    /// デバッグ表示する範囲と色
+    pub debug_rects: Vec<(Aabb2, Vec4)>,
+    /// デバッグ表示する文字列
+    pub debug_lines: Vec<String>,
+    /// 開発者コンソールの表示 (閉じている場合は`None`)
+    pub console: Option<console::ConsoleView>,
+    /// プレイヤーのホットバー (プレイヤーが居ない場合は`None`)
+    pub hotbar: Option<player::HotbarView>,
+    pub base_changes: Vec<journal::Change<base::Base>>,
+    pub block_changes: Vec<journal::Change<block::Block>>,
+    pub entity_changes: Vec<journal::Change<entity::Entity>>,
 }
 
 pub struct GameLoop {
+    assets: std::rc::Rc<assets::Assets>,
+    accumulator: std::time::Duration,
+    input: input::InputState,
+    previous_positions: ahash::HashMap<usize, Vec2>,
+    previous_camera_position: Vec2,
+    rng: random::SplitMix64,
+    camera_state: camera::CameraState,
+    player_state: Option<player::PlayerState>,
+    generation_scripts: Vec<generation::GenerationScript>,
     base_storage: base::BaseStorage,
     block_storage: block::BlockStorage,
     entity_storage: entity::EntityStorage,
     generation_sys: generation::GenerationSystem,
     camera_sys: camera::CameraSystem,
     player_sys: player::PlayerSystem,
+    movement_sys: movement::MovementSystem,
+    crafting_sys: crafting::CraftingSystem,
+    drop_sys: drop::ItemDropSystem,
+    pathfinding_sys: pathfinding::PathfindingSystem,
+    behaviour_sys: behaviour::BehaviourSystem,
+    combat_sys: combat::CombatSystem,
+    projectile_sys: projectile::ProjectileSystem,
+    transition_sys: transition::TransitionSystem,
+    clock_sys: clock::ClockSystem,
+    light_sys: light::LightSystem,
+    weather_state: weather::WeatherState,
+    weather_sys: weather::WeatherSystem,
+    script_sys: script::ScriptSystem,
+    systems: system::Scheduler,
+    console: console::Console,
+    overlays: ahash::HashSet<debug::Overlay>,
 }
 
 impl GameLoop {
+    /// シミュレーションの1ティックの長さ (60Hz)
+    pub const TICK: std::time::Duration = std::time::Duration::from_nanos(1_000_000_000 / 60);
+
+    /// 1フレームで追いつくために実行するティックの最大数
+    ///
+    /// これを超える遅れは破棄され、シミュレーションは減速する。
+    const MAX_CATCH_UP: u32 = 8;
+
     /// 新しいゲームループを作成する。
     #[inline]
-    pub fn new(assets: std::rc::Rc<assets::Assets>) -> Self {
-        Self {
+    pub fn new(assets: std::rc::Rc<assets::Assets>, seed: u64) -> Self {
+        // NOTE: ブロックの変更で経路探索のキャッシュを破棄し、遷移の経過時間を初期化する。
+        let pathfinding_sys = pathfinding::PathfindingSystem::new(assets.clone());
+        let transition_sys = transition::TransitionSystem::new(assets.clone());
+        let mut block_storage = block::BlockStorage::new(assets.clone());
+        block_storage
+            .journal_mut()
+            .observe(pathfinding_sys.observer());
+        block_storage
+            .journal_mut()
+            .observe(transition_sys.observer());
+
+        // NOTE: ブロックとエンティティの追加・削除をスクリプトへ通知する。
+        let script_sys = script::ScriptSystem::new(assets.clone());
+        block_storage
+            .journal_mut()
+            .observe(script_sys.block_observer());
+        let mut entity_storage = entity::EntityStorage::new(assets.clone());
+        entity_storage
+            .journal_mut()
+            .observe(script_sys.entity_observer());
+
+        let mut game_loop = Self {
+            assets: assets.clone(),
+            accumulator: Default::default(),
+            input: Default::default(),
+            previous_positions: Default::default(),
+            previous_camera_position: Default::default(),
+            rng: random::SplitMix64::new(seed),
+            camera_state: camera::CameraSystem::initial_state(),
+            player_state: None,
+            generation_scripts: vec![],
             base_storage: base::BaseStorage::new(assets.clone()),
-            block_storage: block::BlockStorage::new(assets.clone()),
-            entity_storage: entity::EntityStorage::new(assets.clone()),
-            generation_sys: generation::GenerationSystem::new(assets.clone()),
+            block_storage,
+            entity_storage,
+            generation_sys: generation::GenerationSystem::new(assets.clone(), seed),
             camera_sys: camera::CameraSystem::new(assets.clone()),
             player_sys: player::PlayerSystem::new(assets.clone()),
+            movement_sys: Default::default(),
+            crafting_sys: crafting::CraftingSystem::new(assets.clone()),
+            drop_sys: drop::ItemDropSystem::new(assets.clone()),
+            pathfinding_sys,
+            behaviour_sys: behaviour::BehaviourSystem::new(assets.clone()),
+            combat_sys: combat::CombatSystem::new(assets.clone()),
+            projectile_sys: projectile::ProjectileSystem::new(assets.clone()),
+            transition_sys,
+            clock_sys: clock::ClockSystem::new(assets.clone()),
+            light_sys: light::LightSystem::new(assets.clone()),
+            weather_state: weather::WeatherState::new(assets.clone(), seed),
+            weather_sys: Default::default(),
+            script_sys,
+            systems: Default::default(),
+            console: Default::default(),
+            overlays: Default::default(),
+        };
+
+        // NOTE: 開発用のコマンドもシステムとして登録する。
+        game_loop.add_system(debug::DebugSystem::new(assets));
+        game_loop
+    }
+
+    /// 経過時間に応じて固定長のティックを実行する。
+    ///
+    /// 端数は次のフレームへ持ち越され、抽出時の補間に使用される。
+    /// フレーム単位の入力は最初に実行されるティックへ渡される。
+    pub fn update(&mut self, input: &input::InputState, elapsed: &std::time::Duration) {
+        self.input.merge(input);
+        self.accumulator += *elapsed;
+
+        let mut count = 0;
+        while self.accumulator >= Self::TICK {
+            if count == Self::MAX_CATCH_UP {
+                // NOTE: 追いつけない遅れは破棄する。
+                self.accumulator = std::time::Duration::ZERO;
+                break;
+            }
+
+            let input = self.input.clone();
+            self.tick(&input, &Self::TICK);
+            self.input.advance();
+
+            self.accumulator -= Self::TICK;
+            count += 1;
         }
     }
 
-    /// ゲームループを実行する。
-    pub fn update(
+    /// 1ティック分のゲームサイクルを実行する。
+    fn tick(&mut self, input: &input::InputState, tick: &std::time::Duration) {
+        // NOTE: 補間のためにティック開始時の状態を記録する。
+        let journal_offset = self.entity_storage.journal().len();
+        self.previous_camera_position = self.camera_state.position;
+
+        // NOTE: ワールドの時間を進める。
+        self.clock_sys.update(tick);
+
+        system::Phase::ALL.into_iter().for_each(|phase| {
+            self.run_phase(phase, input, *tick);
+        });
+
+        // NOTE: このティックで移動したエンティティの移動前の位置
+        self.previous_positions.clear();
+        self.entity_storage
+            .journal()
+            .iter()
+            .skip(journal_offset)
+            .for_each(|change| {
+                if let journal::Change::Modify { id, old, .. } = change {
+                    self.previous_positions.entry(*id).or_insert(old.position);
+                }
+            });
+    }
+
+    /// 指定した段階の組み込みのシステムと登録されたシステムを実行する。
+    fn run_phase(
         &mut self,
-        input: &winit_input_helper::WinitInputHelper,
-        tick: &std::time::Duration,
-        window_size: (u32, u32),
+        phase: system::Phase,
+        input: &input::InputState,
+        tick: std::time::Duration,
     ) {
-        self.player_sys.update(
-            input,
+        let mut context = system::WorldContext {
+            assets: &self.assets,
             tick,
-            window_size,
-            &mut self.base_storage,
-            &mut self.block_storage,
-            &mut self.entity_storage,
-            &self.camera_sys,
-        );
+            input,
+            rng: &mut self.rng,
+            clock_sys: &mut self.clock_sys,
+            weather_state: &mut self.weather_state,
+            pathfinding_sys: &mut self.pathfinding_sys,
+            camera_state: &mut self.camera_state,
+            base_storage: &mut self.base_storage,
+            block_storage: &mut self.block_storage,
+            entity_storage: &mut self.entity_storage,
+            player_state: &mut self.player_state,
+            generation_scripts: &mut self.generation_scripts,
+        };
+
+        // NOTE: 組み込みのシステムは同じ段階の中でこの順に実行する。
+        let builtins: [&mut dyn system::System; 11] = [
+            &mut self.player_sys,
+            &mut self.behaviour_sys,
+            &mut self.script_sys,
+            &mut self.weather_sys,
+            &mut self.movement_sys,
+            &mut self.projectile_sys,
+            &mut self.combat_sys,
+            &mut self.drop_sys,
+            &mut self.camera_sys,
+            &mut self.generation_sys,
+            &mut self.transition_sys,
+        ];
+        builtins
+            .into_iter()
+            .filter(|system| system.phase() == phase)
+            .for_each(|system| system.update(&mut context));
 
-        // NOTE: カメラの操作
-        if let Some(player) = self.player_sys.get_player() {
-            self.camera_sys
-                .follow_entity(input, tick, &self.entity_storage, player.entity_id);
+        self.systems.run(phase, &mut context);
+    }
+
+    /// システムを登録し、識別子を返す。
+    ///
+    /// システムは宣言した段階で、同じ段階の他のシステムより後に実行される。
+    #[inline]
+    pub fn add_system(&mut self, system: impl system::System + 'static) -> usize {
+        self.systems.insert(Box::new(system))
+    }
+
+    /// システムの登録を解除する。
+    #[inline]
+    pub fn remove_system(&mut self, id: usize) -> bool {
+        self.systems.remove(id).is_some()
+    }
+
+    /// 開発者コンソールの状態を返す。
+    #[inline]
+    pub fn console(&self) -> &console::Console {
+        &self.console
+    }
+
+    /// 開発者コンソールの状態を返す。
+    #[inline]
+    pub fn console_mut(&mut self) -> &mut console::Console {
+        &mut self.console
+    }
+
+    /// コンソールの入力中の行を実行し、結果を出力する。
+    pub fn console_submit(&mut self) {
+        let line = self.console.take_line();
+        match self.execute(&line) {
+            Ok(output) => self.console.print(&output),
+            Err(e) => self.console.print(&e.to_string()),
         }
 
-        // NOTE: 地形の自動生成
-        let rect = self.camera_sys.get().clipping();
-        self.generation_sys.generate(
-            &mut self.base_storage,
-            &mut self.block_storage,
-            &mut self.entity_storage,
-            rect,
+        // NOTE: コマンドによる移動は補間しない。
+        self.previous_positions.clear();
+        self.previous_camera_position = self.camera_state.position;
+    }
+
+    /// コンソールの入力中の行を補完する。
+    ///
+    /// 候補が複数ある場合は候補を出力する。
+    pub fn console_complete(&mut self) {
+        let command_specs = self.command_specs();
+        let (line, candidates) =
+            console::complete(&self.assets, &command_specs, self.console.line());
+        if !candidates.is_empty() {
+            self.console.print(&candidates.join(" "));
+        }
+        self.console.set_line(line);
+    }
+
+    /// 実行できる全てのコマンドの仕様を返す。
+    ///
+    /// ゲームループ・登録されたシステム・スクリプトの順に並ぶ。
+    pub fn command_specs(&self) -> Vec<console::CommandSpec> {
+        let script_command_specs = self
+            .script_sys
+            .commands()
+            .into_iter()
+            .map(|name| console::CommandSpec::new(&name, "script command"));
+        Self::core_command_specs()
+            .into_iter()
+            .chain(self.systems.commands())
+            .chain(script_command_specs)
+            .collect()
+    }
+
+    /// ゲームループが提供するコマンドの仕様を返す。
+    fn core_command_specs() -> Vec<console::CommandSpec> {
+        let overlays = debug::Overlay::ALL
+            .iter()
+            .map(|(name, _)| name.to_string())
+            .collect();
+        vec![
+            console::CommandSpec::new("help", "list commands"),
+            console::CommandSpec::new("seed", "change the world seed and regenerate the world")
+                .optional("seed", console::ArgKind::Int),
+            console::CommandSpec::new("overlay", "toggle a debug overlay")
+                .arg("name", console::ArgKind::Choice(overlays)),
+            console::CommandSpec::new("reload", "reload the scripts"),
+            console::CommandSpec::new("craft", "list craftable recipes or craft one")
+                .optional("recipe", console::ArgKind::RecipeSpec),
+        ]
+    }
+
+    /// コマンドを実行し、結果を文字列で返す。
+    ///
+    /// ゲームループ・登録されたシステム・スクリプトの順にコマンドを探す。
+    pub fn execute(&mut self, line: &str) -> Result<String, console::CommandError> {
+        let tokens = line.split_whitespace().collect::<Vec<_>>();
+        let Some((name, tokens)) = tokens.split_first() else {
+            return Ok(String::new());
+        };
+
+        if let Some(command_spec) = Self::core_command_specs()
+            .into_iter()
+            .find(|spec| spec.name == *name)
+        {
+            let args = command_spec.parse(&self.assets, tokens)?;
+            return match *name {
+                "help" => Ok(self
+                    .command_specs()
+                    .iter()
+                    .map(|spec| format!("{} - {}", spec.usage(), spec.description))
+                    .collect::<Vec<_>>()
+                    .join("\n")),
+                "seed" => {
+                    let seed = args.int(0).map_or_else(rand::random, |seed| seed as u64);
+                    self.regenerate(seed);
+                    Ok(format!("regenerated with seed {}", seed))
+                }
+                "overlay" => {
+                    let Some(name) = args.text(0) else {
+                        return Err(console::CommandError::Usage(command_spec.usage()));
+                    };
+                    let Some(overlay) = debug::Overlay::parse(name) else {
+                        return Err(console::CommandError::Failed(format!(
+                            "unknown overlay: {}",
+                            name
+                        )));
+                    };
+                    let enabled = self.overlays.insert(overlay);
+                    if !enabled {
+                        self.overlays.remove(&overlay);
+                    }
+                    let state = if enabled { "on" } else { "off" };
+                    Ok(format!("{} {}", name, state))
+                }
+                "reload" => {
+                    self.script_sys.reload();
+                    Ok(format!(
+                        "reloaded {} scripts",
+                        self.assets.script_paths.len()
+                    ))
+                }
+                "craft" => {
+                    let Some(recipe_spec_id) = args.spec(0) else {
+                        return Ok(self
+                            .craftable_recipes()
+                            .into_iter()
+                            .map(|id| self.assets.recipe_specs[id].label.clone())
+                            .collect::<Vec<_>>()
+                            .join(" "));
+                    };
+                    self.craft(recipe_spec_id)
+                        .map_err(|e| console::CommandError::Failed(e.to_string()))?;
+                    let recipe_spec = &self.assets.recipe_specs[recipe_spec_id];
+                    Ok(format!("crafted {}", recipe_spec.label))
+                }
+                _ => unreachable!(),
+            };
+        }
+
+        let mut context = system::WorldContext {
+            assets: &self.assets,
+            tick: std::time::Duration::ZERO,
+            input: &self.input,
+            rng: &mut self.rng,
+            clock_sys: &mut self.clock_sys,
+            weather_state: &mut self.weather_state,
+            pathfinding_sys: &mut self.pathfinding_sys,
+            camera_state: &mut self.camera_state,
+            base_storage: &mut self.base_storage,
+            block_storage: &mut self.block_storage,
+            entity_storage: &mut self.entity_storage,
+            player_state: &mut self.player_state,
+            generation_scripts: &mut self.generation_scripts,
+        };
+        if let Some(result) = self.systems.command(name, tokens, &mut context) {
+            return result;
+        }
+
+        let args = tokens.iter().map(|token| token.to_string()).collect();
+        match self.script_sys.command(&mut context, name, args) {
+            Some(result) => result.map_err(|e| console::CommandError::Failed(e.to_string())),
+            None => Err(console::CommandError::Unknown(name.to_string())),
+        }
+    }
+
+    /// シード値を置き換え、プレイヤー以外のワールドを作り直す。
+    ///
+    /// ワールドは次のティックでカメラの周囲から生成される。
+    pub fn regenerate(&mut self, seed: u64) {
+        let player_entity_id = self.player_state.as_ref().map(|player| player.entity_id);
+
+        self.base_storage.restore(std::iter::empty());
+        self.block_storage.restore(std::iter::empty());
+        let entity_ids = self
+            .entity_storage
+            .iter()
+            .map(|(id, _)| id)
+            .filter(|id| Some(*id) != player_entity_id)
+            .collect::<Vec<_>>();
+        entity_ids.into_iter().for_each(|id| {
+            self.entity_storage.remove(id);
+        });
+
+        // NOTE: シード値を持つ全ての状態を新しいシード値で初期化する。
+        self.generation_sys.restore(seed, std::iter::empty());
+        self.weather_state.restore(seed);
+        self.rng = random::SplitMix64::new(seed);
+        self.transition_sys
+            .restore(std::iter::empty(), std::iter::empty());
+        self.pathfinding_sys.clear();
+    }
+
+    /// ビューポート上の位置をワールド上の位置へ変換する。
+    pub fn viewport_to_world(&self, position: Vec2, window_size: (u32, u32)) -> Vec2 {
+        let matrix = self.camera_state.world_to_viewport(window_size).inverse();
+        matrix.project_point3(position.extend(0.0)).xy()
+    }
+
+    /// 現在の時刻 (`0.0`から`1.0`、`0.5`が正午) を返す。
+    #[inline]
+    pub fn time_of_day(&self) -> f32 {
+        self.clock_sys.time_of_day()
+    }
+
+    /// 指定したエンティティが通る経路を探索し、通過点の位置の列を返す。
+    ///
+    /// 通過点はエンティティの位置 (内部の大きさの左下) として返される。
+    pub fn find_path(&mut self, entity_id: usize, goal: Vec2) -> Option<Vec<Vec2>> {
+        let entity = self.entity_storage.get(entity_id)?;
+        let entity_spec = &self.assets.entity_specs[entity.spec_id];
+        let footprint = self.pathfinding_sys.footprint(entity_spec);
+        let start = entity.position.round().as_ivec2();
+        let goal = goal.floor().as_ivec2();
+        let path = self
+            .pathfinding_sys
+            .find_path(&self.block_storage, start, goal, footprint)?;
+        Some(path.into_iter().map(|point| point.as_vec2()).collect())
+    }
+
+    /// プレイヤーの近くに存在するブロックの仕様の識別子を返す。
+    fn player_nearby_block_spec_ids(&self) -> Option<ahash::HashSet<usize>> {
+        let player = self.player_state.as_ref()?;
+        let entity = self.entity_storage.get(player.entity_id)?;
+        let entity_spec = &self.assets.entity_specs[entity.spec_id];
+        let center = entity.position + entity_spec.internal_size * 0.5;
+        Some(
+            self.crafting_sys
+                .nearby_block_spec_ids(&self.block_storage, center),
+        )
+    }
+
+    /// プレイヤーが実行可能な全てのレシピの仕様の識別子を返す。
+    pub fn craftable_recipes(&self) -> Vec<usize> {
+        let Some(nearby_block_spec_ids) = self.player_nearby_block_spec_ids() else {
+            return vec![];
+        };
+        let player = self.player_state.as_ref().unwrap();
+        self.crafting_sys
+            .craftable(&player.inventory, &nearby_block_spec_ids)
+            .collect()
+    }
+
+    /// プレイヤーのインベントリでレシピを実行する。
+    pub fn craft(&mut self, recipe_spec_id: usize) -> Result<(), crafting::CraftError> {
+        let nearby_block_spec_ids = self
+            .player_nearby_block_spec_ids()
+            .ok_or(crafting::CraftError::Player)?;
+        let player = self.player_state.as_mut().unwrap();
+        self.crafting_sys.craft(
+            &mut player.inventory,
+            &nearby_block_spec_ids,
+            recipe_spec_id,
+        )
+    }
+
+    /// スクリプトで発生したエラーを取り出す。
+    #[inline]
+    pub fn drain_script_errors(&mut self) -> Vec<script::ScriptError> {
+        self.script_sys.drain_errors().collect()
+    }
+
+    /// スクリプトが登録したコマンドの名前を返す。
+    #[inline]
+    pub fn script_commands(&self) -> Vec<String> {
+        self.script_sys.commands()
+    }
+
+    /// ワールドの状態のハッシュ値を返す。
+    ///
+    /// セーブデータと同じ全ての状態を符号化してハッシュ値を計算するため、
+    /// 同じシード値と入力から再現された状態は同じハッシュ値となる。
+    pub fn state_hash(&self) -> u64 {
+        // NOTE: 実行毎・環境毎に変化しないハッシュ関数を使用する。
+        let bytes = bincode::serialize(&self.save_data()).unwrap();
+        random::fnv1a(&bytes)
+    }
+
+    /// ワールドの全ての状態をファイルへ保存する。
+    pub fn save<P: AsRef<std::path::Path>>(&self, path: P) -> Result<(), save::SaveError> {
+        let writer = std::io::BufWriter::new(std::fs::File::create(path)?);
+        save::write(writer, &self.save_data())
+    }
+
+    /// ワールドの全ての状態をセーブデータへ変換する。
+    ///
+    /// 順序を持たない集合は並べ替えるため、同じ状態は同じセーブデータとなる。
+    fn save_data(&self) -> save::SaveData {
+        let base_spec_labels = self
+            .assets
+            .base_specs
+            .iter()
+            .map(|spec| spec.label.clone())
+            .collect();
+        let block_spec_labels = self
+            .assets
+            .block_specs
+            .iter()
+            .map(|spec| spec.label.clone())
+            .collect();
+        let entity_spec_labels = self
+            .assets
+            .entity_specs
+            .iter()
+            .map(|spec| spec.label.clone())
+            .collect();
+        let item_spec_labels = self
+            .assets
+            .item_specs
+            .iter()
+            .map(|spec| spec.label.clone())
+            .collect();
+        let behaviour_spec_labels = self
+            .assets
+            .behaviour_specs
+            .iter()
+            .map(|spec| spec.label.clone())
+            .collect();
+        let behaviour_state_labels = self
+            .assets
+            .behaviour_specs
+            .iter()
+            .map(|spec| {
+                spec.states
+                    .iter()
+                    .map(|state| state.label.clone())
+                    .collect()
+            })
+            .collect();
+        let projectile_spec_labels = self
+            .assets
+            .projectile_specs
+            .iter()
+            .map(|spec| spec.label.clone())
+            .collect();
+
+        let mut grid_flags = self
+            .generation_sys
+            .grid_flags()
+            .iter()
+            .map(|grid_point| grid_point.to_array())
+            .collect::<Vec<_>>();
+        grid_flags.sort_unstable();
+
+        let mut chunk_times = self
+            .transition_sys
+            .chunks()
+            .iter()
+            .map(|(chunk_point, time)| (chunk_point.to_array(), *time))
+            .collect::<Vec<_>>();
+        chunk_times.sort_unstable_by_key(|(chunk_point, _)| *chunk_point);
+
+        let base_chunks = self
+            .base_storage
+            .iter_chunks()
+            .map(|(id, grid_point, chunk)| save::BaseChunkData {
+                id,
+                grid_point: grid_point.to_array(),
+                bases: chunk
+                    .iter()
+                    .map(|(local, _, spec_id)| save::BaseData {
+                        spec_label_id: spec_id,
+                        local: local.to_array(),
+                    })
+                    .collect(),
+            })
+            .collect();
+
+        let blocks = self
+            .block_storage
+            .iter()
+            .map(|(id, block)| save::BlockData {
+                id,
+                spec_label_id: block.spec_id,
+                position: block.position.to_array(),
+                z_random: block.z_random,
+                state: block.state,
+                age: self.transition_sys.age(id),
+            })
+            .collect();
+
+        let components = self.entity_storage.components();
+        let entities = self
+            .entity_storage
+            .iter()
+            .map(|(id, entity)| save::EntityData {
+                id,
+                spec_label_id: entity.spec_id,
+                position: entity.position.to_array(),
+                player_controlled: components.get::<component::PlayerControlled>(id).is_some(),
+                velocity: components
+                    .get::<component::Velocity>(id)
+                    .map(|velocity| velocity.0.to_array()),
+                damping: components
+                    .get::<component::Damping>(id)
+                    .map(|damping| damping.0),
+                item_drop: components.get::<component::ItemDrop>(id).map(|item_drop| {
+                    save::ItemDropData {
+                        stack: save::ItemStackData {
+                            spec_label_id: item_drop.stack.item_spec_id,
+                            count: item_drop.stack.count,
+                        },
+                        remaining: item_drop.remaining,
+                    }
+                }),
+                facing: components
+                    .get::<component::Facing>(id)
+                    .map(|facing| facing.0.to_array()),
+                knockback: components
+                    .get::<component::Knockback>(id)
+                    .map(|knockback| knockback.0.to_array()),
+                health: components
+                    .get::<component::Health>(id)
+                    .map(|health| save::HealthData {
+                        current: health.current,
+                        max: health.max,
+                        invulnerable: health.invulnerable,
+                        flash: health.flash,
+                    }),
+                behaviour: components.get::<component::Behaviour>(id).map(|behaviour| {
+                    save::BehaviourData {
+                        spec_label_id: behaviour.behaviour_spec_id,
+                        state_label_id: behaviour.state_id,
+                        elapsed: behaviour.elapsed,
+                        started: behaviour.started,
+                        done: behaviour.done,
+                        target_block: behaviour.target_block,
+                        timer: behaviour.timer,
+                        origin: behaviour.origin.to_array(),
+                        patrol_index: behaviour.patrol_index,
+                    }
+                }),
+                projectile: components
+                    .get::<component::Projectile>(id)
+                    .map(|projectile| save::ProjectileData {
+                        spec_label_id: projectile.projectile_spec_id,
+                        owner: projectile.owner,
+                        velocity: projectile.velocity.to_array(),
+                        remaining: projectile.remaining,
+                        stuck: projectile.stuck,
+                        attached: projectile
+                            .attached
+                            .map(|(target_id, offset)| (target_id, offset.to_array())),
+                    }),
+                path_follow: components
+                    .get::<component::PathFollow>(id)
+                    .map(|path_follow| save::PathFollowData {
+                        waypoints: path_follow
+                            .waypoints
+                            .iter()
+                            .map(|waypoint| waypoint.to_array())
+                            .collect(),
+                        speed: path_follow.speed,
+                    }),
+            })
+            .collect();
+
+        let player = self.player_state.as_ref().map(|player| save::PlayerData {
+            slots: player
+                .inventory
+                .slots()
+                .iter()
+                .map(|slot| {
+                    slot.map(|stack| save::ItemStackData {
+                        spec_label_id: stack.item_spec_id,
+                        count: stack.count,
+                    })
+                })
+                .collect(),
+            selected_slot: player.selected_slot,
+        });
+
+        let camera = save::CameraData {
+            position: self.camera_state.position.to_array(),
+            zoom: self.camera_state.zoom,
+        };
+
+        save::SaveData {
+            seed: self.generation_sys.seed(),
+            rng: self.rng.state(),
+            grid_flags,
+            time: self.clock_sys.time(),
+            chunk_times,
+            base_spec_labels,
+            block_spec_labels,
+            entity_spec_labels,
+            item_spec_labels,
+            behaviour_spec_labels,
+            behaviour_state_labels,
+            projectile_spec_labels,
+            base_chunks,
+            blocks,
+            entities,
+            player,
+            camera,
+        }
+    }
+
+    /// ファイルからワールドの全ての状態を復元する。
+    ///
+    /// 読み込みに失敗した場合、ワールドは変更されない。
+    pub fn load<P: AsRef<std::path::Path>>(&mut self, path: P) -> Result<(), save::SaveError> {
+        let reader = std::io::BufReader::new(std::fs::File::open(path)?);
+        let save_data = save::read(reader)?;
+
+        let base_labels = save::LabelTable::new(
+            &save_data.base_spec_labels,
+            self.assets
+                .base_specs
+                .iter()
+                .map(|spec| spec.label.as_str()),
+        );
+        let block_labels = save::LabelTable::new(
+            &save_data.block_spec_labels,
+            self.assets
+                .block_specs
+                .iter()
+                .map(|spec| spec.label.as_str()),
         );
+        let entity_labels = save::LabelTable::new(
+            &save_data.entity_spec_labels,
+            self.assets
+                .entity_specs
+                .iter()
+                .map(|spec| spec.label.as_str()),
+        );
+        let item_labels = save::LabelTable::new(
+            &save_data.item_spec_labels,
+            self.assets
+                .item_specs
+                .iter()
+                .map(|spec| spec.label.as_str()),
+        );
+        let behaviour_labels = save::LabelTable::new(
+            &save_data.behaviour_spec_labels,
+            self.assets
+                .behaviour_specs
+                .iter()
+                .map(|spec| spec.label.as_str()),
+        );
+        let projectile_labels = save::LabelTable::new(
+            &save_data.projectile_spec_labels,
+            self.assets
+                .projectile_specs
+                .iter()
+                .map(|spec| spec.label.as_str()),
+        );
+
+        // NOTE: 状態を変更する前に全ての識別子と位置を検証する。
+        let chunk_rect = iaabb2(IVec2::ZERO, IVec2::splat(base::CHUNK_SIZE));
+        let base_chunks = save_data
+            .base_chunks
+            .iter()
+            .map(|data| {
+                let mut chunk = base::BaseChunk::default();
+                for base_data in &data.bases {
+                    let spec_id = base_labels.spec_id(base_data.spec_label_id)?;
+                    let layer = self.assets.base_specs[spec_id].layer;
+                    let local = IVec2::from_array(base_data.local);
+                    if !chunk_rect.contains_point(local) || !chunk.insert(local, layer, spec_id) {
+                        return Err(save::SaveError::Corrupt(format!(
+                            "invalid base position {:?} in chunk #{}",
+                            base_data.local, data.id
+                        )));
+                    }
+                }
+                Ok((data.id, IVec2::from_array(data.grid_point), chunk))
+            })
+            .collect::<Result<Vec<_>, save::SaveError>>()?;
+        let mut block_ids = ahash::HashSet::default();
+        if !save_data
+            .blocks
+            .iter()
+            .all(|data| block_ids.insert(data.id))
+        {
+            return Err(save::SaveError::Corrupt("duplicate block ids".to_string()));
+        }
+        let mut entity_ids = ahash::HashSet::default();
+        if !save_data
+            .entities
+            .iter()
+            .all(|data| entity_ids.insert(data.id))
+        {
+            return Err(save::SaveError::Corrupt("duplicate entity ids".to_string()));
+        }
+
+        let ages = save_data
+            .blocks
+            .iter()
+            .filter_map(|data| Some((data.id, data.age?)))
+            .collect::<Vec<_>>();
+
+        let blocks = save_data
+            .blocks
+            .into_iter()
+            .map(|data| {
+                let spec_id = block_labels.spec_id(data.spec_label_id)?;
+                let block = block::Block::new(
+                    spec_id,
+                    IVec2::from_array(data.position),
+                    data.z_random,
+                    data.state,
+                );
+                Ok((data.id, block))
+            })
+            .collect::<Result<Vec<_>, save::SaveError>>()?;
+
+        let entities = save_data
+            .entities
+            .iter()
+            .map(|data| {
+                let spec_id = entity_labels.spec_id(data.spec_label_id)?;
+                let entity = entity::Entity::new(spec_id, Vec2::from_array(data.position));
+                Ok((data.id, entity))
+            })
+            .collect::<Result<Vec<_>, save::SaveError>>()?;
+
+        let item_drops = save_data
+            .entities
+            .iter()
+            .filter_map(|data| Some((data.id, data.item_drop.as_ref()?)))
+            .map(|(id, data)| {
+                let item_spec_id = item_labels.spec_id(data.stack.spec_label_id)?;
+                let item_drop = component::ItemDrop {
+                    stack: inventory::ItemStack::new(item_spec_id, data.stack.count),
+                    remaining: data.remaining,
+                };
+                Ok((id, item_drop))
+            })
+            .collect::<Result<Vec<_>, save::SaveError>>()?;
+
+        let projectiles = save_data
+            .entities
+            .iter()
+            .filter_map(|data| Some((data.id, data.projectile.as_ref()?)))
+            .map(|(id, data)| {
+                let projectile_spec_id = projectile_labels.spec_id(data.spec_label_id)?;
+                let projectile = component::Projectile {
+                    projectile_spec_id,
+                    owner: data.owner,
+                    velocity: Vec2::from_array(data.velocity),
+                    remaining: data.remaining,
+                    stuck: data.stuck,
+                    attached: data
+                        .attached
+                        .map(|(target_id, offset)| (target_id, Vec2::from_array(offset))),
+                };
+                Ok((id, projectile))
+            })
+            .collect::<Result<Vec<_>, save::SaveError>>()?;
+
+        // NOTE: 現在の仕様に存在しない状態は初期状態から再開する。
+        let behaviours = save_data
+            .entities
+            .iter()
+            .filter_map(|data| Some((data.id, data.behaviour.as_ref()?)))
+            .map(|(id, data)| {
+                let behaviour_spec_id = behaviour_labels.spec_id(data.spec_label_id)?;
+                let behaviour_spec = &self.assets.behaviour_specs[behaviour_spec_id];
+                let state_labels = save_data
+                    .behaviour_state_labels
+                    .get(data.spec_label_id)
+                    .map_or(&[][..], |labels| labels.as_slice());
+                let state_labels = save::LabelTable::new(
+                    state_labels,
+                    behaviour_spec
+                        .states
+                        .iter()
+                        .map(|state| state.label.as_str()),
+                );
+
+                let mut behaviour =
+                    component::Behaviour::new(behaviour_spec, Vec2::from_array(data.origin));
+                if let Ok(state_id) = state_labels.spec_id(data.state_label_id) {
+                    behaviour.state_id = state_id;
+                    behaviour.elapsed = data.elapsed;
+                    behaviour.started = data.started;
+                    behaviour.done = data.done;
+                    behaviour.target_block = data.target_block;
+                    behaviour.timer = data.timer;
+                }
+                behaviour.patrol_index = data.patrol_index;
+                Ok((id, behaviour))
+            })
+            .collect::<Result<Vec<_>, save::SaveError>>()?;
+
+        let player = save_data
+            .player
+            .as_ref()
+            .map(|data| {
+                let slots = data
+                    .slots
+                    .iter()
+                    .map(|slot| {
+                        slot.as_ref()
+                            .map(|stack| {
+                                let item_spec_id = item_labels.spec_id(stack.spec_label_id)?;
+                                Ok(inventory::ItemStack::new(item_spec_id, stack.count))
+                            })
+                            .transpose()
+                    })
+                    .collect::<Result<Vec<_>, save::SaveError>>()?;
+                if data.selected_slot >= inventory::Inventory::HOTBAR_SIZE.min(slots.len()) {
+                    return Err(save::SaveError::Corrupt(format!(
+                        "invalid selected slot: {}",
+                        data.selected_slot
+                    )));
+                }
+                Ok((slots, data.selected_slot))
+            })
+            .transpose()?;
+
+        // NOTE: ベースの復元が最初の変更であり、失敗した場合は何も変更されていない。
+        if !self.base_storage.restore(base_chunks) {
+            return Err(save::SaveError::Corrupt(
+                "invalid base chunk ids".to_string(),
+            ));
+        }
+        self.block_storage.restore(blocks);
+        self.entity_storage.restore(entities);
+
+        let components = self.entity_storage.components_mut();
+        for data in &save_data.entities {
+            if data.player_controlled {
+                components.insert(data.id, component::PlayerControlled);
+            }
+            if let Some(velocity) = data.velocity {
+                components.insert(data.id, component::Velocity(Vec2::from_array(velocity)));
+            }
+            if let Some(damping) = data.damping {
+                components.insert(data.id, component::Damping(damping));
+            }
+            if let Some(facing) = data.facing {
+                components.insert(data.id, component::Facing(Vec2::from_array(facing)));
+            }
+            if let Some(knockback) = data.knockback {
+                components.insert(data.id, component::Knockback(Vec2::from_array(knockback)));
+            }
+            if let Some(health) = &data.health {
+                let mut component = component::Health::new(health.max);
+                component.current = health.current;
+                component.invulnerable = health.invulnerable;
+                component.flash = health.flash;
+                components.insert(data.id, component);
+            }
+            if let Some(path_follow) = &data.path_follow {
+                let path_follow = component::PathFollow {
+                    waypoints: path_follow
+                        .waypoints
+                        .iter()
+                        .map(|waypoint| Vec2::from_array(*waypoint))
+                        .collect(),
+                    speed: path_follow.speed,
+                };
+                components.insert(data.id, path_follow);
+            }
+        }
+        for (id, item_drop) in item_drops {
+            components.insert(id, item_drop);
+        }
+        for (id, behaviour) in behaviours {
+            components.insert(id, behaviour);
+        }
+        for (id, projectile) in projectiles {
+            components.insert(id, projectile);
+        }
+
+        let grid_flags = save_data
+            .grid_flags
+            .iter()
+            .map(|grid_point| IVec2::from_array(*grid_point));
+        self.generation_sys.restore(save_data.seed, grid_flags);
+        self.weather_state.restore(save_data.seed);
+        self.rng = random::SplitMix64::new(save_data.rng);
+
+        let chunk_times = save_data
+            .chunk_times
+            .iter()
+            .map(|(chunk_point, time)| (IVec2::from_array(*chunk_point), *time));
+        self.clock_sys.restore(save_data.time);
+        self.transition_sys.restore(chunk_times, ages);
+
+        // NOTE: プレイヤーが操作するエンティティにインベントリを戻す。
+        let player_state = self
+            .entity_storage
+            .components()
+            .iter_with::<(component::PlayerControlled,)>()
+            .next()
+            .zip(player)
+            .map(|(entity_id, (slots, selected_slot))| {
+                let mut player_state = player::PlayerState::new(entity_id);
+                player_state.inventory = inventory::Inventory::from_slots(slots);
+                player_state.selected_slot = selected_slot;
+                player_state
+            });
+        self.player_state = player_state;
+
+        self.pathfinding_sys.clear();
+        self.accumulator = std::time::Duration::ZERO;
+        self.input = Default::default();
+        self.previous_positions.clear();
+        self.previous_camera_position = Vec2::from_array(save_data.camera.position);
+
+        let camera_state = &mut self.camera_state;
+        camera_state.position = Vec2::from_array(save_data.camera.position);
+        camera_state.zoom = save_data.camera.zoom;
+
+        Ok(())
     }
 
-    pub fn extract(&self, window_size: (u32, u32)) -> Extract {
-        let matrix = self.camera_sys.get().world_to_ndc(window_size);
+    /// ベースの変更を監視するコールバックを登録し、識別子を返す。
+    #[inline]
+    pub fn observe_base(
+        &mut self,
+        observer: impl FnMut(&journal::Change<base::Base>) + 'static,
+    ) -> usize {
+        self.base_storage.journal_mut().observe(observer)
+    }
 
-        let rect = self.camera_sys.get().clipping();
+    /// ブロックの変更を監視するコールバックを登録し、識別子を返す。
+    #[inline]
+    pub fn observe_block(
+        &mut self,
+        observer: impl FnMut(&journal::Change<block::Block>) + 'static,
+    ) -> usize {
+        self.block_storage.journal_mut().observe(observer)
+    }
+
+    /// エンティティの変更を監視するコールバックを登録し、識別子を返す。
+    #[inline]
+    pub fn observe_entity(
+        &mut self,
+        observer: impl FnMut(&journal::Change<entity::Entity>) + 'static,
+    ) -> usize {
+        self.entity_storage.journal_mut().observe(observer)
+    }
+
+    /// ベースの変更の監視を解除する。
+    #[inline]
+    pub fn unobserve_base(&mut self, id: usize) -> bool {
+        self.base_storage.journal_mut().unobserve(id)
+    }
+
+    /// ブロックの変更の監視を解除する。
+    #[inline]
+    pub fn unobserve_block(&mut self, id: usize) -> bool {
+        self.block_storage.journal_mut().unobserve(id)
+    }
+
+    /// エンティティの変更の監視を解除する。
+    #[inline]
+    pub fn unobserve_entity(&mut self, id: usize) -> bool {
+        self.entity_storage.journal_mut().unobserve(id)
+    }
+
+    /// 前回の抽出以降に記録された変更履歴を破棄する。
+    ///
+    /// 抽出を行わずにゲームループを実行する場合、変更履歴が蓄積しないよう
+    /// 更新の度に呼び出す。監視者への通知は記録時に済んでいる。
+    pub fn discard_changes(&mut self) {
+        self.base_storage.journal_mut().clear();
+        self.block_storage.journal_mut().clear();
+        self.entity_storage.journal_mut().clear();
+    }
+
+    /// 描写に必要な情報を抽出する。
+    ///
+    /// エンティティとカメラの位置は直前の2ティックの間で補間される。
+    /// 前回の抽出以降に記録された変更履歴はここで取り出される。
+    pub fn extract(&mut self, window_size: (u32, u32)) -> Extract {
+        let alpha = self.accumulator.as_secs_f32() / Self::TICK.as_secs_f32();
+
+        let mut camera_state = self.camera_state.clone();
+        camera_state.position = self
+            .previous_camera_position
+            .lerp(camera_state.position, alpha);
+
+        let matrix = camera_state.world_to_ndc(window_size);
+
+        let rect = camera_state.clipping();
 
         let bases = self
             .base_storage
             .get_rendering_by_rect(rect)
             .map(|(_, item)| item)
-            .cloned()
             .collect::<Vec<_>>();
+        let base_wets = self.weather_state.wets(
+            &self.clock_sys,
+            bases.iter().map(|base| base.position.as_vec2() + 0.5),
+        );
 
         let blocks = self
             .block_storage
@@ -93,18 +1180,255 @@ impl GameLoop {
             .cloned()
             .collect::<Vec<_>>();
 
-        let entities = self
+        let (entities, entity_flashes): (Vec<_>, Vec<_>) = self
             .entity_storage
             .get_rendering_by_rect(rect)
-            .map(|(_, item)| item)
-            .cloned()
-            .collect::<Vec<_>>();
+            .map(|(id, item)| {
+                let mut item = item.clone();
+                if let Some(previous) = self.previous_positions.get(&id) {
+                    item.position = previous.lerp(item.position, alpha);
+                }
+                let flash = self.combat_sys.flash(&self.entity_storage, id);
+                (item, flash)
+            })
+            .unzip();
+
+        let lights = self.light_sys.lights(
+            &self.clock_sys,
+            &self.block_storage,
+            &self.entity_storage,
+            rect,
+            |id, position| match self.previous_positions.get(&id) {
+                Some(previous) => previous.lerp(position, alpha),
+                None => position,
+            },
+        );
+        let occluders = self.light_sys.occluders(&self.block_storage, rect);
+
+        let weather = self
+            .weather_state
+            .weather(&self.clock_sys, camera_state.position);
+
+        // NOTE: デバッグ表示
+        let mut debug_rects = vec![];
+        if self.overlays.contains(&debug::Overlay::Chunks) {
+            let chunk_size = base::CHUNK_SIZE as f32;
+            let grid_rect = rect.to_grid_space(chunk_size);
+            grid_rect.into_iter_points().for_each(|grid_point| {
+                let min = grid_point.as_vec2() * chunk_size;
+                let chunk_rect = aabb2(min, min + chunk_size);
+                debug_rects.push((chunk_rect, vec4(0.2, 1.0, 0.2, 0.8)));
+            });
+        }
+        if self.overlays.contains(&debug::Overlay::Hitboxes) {
+            self.block_storage
+                .get_rendering_by_rect(rect)
+                .for_each(|(_, block)| {
+                    let spec = &self.assets.block_specs[block.spec_id];
+                    let min = block.position.as_vec2();
+                    let block_rect = aabb2(min, min + spec.internal_size.as_vec2());
+                    debug_rects.push((block_rect, vec4(0.3, 0.6, 1.0, 0.8)));
+                });
+            entities.iter().for_each(|entity| {
+                let spec = &self.assets.entity_specs[entity.spec_id];
+                let entity_rect = aabb2(entity.position, entity.position + spec.internal_size);
+                let color = if spec.solid {
+                    vec4(1.0, 0.3, 0.3, 0.9)
+                } else {
+                    vec4(1.0, 1.0, 0.3, 0.9)
+                };
+                debug_rects.push((entity_rect, color));
+            });
+        }
+
+        let mut debug_lines = vec![];
+        if self.overlays.contains(&debug::Overlay::Stats) {
+            let hours = self.clock_sys.time_of_day() * 24.0;
+            debug_lines.push(format!(
+                "position: {:.1} {:.1}, day {} {:02}:{:02}, seed {}",
+                camera_state.position.x,
+                camera_state.position.y,
+                self.clock_sys.day(),
+                hours as u32,
+                (hours.fract() * 60.0) as u32,
+                self.generation_sys.seed(),
+            ));
+            debug_lines.extend(debug::DebugSystem::stats(
+                &self.assets,
+                &self.base_storage,
+                &self.block_storage,
+                &self.entity_storage,
+            ));
+        }
+
+        let console = self.console.is_open().then(|| console::ConsoleView {
+            output: self.console.output().map(ToString::to_string).collect(),
+            line: self.console.line().to_string(),
+        });
+
+        let hotbar = self
+            .player_state
+            .as_ref()
+            .map(player::PlayerState::hotbar_view);
+
+        // NOTE: フレーム毎の変更履歴
+        let base_changes = self.base_storage.journal_mut().drain().collect();
+        let block_changes = self.block_storage.journal_mut().drain().collect();
+        let entity_changes = self.entity_storage.journal_mut().drain().collect();
 
         Extract {
             matrix,
             bases,
+            base_wets,
             blocks,
             entities,
+            entity_flashes,
+            ambient: self.clock_sys.ambient(),
+            lights,
+            occluders,
+            weather,
+            time: self.clock_sys.time(),
+            debug_rects,
+            debug_lines,
+            console,
+            hotbar,
+            base_changes,
+            block_changes,
+            entity_changes,
         }
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn game_loop() -> GameLoop {
+        let assets = assets::Assets::new("assets/assets.json");
+        GameLoop::new(std::rc::Rc::new(assets), 0)
+    }
+
+    /// 同じ入力で指定した回数のティックを実行する。
+    fn run(game_loop: &mut GameLoop, input: &input::InputState, ticks: u32) {
+        (0..ticks).for_each(|_| {
+            game_loop.update(input, &GameLoop::TICK);
+            game_loop.discard_changes();
+        });
+    }
+
+    #[test]
+    fn player_moves_and_camera_follows() {
+        let mut game_loop = game_loop();
+        run(&mut game_loop, &Default::default(), 2);
+
+        // NOTE: 生成された障害物を取り除く。生成済みのチャンクは再び生成されない。
+        let entity_id = game_loop.player_state.as_ref().unwrap().entity_id;
+        game_loop.block_storage.restore(std::iter::empty());
+        let others = game_loop
+            .entity_storage
+            .iter()
+            .map(|(id, _)| id)
+            .filter(|id| *id != entity_id)
+            .collect::<Vec<_>>();
+        others.into_iter().for_each(|id| {
+            game_loop.entity_storage.remove(id);
+        });
+        let start = game_loop.entity_storage.get(entity_id).unwrap().position;
+
+        let mut input = input::InputState::default();
+        input.press(input::Action::MoveRight);
+        run(&mut game_loop, &input, 60);
+
+        let entity = game_loop.entity_storage.get(entity_id).unwrap();
+        let delta = entity.position - start;
+        assert!(delta.x > 1.0, "moved {:?}", delta);
+        assert!(delta.y.abs() < 1e-3, "moved {:?}", delta);
+
+        let entity_spec = &game_loop.assets.entity_specs[entity.spec_id];
+        let center = entity.position + entity_spec.rendering_size.center();
+        assert!(game_loop.camera_state.position.abs_diff_eq(center, 1e-4));
+
+        // NOTE: 離すと停止する。
+        let position = entity.position;
+        run(&mut game_loop, &Default::default(), 30);
+        let entity = game_loop.entity_storage.get(entity_id).unwrap();
+        assert!(entity.position.abs_diff_eq(position, 1e-3));
+    }
+
+    #[test]
+    fn update_catches_up_to_limit() {
+        let mut game_loop = game_loop();
+        let tick = GameLoop::TICK.as_secs_f64();
+        let input = input::InputState::default();
+
+        // NOTE: 1ティックに満たない時間は持ち越される。
+        let start = game_loop.clock_sys.time();
+        game_loop.update(&input, &(GameLoop::TICK / 2));
+        assert_eq!(game_loop.clock_sys.time(), start);
+        game_loop.update(&input, &(GameLoop::TICK * 3));
+        let ticks = (game_loop.clock_sys.time() - start) / tick;
+        assert!((ticks - 3.0).abs() < 1e-6);
+        game_loop.discard_changes();
+
+        // NOTE: 上限を超える遅れは破棄され、次のフレームに持ち越されない。
+        let start = game_loop.clock_sys.time();
+        game_loop.update(&input, &(GameLoop::TICK * 100));
+        let ticks = (game_loop.clock_sys.time() - start) / tick;
+        assert!((ticks - GameLoop::MAX_CATCH_UP as f64).abs() < 1e-6);
+        assert_eq!(game_loop.accumulator, std::time::Duration::ZERO);
+        game_loop.discard_changes();
+    }
+
+    #[test]
+    fn craft_command() {
+        let mut game_loop = game_loop();
+        run(&mut game_loop, &Default::default(), 1);
+
+        let item_spec_id = |label: &str| {
+            game_loop
+                .assets
+                .item_specs
+                .iter()
+                .position(|spec| spec.label == label)
+                .unwrap()
+        };
+        let branch = item_spec_id("fallenBranch");
+        let torch = item_spec_id("torch");
+        let assets = game_loop.assets.clone();
+        let player = game_loop.player_state.as_mut().unwrap();
+        player.inventory = inventory::Inventory::new(player.inventory.slots().len());
+        player.inventory.insert(&assets, branch, 1);
+
+        assert_eq!(game_loop.execute("craft").unwrap(), "branchToTorches");
+        assert!(game_loop.execute("craft branchToTorches").is_ok());
+        let player = game_loop.player_state.as_ref().unwrap();
+        assert_eq!(player.inventory.count(branch), 0);
+        assert_eq!(player.inventory.count(torch), 2);
+
+        assert!(matches!(
+            game_loop.execute("craft branchToTorches"),
+            Err(console::CommandError::Failed(_))
+        ));
+        assert!(matches!(
+            game_loop.execute("craft unknownRecipe"),
+            Err(console::CommandError::Failed(_))
+        ));
+    }
+
+    #[test]
+    fn extract_includes_hotbar() {
+        let mut game_loop = game_loop();
+        run(&mut game_loop, &Default::default(), 1);
+
+        let assets = game_loop.assets.clone();
+        let player = game_loop.player_state.as_mut().unwrap();
+        player.inventory = inventory::Inventory::new(player.inventory.slots().len());
+        player.inventory.insert(&assets, 0, 3);
+        player.selected_slot = 2;
+
+        let hotbar = game_loop.extract((800, 600)).hotbar.unwrap();
+        assert_eq!(hotbar.slots.len(), inventory::Inventory::HOTBAR_SIZE);
+        assert_eq!(hotbar.slots[0], Some(inventory::ItemStack::new(0, 3)));
+        assert_eq!(hotbar.selected_slot, 2);
+    }
+}
@@ -1,13 +1,14 @@
 //! ベースシステムの機能に関するモジュール
 
-use ahash::HashMap;
+use std::collections::BTreeMap;
+
+use ahash::{HashMap, HashSet};
 use glam::*;
-use slab::Slab;
 
 use crate::aabb::*;
-use crate::assets;
+use crate::{assets, game_loop::journal};
 
-#[derive(Clone)]
+#[derive(Clone, Copy)]
 pub struct Base {
     pub spec_id: usize,
     pub position: IVec2,
@@ -21,120 +22,356 @@ impl Base {
     }
 }
 
-struct BaseMeta {
-    base: Base,
-    grid_index_rev: (IVec2, usize),
-    global_index_rev: IVec2,
+/// チャンクの一辺の大きさ
+pub const CHUNK_SIZE: i32 = 32;
+
+/// チャンクに含まれるタイルの数
+const CHUNK_VOLUME: usize = (CHUNK_SIZE * CHUNK_SIZE) as usize;
+
+/// チャンクに含まれるベースの最大数 (全ての層)
+const CHUNK_CAPACITY: usize = CHUNK_VOLUME * assets::BaseLayer::ALL.len();
+
+/// 全てのベースの識別子が`usize`に収まるチャンクの識別子の最大値
+const MAX_CHUNK_ID: usize = usize::MAX / CHUNK_CAPACITY - 1;
+
+/// チャンク内の全てのベースの仕様を層毎に密に保持する配列
+#[derive(Clone)]
+pub struct BaseChunk {
+    spec_ids: Box<[u16; CHUNK_CAPACITY]>,
+    len: usize,
+}
+
+impl Default for BaseChunk {
+    #[inline]
+    fn default() -> Self {
+        Self {
+            spec_ids: Box::new([Self::EMPTY; CHUNK_CAPACITY]),
+            len: 0,
+        }
+    }
+}
+
+impl BaseChunk {
+    /// ベースが存在しないことを表す値
+    const EMPTY: u16 = u16::MAX;
+
+    /// チャンク内の全ての位置
+    #[inline]
+    pub fn local_points() -> impl Iterator<Item = IVec2> {
+        iaabb2(IVec2::ZERO, IVec2::splat(CHUNK_SIZE)).into_iter_points()
+    }
+
+    /// チャンク内の位置と層に対応する添字を返す。
+    #[inline]
+    fn index(local: IVec2, layer: assets::BaseLayer) -> usize {
+        layer as usize * CHUNK_VOLUME + (local.y * CHUNK_SIZE + local.x) as usize
+    }
+
+    /// 添字に対応するチャンク内の位置と層を返す。
+    #[inline]
+    fn local(index: usize) -> (IVec2, assets::BaseLayer) {
+        let layer = assets::BaseLayer::ALL[index / CHUNK_VOLUME];
+        let index = (index % CHUNK_VOLUME) as i32;
+        (ivec2(index % CHUNK_SIZE, index / CHUNK_SIZE), layer)
+    }
+
+    /// 指定した位置と層に存在するベースの仕様を返す。
+    #[inline]
+    pub fn get(&self, local: IVec2, layer: assets::BaseLayer) -> Option<usize> {
+        let spec_id = self.spec_ids[Self::index(local, layer)];
+        (spec_id != Self::EMPTY).then_some(spec_id as usize)
+    }
+
+    /// 指定した位置と層にベースが存在しなければ仕様を設定し、設定できたか真偽値を返す。
+    #[inline]
+    pub fn insert(&mut self, local: IVec2, layer: assets::BaseLayer, spec_id: usize) -> bool {
+        let slot = &mut self.spec_ids[Self::index(local, layer)];
+        if *slot != Self::EMPTY {
+            return false;
+        }
+        *slot = spec_id as u16;
+        self.len += 1;
+        true
+    }
+
+    /// 指定した位置と層に存在するベースの仕様を取り除き、その仕様を返す。
+    #[inline]
+    pub fn remove(&mut self, local: IVec2, layer: assets::BaseLayer) -> Option<usize> {
+        let slot = &mut self.spec_ids[Self::index(local, layer)];
+        let spec_id = std::mem::replace(slot, Self::EMPTY);
+        if spec_id == Self::EMPTY {
+            return None;
+        }
+        self.len -= 1;
+        Some(spec_id as usize)
+    }
+
+    /// 存在する全てのベースの位置、層、仕様を返す。
+    #[inline]
+    pub fn iter(&self) -> impl Iterator<Item = (IVec2, assets::BaseLayer, usize)> + '_ {
+        self.spec_ids
+            .iter()
+            .enumerate()
+            .filter(|(_, &spec_id)| spec_id != Self::EMPTY)
+            .map(|(index, &spec_id)| {
+                let (local, layer) = Self::local(index);
+                (local, layer, spec_id as usize)
+            })
+    }
+
+    /// 存在するベースの数を返す。
+    #[inline]
+    pub fn len(&self) -> usize {
+        self.len
+    }
+
+    /// ベースが存在しないか真偽値を返す。
+    #[inline]
+    pub fn is_empty(&self) -> bool {
+        self.len == 0
+    }
+}
+
+struct ChunkMeta {
+    grid_point: IVec2,
+    chunk: BaseChunk,
 }
 
 /// ベースシステムの機能
+///
+/// ベースはチャンク毎の密な配列に保持される。識別子はチャンクの識別子と
+/// チャンク内の添字から構成される。チャンクの識別子は再利用されないため、
+/// 破棄されたチャンクのベースの識別子が別のベースを指すことはない。
 pub struct BaseStorage {
     assets: std::rc::Rc<assets::Assets>,
-    base_metas: Slab<BaseMeta>,
-    grid_index: HashMap<IVec2, Slab<usize>>,
-    global_index: HashMap<IVec2, usize>,
+    chunk_metas: BTreeMap<usize, ChunkMeta>,
+    chunk_index: HashMap<IVec2, usize>,
+    /// 次に作成するチャンクの識別子
+    next_chunk_id: usize,
+    journal: journal::Journal<Base>,
 }
 
 impl BaseStorage {
-    /// 近傍探索のための空間分割サイズ
-    const GRID_SIZE: i32 = 32;
-
-    const VOLUME_THRESHOLD: i32 = 256;
-
     #[inline]
     pub fn new(assets: std::rc::Rc<assets::Assets>) -> Self {
         Self {
             assets,
-            base_metas: Default::default(),
-            grid_index: Default::default(),
-            global_index: Default::default(),
+            chunk_metas: Default::default(),
+            chunk_index: Default::default(),
+            next_chunk_id: 0,
+            journal: Default::default(),
         }
     }
 
+    /// チャンクの識別子とチャンク内の添字から識別子を返す。
+    #[inline]
+    fn encode_id(chunk_id: usize, local: IVec2, layer: assets::BaseLayer) -> usize {
+        chunk_id * CHUNK_CAPACITY + BaseChunk::index(local, layer)
+    }
+
+    /// 識別子からチャンクの識別子とチャンク内の位置と層を返す。
+    #[inline]
+    fn decode_id(id: usize) -> (usize, IVec2, assets::BaseLayer) {
+        let (local, layer) = BaseChunk::local(id % CHUNK_CAPACITY);
+        (id / CHUNK_CAPACITY, local, layer)
+    }
+
+    /// 指定したチャンクの識別子を返す。存在しなければ空のチャンクを作成する。
+    fn chunk_id_or_insert(&mut self, grid_point: IVec2) -> usize {
+        *self.chunk_index.entry(grid_point).or_insert_with(|| {
+            let chunk_id = self.next_chunk_id;
+            self.next_chunk_id += 1;
+            let chunk_meta = ChunkMeta {
+                grid_point,
+                chunk: Default::default(),
+            };
+            self.chunk_metas.insert(chunk_id, chunk_meta);
+            chunk_id
+        })
+    }
+
     /// ベースを追加し、識別子を返す。
     pub fn insert(&mut self, base: Base) -> Option<usize> {
-        // 重複の回避
-        let rect = iaabb2(base.position, base.position + IVec2::ONE);
-        if self.has_internal_by_rect(rect) {
-            return None;
+        let spec = &self.assets.base_specs[base.spec_id];
+        let layer = spec.layer;
+
+        let grid_point = base.position.to_grid_space(CHUNK_SIZE);
+        let local = base.position - grid_point * CHUNK_SIZE;
+
+        // 重複の回避 (層毎)
+        if let Some(&chunk_id) = self.chunk_index.get(&grid_point) {
+            if self.chunk_metas[&chunk_id]
+                .chunk
+                .get(local, layer)
+                .is_some()
+            {
+                return None;
+            }
         }
 
-        let base_id = self.base_metas.vacant_key();
-
-        // インデクスを構築 (1)
-        let grid_point = base.position.to_grid_space(Self::GRID_SIZE);
-        let id = self
-            .grid_index
-            .entry(grid_point)
-            .or_default()
-            .insert(base_id);
-        let grid_index_rev = (grid_point, id);
-
-        // インデクスを構築 (2)
-        self.global_index.insert(base.position, base_id);
-        let global_index_rev = base.position;
-
-        self.base_metas.insert(BaseMeta {
-            base,
-            grid_index_rev,
-            global_index_rev,
-        });
-        Some(base_id)
+        let chunk_id = self.chunk_id_or_insert(grid_point);
+        let chunk_meta = self.chunk_metas.get_mut(&chunk_id).unwrap();
+        chunk_meta.chunk.insert(local, layer, base.spec_id);
+
+        let id = Self::encode_id(chunk_id, local, layer);
+        self.journal
+            .record(journal::Change::Insert { id, new: base });
+        Some(id)
     }
 
     /// ベースを削除し、そのベースを返す。
     pub fn remove(&mut self, id: usize) -> Option<Base> {
-        let BaseMeta {
-            base,
-            grid_index_rev,
-            global_index_rev,
-        } = self.base_metas.try_remove(id)?;
+        let (chunk_id, local, layer) = Self::decode_id(id);
+
+        let chunk_meta = self.chunk_metas.get_mut(&chunk_id)?;
+        let spec_id = chunk_meta.chunk.remove(local, layer)?;
 
-        // インデクスを破棄 (1)
-        let (grid_point, id) = grid_index_rev;
-        self.grid_index.get_mut(&grid_point).unwrap().remove(id);
+        let base = Base::new(spec_id, chunk_meta.grid_point * CHUNK_SIZE + local);
 
-        // インデクスを破棄 (2)
-        self.global_index.remove(&global_index_rev);
+        // NOTE: 空になったチャンクを破棄する。
+        if chunk_meta.chunk.is_empty() {
+            let chunk_meta = self.chunk_metas.remove(&chunk_id).unwrap();
+            self.chunk_index.remove(&chunk_meta.grid_point);
+        }
 
+        self.journal
+            .record(journal::Change::Remove { id, old: base });
         Some(base)
     }
 
-    /// 指定した識別子に対応するベースの参照を返す。
-    #[inline]
-    pub fn get(&self, id: usize) -> Option<&Base> {
-        self.base_metas.get(id).map(|base_meta| &base_meta.base)
+    /// チャンク単位でベースを追加する。既にベースが存在する位置と層は変更されない。
+    pub fn insert_chunk(&mut self, grid_point: IVec2, chunk: &BaseChunk) {
+        let chunk_id = self.chunk_id_or_insert(grid_point);
+
+        let chunk_meta = self.chunk_metas.get_mut(&chunk_id).unwrap();
+        chunk.iter().for_each(|(local, layer, spec_id)| {
+            if chunk_meta.chunk.insert(local, layer, spec_id) {
+                let id = Self::encode_id(chunk_id, local, layer);
+                let base = Base::new(spec_id, grid_point * CHUNK_SIZE + local);
+                self.journal
+                    .record(journal::Change::Insert { id, new: base });
+            }
+        });
+
+        // NOTE: 空のチャンクを保持しない。
+        if chunk_meta.chunk.is_empty() {
+            self.chunk_metas.remove(&chunk_id);
+            self.chunk_index.remove(&grid_point);
+        }
     }
 
-    /// 指定した範囲にベースが存在するか真偽値を返す。
-    #[inline]
-    pub fn has_rendering_by_rect(&self, rect: Aabb2) -> bool {
-        self.get_rendering_by_rect(rect).next().is_some()
+    /// チャンク単位でベースを削除し、そのチャンクを返す。
+    pub fn remove_chunk(&mut self, grid_point: IVec2) -> Option<BaseChunk> {
+        let chunk_id = self.chunk_index.remove(&grid_point)?;
+        let ChunkMeta { chunk, .. } = self.chunk_metas.remove(&chunk_id).unwrap();
+
+        chunk.iter().for_each(|(local, layer, spec_id)| {
+            let id = Self::encode_id(chunk_id, local, layer);
+            let base = Base::new(spec_id, grid_point * CHUNK_SIZE + local);
+            self.journal
+                .record(journal::Change::Remove { id, old: base });
+        });
+
+        Some(chunk)
     }
 
-    /// 指定した範囲に存在するベースの識別子と参照を返す。
-    pub fn get_internal_by_rect(&self, rect: IAabb2) -> impl Iterator<Item = (usize, &Base)> {
-        if rect.volume() <= Self::VOLUME_THRESHOLD {
-            // 指定した範囲に存在するベースの識別子と参照を返す。狭い範囲で効果的。
-            let iter = rect
-                .into_iter_points()
-                .filter_map(move |position| self.global_index.get(&position))
-                .map(|&id| (id, &self.base_metas[id].base));
-            itertools::Either::Right(iter)
-        } else {
-            // 指定した範囲に存在するベースの識別子と参照を返す。広い範囲で効果的。
-            let iter = rect
-                .to_grid_space(Self::GRID_SIZE)
-                .into_iter_points()
-                .filter_map(move |grid_point| self.grid_index.get(&grid_point))
-                .flatten()
-                .map(|(_, &id)| (id, &self.base_metas[id].base))
-                .filter(move |(_, base)| {
-                    let base_rect = iaabb2(base.position, base.position + IVec2::ONE);
-                    rect.intersects(base_rect)
+    /// 全てのベースを削除し、識別子を指定したチャンクで置き換え、置き換えたか真偽値を返す。
+    ///
+    /// チャンクの識別子や位置が重複する場合、または識別子が範囲外の場合は何も変更しない。
+    pub fn restore(&mut self, chunks: impl IntoIterator<Item = (usize, IVec2, BaseChunk)>) -> bool {
+        let chunks = chunks.into_iter().collect::<Vec<_>>();
+        let mut chunk_ids = HashSet::default();
+        let mut grid_points = HashSet::default();
+        let valid = chunks.iter().all(|(chunk_id, grid_point, _)| {
+            *chunk_id <= MAX_CHUNK_ID
+                && chunk_ids.insert(*chunk_id)
+                && grid_points.insert(*grid_point)
+        });
+        if !valid {
+            return false;
+        }
+
+        let grid_points = self
+            .chunk_metas
+            .values()
+            .map(|chunk_meta| chunk_meta.grid_point)
+            .collect::<Vec<_>>();
+        grid_points.into_iter().for_each(|grid_point| {
+            self.remove_chunk(grid_point);
+        });
+
+        let chunk_metas = chunks
+            .into_iter()
+            .map(|(chunk_id, grid_point, chunk)| {
+                chunk.iter().for_each(|(local, layer, spec_id)| {
+                    let id = Self::encode_id(chunk_id, local, layer);
+                    let base = Base::new(spec_id, grid_point * CHUNK_SIZE + local);
+                    self.journal
+                        .record(journal::Change::Insert { id, new: base });
                 });
-            itertools::Either::Left(iter)
+
+                self.chunk_index.insert(grid_point, chunk_id);
+                (chunk_id, ChunkMeta { grid_point, chunk })
+            })
+            .collect::<Vec<_>>();
+        self.chunk_metas = chunk_metas.into_iter().collect();
+
+        // NOTE: 復元した識別子と以前の識別子のどちらも再利用しない。
+        if let Some(&chunk_id) = self.chunk_metas.keys().next_back() {
+            self.next_chunk_id = self.next_chunk_id.max(chunk_id + 1);
         }
+        true
+    }
+
+    /// 指定した識別子に対応するベースを返す。
+    #[inline]
+    pub fn get(&self, id: usize) -> Option<Base> {
+        let (chunk_id, local, layer) = Self::decode_id(id);
+        let chunk_meta = self.chunk_metas.get(&chunk_id)?;
+        let spec_id = chunk_meta.chunk.get(local, layer)?;
+        Some(Base::new(
+            spec_id,
+            chunk_meta.grid_point * CHUNK_SIZE + local,
+        ))
+    }
+
+    /// 指定したチャンクの参照を返す。
+    #[inline]
+    pub fn get_chunk(&self, grid_point: IVec2) -> Option<&BaseChunk> {
+        let &chunk_id = self.chunk_index.get(&grid_point)?;
+        Some(&self.chunk_metas[&chunk_id].chunk)
+    }
+
+    /// 全てのチャンクの識別子、位置、参照を返す。
+    #[inline]
+    pub fn iter_chunks(&self) -> impl Iterator<Item = (usize, IVec2, &BaseChunk)> {
+        self.chunk_metas
+            .iter()
+            .map(|(&chunk_id, chunk_meta)| (chunk_id, chunk_meta.grid_point, &chunk_meta.chunk))
+    }
+
+    /// 全てのベースの識別子とベースを返す。
+    #[inline]
+    pub fn iter(&self) -> impl Iterator<Item = (usize, Base)> + '_ {
+        self.iter_chunks()
+            .flat_map(|(chunk_id, grid_point, chunk)| {
+                chunk.iter().map(move |(local, layer, spec_id)| {
+                    let id = Self::encode_id(chunk_id, local, layer);
+                    (id, Base::new(spec_id, grid_point * CHUNK_SIZE + local))
+                })
+            })
+    }
+
+    /// 変更履歴の参照を返す。
+    #[inline]
+    pub fn journal(&self) -> &journal::Journal<Base> {
+        &self.journal
+    }
+
+    /// 変更履歴の可変参照を返す。
+    #[inline]
+    pub fn journal_mut(&mut self) -> &mut journal::Journal<Base> {
+        &mut self.journal
     }
 
     /// 指定した範囲にベースが存在するか真偽値を返す。
@@ -143,30 +380,184 @@ impl BaseStorage {
         self.get_internal_by_rect(rect).next().is_some()
     }
 
-    /// 指定した範囲に存在するベースの識別子と参照を返す。
-    pub fn get_rendering_by_rect(&self, rect: Aabb2) -> impl Iterator<Item = (usize, &Base)> {
-        let rect = rect.trunc_over().as_iaabb2();
-
-        if rect.volume() <= Self::VOLUME_THRESHOLD {
-            // 指定した範囲に存在するベースの識別子と参照を返す。狭い範囲で効果的。
-            let iter = rect
-                .into_iter_points()
-                .filter_map(move |position| self.global_index.get(&position))
-                .map(|&id| (id, &self.base_metas[id].base));
-            itertools::Either::Right(iter)
-        } else {
-            // 指定した範囲に存在するベースの識別子と参照を返す。広い範囲で効果的。
-            let iter = rect
-                .to_grid_space(Self::GRID_SIZE)
-                .into_iter_points()
-                .filter_map(move |grid_point| self.grid_index.get(&grid_point))
-                .flatten()
-                .map(|(_, &id)| (id, &self.base_metas[id].base))
-                .filter(move |(_, base)| {
-                    let base_rect = iaabb2(base.position, base.position + IVec2::ONE);
-                    rect.intersects(base_rect)
-                });
-            itertools::Either::Left(iter)
-        }
+    /// 指定した範囲に存在するベースの識別子とベースを返す。
+    #[inline]
+    pub fn get_internal_by_rect(&self, rect: IAabb2) -> impl Iterator<Item = (usize, Base)> + '_ {
+        self.get_by_rect(rect, None)
+    }
+
+    /// 指定した範囲と層にベースが存在するか真偽値を返す。
+    #[inline]
+    pub fn has_internal_by_layer(&self, rect: IAabb2, layer: assets::BaseLayer) -> bool {
+        self.get_internal_by_layer(rect, layer).next().is_some()
+    }
+
+    /// 指定した範囲と層に存在するベースの識別子とベースを返す。
+    #[inline]
+    pub fn get_internal_by_layer(
+        &self,
+        rect: IAabb2,
+        layer: assets::BaseLayer,
+    ) -> impl Iterator<Item = (usize, Base)> + '_ {
+        self.get_by_rect(rect, Some(layer))
+    }
+
+    /// 指定した範囲にベースが存在するか真偽値を返す。
+    #[inline]
+    pub fn has_rendering_by_rect(&self, rect: Aabb2) -> bool {
+        self.get_rendering_by_rect(rect).next().is_some()
+    }
+
+    /// 指定した範囲に存在するベースの識別子とベースを返す。
+    #[inline]
+    pub fn get_rendering_by_rect(&self, rect: Aabb2) -> impl Iterator<Item = (usize, Base)> + '_ {
+        self.get_by_rect(rect.trunc_over().as_iaabb2(), None)
+    }
+
+    /// 指定した範囲と層にベースが存在するか真偽値を返す。
+    #[inline]
+    pub fn has_rendering_by_layer(&self, rect: Aabb2, layer: assets::BaseLayer) -> bool {
+        self.get_rendering_by_layer(rect, layer).next().is_some()
+    }
+
+    /// 指定した範囲と層に存在するベースの識別子とベースを返す。
+    #[inline]
+    pub fn get_rendering_by_layer(
+        &self,
+        rect: Aabb2,
+        layer: assets::BaseLayer,
+    ) -> impl Iterator<Item = (usize, Base)> + '_ {
+        self.get_by_rect(rect.trunc_over().as_iaabb2(), Some(layer))
+    }
+
+    /// 指定した範囲 (と層) に存在するベースの識別子とベースを返す。
+    ///
+    /// チャンク毎に、同じ位置のベースは層の順に返す。
+    fn get_by_rect(
+        &self,
+        rect: IAabb2,
+        layer: Option<assets::BaseLayer>,
+    ) -> impl Iterator<Item = (usize, Base)> + '_ {
+        rect.to_grid_space(CHUNK_SIZE)
+            .into_iter_points()
+            .filter_map(move |grid_point| {
+                let &chunk_id = self.chunk_index.get(&grid_point)?;
+                let chunk_rect = grid_point.to_base_space(CHUNK_SIZE);
+                rect.intersects(chunk_rect)
+                    .then_some((chunk_id, chunk_rect))
+            })
+            .flat_map(move |(chunk_id, chunk_rect)| {
+                let chunk = &self.chunk_metas[&chunk_id].chunk;
+                let clip = iaabb2(rect.min.max(chunk_rect.min), rect.max.min(chunk_rect.max));
+                clip.into_iter_points().flat_map(move |position| {
+                    let local = position - chunk_rect.min;
+                    assets::BaseLayer::ALL
+                        .into_iter()
+                        .filter(move |base_layer| layer.is_none_or(|layer| layer == *base_layer))
+                        .filter_map(move |base_layer| {
+                            let spec_id = chunk.get(local, base_layer)?;
+                            let id = Self::encode_id(chunk_id, local, base_layer);
+                            Some((id, Base::new(spec_id, position)))
+                        })
+                })
+            })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn chunk_index_round_trip() {
+        (0..CHUNK_CAPACITY).for_each(|index| {
+            let (local, layer) = BaseChunk::local(index);
+            assert!(iaabb2(IVec2::ZERO, IVec2::splat(CHUNK_SIZE)).contains_point(local));
+            assert_eq!(BaseChunk::index(local, layer), index);
+        });
+
+        let chunk_id = 3;
+        BaseChunk::local_points().for_each(|local| {
+            assets::BaseLayer::ALL.into_iter().for_each(|layer| {
+                let id = BaseStorage::encode_id(chunk_id, local, layer);
+                assert_eq!(BaseStorage::decode_id(id), (chunk_id, local, layer));
+            });
+        });
+    }
+
+    #[test]
+    fn chunk_counts_bases() {
+        let mut chunk = BaseChunk::default();
+        assert!(chunk.is_empty());
+
+        let layer = assets::BaseLayer::Ground;
+        assert!(chunk.insert(ivec2(1, 2), layer, 5));
+        assert!(!chunk.insert(ivec2(1, 2), layer, 6));
+        assert!(chunk.insert(ivec2(1, 2), assets::BaseLayer::Decal, 6));
+        assert_eq!(chunk.len(), 2);
+        assert_eq!(chunk.get(ivec2(1, 2), layer), Some(5));
+
+        assert_eq!(chunk.remove(ivec2(1, 2), layer), Some(5));
+        assert_eq!(chunk.remove(ivec2(1, 2), layer), None);
+        assert_eq!(chunk.len(), 1);
+        assert_eq!(chunk.len(), chunk.iter().count());
+    }
+
+    #[test]
+    fn storage_does_not_reuse_ids() {
+        let assets = std::rc::Rc::new(assets::Assets::new("assets/assets.json"));
+        let mut base_storage = BaseStorage::new(assets);
+
+        let old_id = base_storage.insert(Base::new(0, ivec2(1, 1))).unwrap();
+        assert!(base_storage.remove(old_id).is_some());
+
+        // NOTE: 破棄されたチャンクの識別子は別のチャンクへ割り当てない。
+        let new_id = base_storage
+            .insert(Base::new(0, ivec2(CHUNK_SIZE + 1, 1)))
+            .unwrap();
+        assert_ne!(new_id, old_id);
+        assert!(base_storage.get(old_id).is_none());
+        assert_eq!(
+            base_storage.get(new_id).unwrap().position,
+            ivec2(CHUNK_SIZE + 1, 1)
+        );
+    }
+
+    #[test]
+    fn storage_queries_by_layer() {
+        let assets = std::rc::Rc::new(assets::Assets::new("assets/assets.json"));
+        let mut base_storage = BaseStorage::new(assets);
+
+        // NOTE: 地面 (surfaceDirt) と重ねる層 (surfaceGravel) を同じタイルに置く。
+        let ground_id = base_storage.insert(Base::new(0, ivec2(2, 3))).unwrap();
+        let overlay_id = base_storage.insert(Base::new(2, ivec2(2, 3))).unwrap();
+        assert!(base_storage.insert(Base::new(1, ivec2(2, 3))).is_none());
+
+        let rect = iaabb2(ivec2(2, 3), ivec2(3, 4));
+        let ids = |layer| {
+            base_storage
+                .get_internal_by_layer(rect, layer)
+                .map(|(id, _)| id)
+                .collect::<Vec<_>>()
+        };
+        assert_eq!(ids(assets::BaseLayer::Ground), vec![ground_id]);
+        assert_eq!(ids(assets::BaseLayer::Overlay), vec![overlay_id]);
+        assert!(ids(assets::BaseLayer::Decal).is_empty());
+        assert!(!base_storage.has_internal_by_layer(rect, assets::BaseLayer::Decal));
+
+        let rect = aabb2(vec2(2.5, 3.5), vec2(2.5, 3.5));
+        assert!(base_storage.has_rendering_by_layer(rect, assets::BaseLayer::Overlay));
+        let bases = base_storage
+            .get_rendering_by_layer(rect, assets::BaseLayer::Ground)
+            .map(|(_, base)| base.spec_id)
+            .collect::<Vec<_>>();
+        assert_eq!(bases, vec![0]);
+
+        // NOTE: 層を指定しない場合は層の順に返す。
+        let ids = base_storage
+            .get_internal_by_rect(iaabb2(ivec2(2, 3), ivec2(3, 4)))
+            .map(|(id, _)| id)
+            .collect::<Vec<_>>();
+        assert_eq!(ids, vec![ground_id, overlay_id]);
     }
 }
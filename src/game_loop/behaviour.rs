@@ -0,0 +1,415 @@
+//! エンティティの振る舞いの機能に関するモジュール
+
+use glam::*;
+use rand::Rng;
+
+use crate::aabb::*;
+use crate::{
+    assets,
+    game_loop::{
+        component, entity,
+        system::{Phase, System, WorldContext},
+    },
+};
+
+/// エンティティの振る舞いの機能
+///
+/// [`component::Behaviour`]を持つエンティティを仕様の状態機械に従って行動させる。
+/// 移動は経路探索の結果を[`component::PathFollow`]として反映する。
+/// 行き先の選択はワールドの乱数生成器から決まる。
+pub struct BehaviourSystem {
+    assets: std::rc::Rc<assets::Assets>,
+}
+
+impl BehaviourSystem {
+    /// 経路を再探索する間隔 (秒)
+    const REPLAN_INTERVAL: f32 = 0.5;
+
+    /// 対象のブロックを食べられる中心同士の距離
+    const GRAZE_DISTANCE: f32 = 1.5;
+
+    /// 新しい振る舞いシステムを作成する。
+    #[inline]
+    pub fn new(assets: std::rc::Rc<assets::Assets>) -> Self {
+        Self { assets }
+    }
+
+    /// 指定した位置への経路を探索し、経路に沿った移動を始める。
+    ///
+    /// 経路が見つかったか真偽値を返す。
+    fn travel(
+        &self,
+        context: &mut WorldContext,
+        entity_id: usize,
+        goal: IVec2,
+        speed: f32,
+    ) -> bool {
+        let Some(entity) = context.entity_storage.get(entity_id) else {
+            return false;
+        };
+        let entity_spec = &self.assets.entity_specs[entity.spec_id];
+        let footprint = context.pathfinding_sys.footprint(entity_spec);
+        let start = entity.position.round().as_ivec2();
+
+        let Some(path) =
+            context
+                .pathfinding_sys
+                .find_path(context.block_storage, start, goal, footprint)
+        else {
+            return false;
+        };
+        let path_follow = component::PathFollow {
+            waypoints: path.into_iter().map(|point| point.as_vec2()).collect(),
+            speed,
+        };
+        context
+            .entity_storage
+            .components_mut()
+            .insert(entity_id, path_follow);
+        true
+    }
+
+    /// 経路に沿った移動を中断し、停止させる。
+    fn stop(entity_storage: &mut entity::EntityStorage, entity_id: usize) {
+        let components = entity_storage.components_mut();
+        components.remove::<component::PathFollow>(entity_id);
+        components.insert(entity_id, component::Velocity::default());
+    }
+}
+
+impl System for BehaviourSystem {
+    #[inline]
+    fn phase(&self) -> Phase {
+        Phase::Simulation
+    }
+
+    /// 全てのエンティティの状態を遷移させ、行動を実行する。
+    fn update(&mut self, context: &mut WorldContext) {
+        let delta_secs = context.tick.as_secs_f32();
+
+        let player = context
+            .player_state
+            .as_ref()
+            .and_then(|player_state| context.entity_storage.get(player_state.entity_id))
+            .map(|entity| {
+                let spec = &self.assets.entity_specs[entity.spec_id];
+                (entity.position, entity.position + spec.internal_size * 0.5)
+            });
+
+        let entity_ids = context
+            .entity_storage
+            .components()
+            .iter_with::<(component::Behaviour,)>()
+            .collect::<Vec<_>>();
+
+        entity_ids.into_iter().for_each(|entity_id| {
+            let Some(entity) = context.entity_storage.get(entity_id) else {
+                return;
+            };
+            let entity_spec = &self.assets.entity_specs[entity.spec_id];
+            let center = entity.position + entity_spec.internal_size * 0.5;
+            let player_distance = player.map(|(_, player_center)| center.distance(player_center));
+
+            let mut behaviour = *context
+                .entity_storage
+                .components()
+                .get::<component::Behaviour>(entity_id)
+                .unwrap();
+            let behaviour_spec = &self.assets.behaviour_specs[behaviour.behaviour_spec_id];
+
+            // NOTE: 状態の遷移 (最初に満たされた遷移のみ)
+            let state_spec = &behaviour_spec.states[behaviour.state_id];
+            let transition =
+                state_spec
+                    .transitions
+                    .iter()
+                    .find(|transition| match transition.condition {
+                        assets::BehaviourConditionSpec::PlayerWithin { distance } => {
+                            player_distance.is_some_and(|d| d <= distance)
+                        }
+                        assets::BehaviourConditionSpec::PlayerBeyond { distance } => {
+                            player_distance.is_none_or(|d| d > distance)
+                        }
+                        assets::BehaviourConditionSpec::Elapsed { seconds } => {
+                            behaviour.elapsed >= seconds
+                        }
+                        assets::BehaviourConditionSpec::Done => behaviour.done,
+                    });
+            if let Some(transition) = transition {
+                behaviour.enter(transition.state_id);
+                Self::stop(context.entity_storage, entity_id);
+            }
+
+            // NOTE: 現在の状態の行動
+            let state_spec = &behaviour_spec.states[behaviour.state_id];
+            let following = context
+                .entity_storage
+                .components()
+                .get::<component::PathFollow>(entity_id)
+                .is_some();
+
+            match state_spec.action {
+                assets::BehaviourActionSpec::Idle => {
+                    if !behaviour.started {
+                        Self::stop(context.entity_storage, entity_id);
+                    }
+                }
+                assets::BehaviourActionSpec::Wander { speed, radius } => {
+                    if !behaviour.started {
+                        let offset = ivec2(
+                            context.rng.gen_range(-radius..=radius),
+                            context.rng.gen_range(-radius..=radius),
+                        );
+                        let goal = behaviour.origin.round().as_ivec2() + offset;
+                        behaviour.done = !self.travel(context, entity_id, goal, speed);
+                    } else if !following {
+                        behaviour.done = true;
+                    }
+                }
+                assets::BehaviourActionSpec::Flee { speed, distance } => match player {
+                    Some((_, player_center)) if !behaviour.started => {
+                        // NOTE: プレイヤーと重なる場合はランダムな方向へ逃げる。
+                        let direction =
+                            (center - player_center).try_normalize().unwrap_or_else(|| {
+                                Vec2::from_angle(context.rng.gen_range(0.0..std::f32::consts::TAU))
+                            });
+                        let goal = (center + direction * distance).floor().as_ivec2();
+                        behaviour.done = !self.travel(context, entity_id, goal, speed);
+                    }
+                    Some(_) if following => {}
+                    _ => behaviour.done = true,
+                },
+                assets::BehaviourActionSpec::Follow { speed, distance } => match player {
+                    Some((player_position, _)) if player_distance.unwrap() > distance => {
+                        // NOTE: 経路を見失った場合も一定の間隔でのみ再探索する。
+                        behaviour.timer -= delta_secs;
+                        if behaviour.timer <= 0.0 {
+                            let goal = player_position.round().as_ivec2();
+                            self.travel(context, entity_id, goal, speed);
+                            behaviour.timer = Self::REPLAN_INTERVAL;
+                        }
+                    }
+                    _ => {
+                        if following || !behaviour.started {
+                            Self::stop(context.entity_storage, entity_id);
+                        }
+                        behaviour.timer = 0.0;
+                    }
+                },
+                assets::BehaviourActionSpec::Graze {
+                    block_spec_id,
+                    speed,
+                    radius,
+                    duration,
+                } => {
+                    if !behaviour.started {
+                        // NOTE: 最も近い対象のブロックへ向かう。
+                        let point = center.floor().as_ivec2();
+                        let target = context
+                            .block_storage
+                            .get_internal_by_rect(iaabb2(point - radius, point + radius + 1))
+                            .filter(|(_, block)| block.spec_id == block_spec_id)
+                            .map(|(block_id, block)| {
+                                let distance = block.position.as_vec2().distance(center);
+                                (block_id, block.position, distance)
+                            })
+                            .min_by(|a, b| a.2.total_cmp(&b.2).then(a.0.cmp(&b.0)));
+
+                        behaviour.target_block = target.map(|(block_id, ..)| block_id);
+                        behaviour.done = !target.is_some_and(|(_, position, _)| {
+                            self.travel(context, entity_id, position, speed)
+                        });
+                    } else if let Some(block_id) = behaviour.target_block {
+                        let arrived = context.block_storage.get(block_id).and_then(|block| {
+                            let block_spec = &self.assets.block_specs[block.spec_id];
+                            let block_center =
+                                block.position.as_vec2() + block_spec.internal_size.as_vec2() * 0.5;
+                            (block.spec_id == block_spec_id)
+                                .then(|| block_center.distance(center) <= Self::GRAZE_DISTANCE)
+                        });
+                        match arrived {
+                            // NOTE: 移動中
+                            Some(_) if following => {}
+                            // NOTE: 到着後、一定時間が経過したらブロックを取り除く。
+                            Some(true) => {
+                                behaviour.timer += delta_secs;
+                                if behaviour.timer >= duration {
+                                    context.block_storage.remove(block_id);
+                                    behaviour.target_block = None;
+                                    behaviour.done = true;
+                                }
+                            }
+                            // NOTE: 対象が無くなった、または経路を見失い到着できなかった。
+                            _ => {
+                                behaviour.target_block = None;
+                                behaviour.done = true;
+                            }
+                        }
+                    }
+                }
+                assets::BehaviourActionSpec::Patrol { speed, ref points } => {
+                    if points.is_empty() {
+                        behaviour.done = true;
+                    } else if !following {
+                        // NOTE: 経路が見つからなかった地点は飛ばし、次の探索まで待つ。
+                        behaviour.timer -= delta_secs;
+                        if behaviour.timer <= 0.0 {
+                            let point = points[behaviour.patrol_index % points.len()];
+                            let goal = (behaviour.origin + point).round().as_ivec2();
+                            let found = self.travel(context, entity_id, goal, speed);
+                            behaviour.timer = if found { 0.0 } else { Self::REPLAN_INTERVAL };
+                            behaviour.patrol_index = (behaviour.patrol_index + 1) % points.len();
+                        }
+                    }
+                }
+                // NOTE: スクリプトの行動はスクリプトシステムが実行する。
+                assets::BehaviourActionSpec::Script { .. } => {
+                    if !behaviour.started {
+                        Self::stop(context.entity_storage, entity_id);
+                    }
+                }
+            }
+
+            behaviour.started = true;
+            behaviour.elapsed += delta_secs;
+            context
+                .entity_storage
+                .components_mut()
+                .insert(entity_id, behaviour);
+        });
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::game_loop::{block, GameLoop};
+
+    /// 生成されたブロックとプレイヤー以外のエンティティを取り除いたゲームループを返す。
+    fn empty_game_loop() -> (GameLoop, Vec2) {
+        let assets = assets::Assets::new("assets/assets.json");
+        let mut game_loop = GameLoop::new(std::rc::Rc::new(assets), 0);
+        game_loop.update(&Default::default(), &GameLoop::TICK);
+
+        let player_id = game_loop.player_state.as_ref().unwrap().entity_id;
+        game_loop.block_storage.restore(std::iter::empty());
+        let others = game_loop
+            .entity_storage
+            .iter()
+            .map(|(id, _)| id)
+            .filter(|id| *id != player_id)
+            .collect::<Vec<_>>();
+        others.into_iter().for_each(|id| {
+            game_loop.entity_storage.remove(id);
+        });
+
+        let position = game_loop.entity_storage.get(player_id).unwrap().position;
+        (game_loop, position)
+    }
+
+    /// 指定した状態のエンティティを出現させる。
+    fn spawn(game_loop: &mut GameLoop, label: &str, state_label: &str, position: Vec2) -> usize {
+        let assets = game_loop.assets.clone();
+        let spec_id = assets
+            .entity_specs
+            .iter()
+            .position(|spec| spec.label == label)
+            .unwrap();
+        let entity_id = game_loop
+            .entity_storage
+            .spawn(entity::Entity::new(spec_id, position))
+            .unwrap();
+
+        let components = game_loop.entity_storage.components_mut();
+        let behaviour = components
+            .get_mut::<component::Behaviour>(entity_id)
+            .unwrap();
+        let behaviour_spec = &assets.behaviour_specs[behaviour.behaviour_spec_id];
+        let state_id = behaviour_spec
+            .states
+            .iter()
+            .position(|state| state.label == state_label)
+            .unwrap();
+        behaviour.enter(state_id);
+        entity_id
+    }
+
+    fn place(game_loop: &mut GameLoop, label: &str, position: IVec2) -> usize {
+        let spec = game_loop
+            .assets
+            .block_specs
+            .iter()
+            .find(|spec| spec.label == label)
+            .unwrap();
+        let block = block::Block::new(spec.id, position, 0, block::BlockState::initial(spec));
+        game_loop.block_storage.insert(block).unwrap()
+    }
+
+    fn behaviour(game_loop: &GameLoop, entity_id: usize) -> component::Behaviour {
+        *game_loop
+            .entity_storage
+            .components()
+            .get::<component::Behaviour>(entity_id)
+            .unwrap()
+    }
+
+    #[test]
+    fn follow_waits_after_failed_replan() {
+        let (mut game_loop, player_position) = empty_game_loop();
+
+        // NOTE: 岩で囲まれ、プレイヤーへの経路が存在しない。
+        let origin = player_position.floor().as_ivec2() + ivec2(6, 0);
+        (-4..=4).step_by(2).for_each(|offset| {
+            place(&mut game_loop, "mixRock", origin + ivec2(offset, -4));
+            place(&mut game_loop, "mixRock", origin + ivec2(offset, 4));
+        });
+        (-2..=2).step_by(2).for_each(|offset| {
+            place(&mut game_loop, "mixRock", origin + ivec2(-4, offset));
+            place(&mut game_loop, "mixRock", origin + ivec2(4, offset));
+        });
+        let entity_id = spawn(&mut game_loop, "villager", "follow", origin.as_vec2());
+
+        game_loop.update(&Default::default(), &GameLoop::TICK);
+        let following = game_loop
+            .entity_storage
+            .components()
+            .get::<component::PathFollow>(entity_id)
+            .is_some();
+        assert!(!following);
+        assert_eq!(
+            behaviour(&game_loop, entity_id).timer,
+            BehaviourSystem::REPLAN_INTERVAL
+        );
+
+        // NOTE: 次の探索まで待つ。
+        game_loop.update(&Default::default(), &GameLoop::TICK);
+        assert!(behaviour(&game_loop, entity_id).timer < BehaviourSystem::REPLAN_INTERVAL);
+    }
+
+    #[test]
+    fn graze_needs_to_arrive() {
+        let (mut game_loop, player_position) = empty_game_loop();
+
+        let position = player_position + vec2(20.0, 0.0);
+        let entity_id = spawn(&mut game_loop, "sheep", "graze", position);
+        let block_id = place(
+            &mut game_loop,
+            "mixGrass",
+            position.floor().as_ivec2() + ivec2(5, 0),
+        );
+
+        // NOTE: 経路を見失った状態
+        let mut behaviour = behaviour(&game_loop, entity_id);
+        behaviour.started = true;
+        behaviour.target_block = Some(block_id);
+        game_loop
+            .entity_storage
+            .components_mut()
+            .insert(entity_id, behaviour);
+
+        (0..180).for_each(|_| {
+            game_loop.update(&Default::default(), &GameLoop::TICK);
+        });
+        assert!(game_loop.block_storage.get(block_id).is_some());
+    }
+}
@@ -5,23 +5,76 @@ use glam::*;
 use slab::Slab;
 
 use crate::aabb::*;
-use crate::assets;
+use crate::{assets, game_loop::journal};
+
+/// ブロックが個別に持つ状態
+///
+/// 状態の種類は仕様の[`assets::BlockStateSpec`]によって決まる。
+#[derive(Clone, Copy, PartialEq, Eq, Hash, Default, serde::Serialize, serde::Deserialize)]
+pub enum BlockState {
+    #[default]
+    None,
+    Durability(u32),
+    Growth(u32),
+    Toggle(bool),
+    Container(Option<u32>),
+}
+
+impl BlockState {
+    /// 仕様に基づいた初期状態を返す。
+    #[inline]
+    pub fn initial(spec: &assets::BlockSpec) -> Self {
+        match spec.state_spec {
+            None => BlockState::None,
+            Some(assets::BlockStateSpec::Durability { max }) => BlockState::Durability(max),
+            Some(assets::BlockStateSpec::Growth { .. }) => BlockState::Growth(0),
+            Some(assets::BlockStateSpec::Toggle { open }) => BlockState::Toggle(open),
+            Some(assets::BlockStateSpec::Container) => BlockState::Container(None),
+        }
+    }
+
+    /// 描写に使用するスプライトの番号を返す。
+    ///
+    /// `0`は仕様の`texture_path`、`n`は`state_texture_paths[n - 1]`に対応する。
+    pub fn sprite(&self, spec: &assets::BlockSpec) -> usize {
+        let count = spec.state_texture_paths.len();
+        let sprite = match (*self, &spec.state_spec) {
+            // NOTE: 耐久値の減少に応じて均等に切り替える。
+            (
+                BlockState::Durability(durability),
+                Some(assets::BlockStateSpec::Durability { max }),
+            ) if *max > 0 => {
+                let damage = max.saturating_sub(durability) as usize;
+                damage * (count + 1) / (*max as usize + 1)
+            }
+            // NOTE: 仕様の段階数を超えた成長段階は最終段階として扱う。
+            (BlockState::Growth(stage), Some(assets::BlockStateSpec::Growth { stages })) => {
+                stage.min(stages.saturating_sub(1)) as usize
+            }
+            (BlockState::Toggle(open), _) => open as usize,
+            _ => 0,
+        };
+        sprite.min(count)
+    }
+}
 
 #[derive(Clone)]
 pub struct Block {
     pub spec_id: usize,
     pub position: IVec2,
     pub z_random: u8,
+    pub state: BlockState,
 }
 
 impl Block {
     /// 新しいブロックを作成する。
     #[inline]
-    pub fn new(spec_id: usize, position: IVec2, z_random: u8) -> Self {
+    pub fn new(spec_id: usize, position: IVec2, z_random: u8, state: BlockState) -> Self {
         Self {
             spec_id,
             position,
             z_random,
+            state,
         }
     }
 }
@@ -38,6 +91,7 @@ pub struct BlockStorage {
     block_metas: Slab<BlockMeta>,
     internal_grid_index: HashMap<IVec2, Slab<usize>>,
     rendering_grid_index: HashMap<IVec2, Slab<usize>>,
+    journal: journal::Journal<Block>,
 }
 
 impl BlockStorage {
@@ -54,6 +108,7 @@ impl BlockStorage {
             block_metas: Default::default(),
             internal_grid_index: Default::default(),
             rendering_grid_index: Default::default(),
+            journal: Default::default(),
         }
     }
 
@@ -69,9 +124,90 @@ impl BlockStorage {
 
         let block_id = self.block_metas.vacant_key();
 
+        self.journal.record(journal::Change::Insert {
+            id: block_id,
+            new: block.clone(),
+        });
+
+        let block_meta = self.build_index(block_id, block);
+        self.block_metas.insert(block_meta);
+        Some(block_id)
+    }
+
+    /// ブロックを削除し、そのブロックを返す。
+    pub fn remove(&mut self, id: usize) -> Option<Block> {
+        let block_meta = self.block_metas.try_remove(id)?;
+        let block = self.destroy_index(block_meta);
+
+        self.journal.record(journal::Change::Remove {
+            id,
+            old: block.clone(),
+        });
+
+        Some(block)
+    }
+
+    /// ブロックを更新し、更新前のブロックを返す。識別子は維持される。
+    pub fn modify(&mut self, id: usize, block: Block) -> Option<Block> {
+        let spec = &self.assets.block_specs[block.spec_id];
+
+        // 重複の回避
+        let rect = iaabb2(block.position, block.position + spec.internal_size);
+        if self
+            .get_internal_by_rect(rect)
+            .any(|(other_id, _)| other_id != id)
+        {
+            return None;
+        }
+
+        if !self.block_metas.contains(id) {
+            return None;
+        }
+
+        let block_meta = self.build_index(id, block.clone());
+        let block_meta = std::mem::replace(&mut self.block_metas[id], block_meta);
+        let old = self.destroy_index(block_meta);
+
+        self.journal.record(journal::Change::Modify {
+            id,
+            old: old.clone(),
+            new: block,
+        });
+
+        Some(old)
+    }
+
+    /// 全てのブロックを削除し、識別子を指定したブロックで置き換える。
+    pub fn restore(&mut self, blocks: impl IntoIterator<Item = (usize, Block)>) {
+        let ids = self
+            .block_metas
+            .iter()
+            .map(|(id, _)| id)
+            .collect::<Vec<_>>();
+        ids.into_iter().for_each(|id| {
+            self.remove(id);
+        });
+
+        let block_metas = blocks
+            .into_iter()
+            .map(|(block_id, block)| {
+                self.journal.record(journal::Change::Insert {
+                    id: block_id,
+                    new: block.clone(),
+                });
+
+                (block_id, self.build_index(block_id, block))
+            })
+            .collect::<Vec<_>>();
+        self.block_metas = block_metas.into_iter().collect();
+    }
+
+    fn build_index(&mut self, block_id: usize, block: Block) -> BlockMeta {
+        let spec = &self.assets.block_specs[block.spec_id];
+
         // インデクスを構築 (1)
         let rect = iaabb2(block.position, block.position + spec.internal_size);
-        let internal_grid_index_rev = rect
+        let internal_grid_index_ref = rect
             .to_grid_space(Self::INTERNAL_GRID_SIZE)
             .into_iter_points()
             .map(|grid_point| {
@@ -86,7 +222,7 @@ impl BlockStorage {
 
         // インデクスを構築 (2)
         let rect = iaabb2(block.position, block.position).as_aabb2() + spec.rendering_size;
-        let rendering_grid_index_rev = rect
+        let rendering_grid_index_ref = rect
             .to_grid_space(Self::RENDERING_GRID_SIZE)
             .into_iter_points()
             .map(|grid_point| {
@@ -99,21 +235,19 @@ impl BlockStorage {
             })
             .collect::<Vec<_>>();
 
-        self.block_metas.insert(BlockMeta {
+        BlockMeta {
             block,
-            internal_grid_index_ref: internal_grid_index_rev,
-            rendering_grid_index_ref: rendering_grid_index_rev,
-        });
-        Some(block_id)
+            internal_grid_index_ref,
+            rendering_grid_index_ref,
+        }
     }
 
-    /// ブロックを削除し、そのブロックを返す。
-    pub fn remove(&mut self, id: usize) -> Option<Block> {
+    fn destroy_index(&mut self, block_meta: BlockMeta) -> Block {
         let BlockMeta {
             block,
             internal_grid_index_ref,
             rendering_grid_index_ref,
-        } = self.block_metas.try_remove(id)?;
+        } = block_meta;
 
         // インデクスを破棄 (1)
         internal_grid_index_ref
@@ -135,7 +269,7 @@ impl BlockStorage {
                     .remove(id);
             });
 
-        Some(block)
+        block
     }
 
     /// 指定した識別子に対応するブロックの参照を返す。
@@ -144,6 +278,26 @@ impl BlockStorage {
         self.block_metas.get(id).map(|block_meta| &block_meta.block)
     }
 
+    /// 全てのブロックの識別子と参照を返す。
+    #[inline]
+    pub fn iter(&self) -> impl Iterator<Item = (usize, &Block)> {
+        self.block_metas
+            .iter()
+            .map(|(id, block_meta)| (id, &block_meta.block))
+    }
+
+    /// 変更履歴の参照を返す。
+    #[inline]
+    pub fn journal(&self) -> &journal::Journal<Block> {
+        &self.journal
+    }
+
+    /// 変更履歴の可変参照を返す。
+    #[inline]
+    pub fn journal_mut(&mut self) -> &mut journal::Journal<Block> {
+        &mut self.journal
+    }
+
     /// 指定した範囲にベースが存在するか真偽値を返す。
     #[inline]
     pub fn has_internal_by_rect(&self, rect: IAabb2) -> bool {
@@ -189,3 +343,52 @@ impl BlockStorage {
             })
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn block_spec<'a>(assets: &'a mut assets::Assets, label: &str) -> &'a mut assets::BlockSpec {
+        assets
+            .block_specs
+            .iter_mut()
+            .find(|spec| spec.label == label)
+            .unwrap()
+    }
+
+    #[test]
+    fn durability_sprite_follows_damage() {
+        let mut assets = assets::Assets::new("assets/assets.json");
+        let spec = block_spec(&mut assets, "mixRock");
+        assert!(BlockState::initial(spec) == BlockState::Durability(3));
+
+        let sprites = (0..=3)
+            .rev()
+            .map(|durability| BlockState::Durability(durability).sprite(spec))
+            .collect::<Vec<_>>();
+        assert_eq!(sprites, vec![0, 0, 1, 1]);
+    }
+
+    #[test]
+    fn growth_and_toggle_sprites() {
+        let mut assets = assets::Assets::new("assets/assets.json");
+        let spec = block_spec(&mut assets, "mixRock");
+        spec.state_spec = Some(assets::BlockStateSpec::Growth { stages: 2 });
+        spec.state_texture_paths = vec!["a.png".into(), "b.png".into()];
+        assert!(BlockState::initial(spec) == BlockState::Growth(0));
+
+        // NOTE: 段階数を超えた成長段階は最終段階のスプライトとなる。
+        assert_eq!(BlockState::Growth(0).sprite(spec), 0);
+        assert_eq!(BlockState::Growth(1).sprite(spec), 1);
+        assert_eq!(BlockState::Growth(5).sprite(spec), 1);
+
+        spec.state_spec = Some(assets::BlockStateSpec::Toggle { open: true });
+        assert!(BlockState::initial(spec) == BlockState::Toggle(true));
+        assert_eq!(BlockState::Toggle(false).sprite(spec), 0);
+        assert_eq!(BlockState::Toggle(true).sprite(spec), 1);
+
+        // NOTE: 状態のスプライトが無い場合は基本のスプライトを使用する。
+        spec.state_texture_paths.clear();
+        assert_eq!(BlockState::Toggle(true).sprite(spec), 0);
+    }
+}
@@ -1,8 +1,15 @@
 use glam::*;
 
 use crate::aabb::*;
-use crate::{assets, game_loop::entity};
-
+use crate::{
+    assets,
+    game_loop::{
+        input,
+        system::{Phase, System, WorldContext},
+    },
+};
+
+#[derive(Clone)]
 pub struct CameraState {
     pub position: Vec2,
     pub zoom: f32,
@@ -52,7 +59,6 @@ impl CameraState {
 
 pub struct CameraSystem {
     assets: std::rc::Rc<assets::Assets>,
-    camera_state: CameraState,
 }
 
 impl CameraSystem {
@@ -79,51 +85,55 @@ impl CameraSystem {
 
     #[inline]
     pub fn new(assets: std::rc::Rc<assets::Assets>) -> Self {
-        let camera_state = CameraState {
+        Self { assets }
+    }
+
+    /// カメラの初期状態を返す。
+    #[inline]
+    pub fn initial_state() -> CameraState {
+        CameraState {
             position: Self::ORIZIN,
             zoom: Self::ZOOM_INIT,
             z_near: Self::Z_NEAR,
             z_far: Self::Z_FAR,
-        };
-
-        Self {
-            assets,
-            camera_state,
         }
     }
+}
 
+impl System for CameraSystem {
     #[inline]
-    pub fn get(&self) -> &CameraState {
-        &self.camera_state
+    fn phase(&self) -> Phase {
+        Phase::PostSimulation
     }
 
-    pub fn follow_entity(
-        &mut self,
-        input: &winit_input_helper::WinitInputHelper,
-        tick: &std::time::Duration,
-        entity_storage: &entity::EntityStorage,
-        entity_id: usize,
-    ) {
+    /// プレイヤーを追従し、操作に応じて拡大・縮小する。
+    fn update(&mut self, context: &mut WorldContext) {
+        let Some(player_state) = context.player_state.as_ref() else {
+            return;
+        };
+        let Some(entity) = context.entity_storage.get(player_state.entity_id) else {
+            return;
+        };
+        let tick = &context.tick;
+        let camera_state = &mut *context.camera_state;
+
         // NOTE: 視点の追従
-        let entity = entity_storage.get(entity_id).unwrap();
         let entity_spec = &self.assets.entity_specs[entity.spec_id];
-        self.camera_state.position = entity.position + entity_spec.rendering_size.center();
+        camera_state.position = entity.position + entity_spec.rendering_size.center();
 
         // NOTE: 視点の拡大・縮小
-        if input.key_held(winit::keyboard::KeyCode::KeyE) {
-            self.camera_state.zoom = (self.camera_state.zoom
-                + Self::ZOOM_SPEED * tick.as_secs_f32())
-            .clamp(Self::ZOOM_MIN, Self::ZOOM_MAX);
+        if context.input.held(input::Action::ZoomIn) {
+            camera_state.zoom = (camera_state.zoom + Self::ZOOM_SPEED * tick.as_secs_f32())
+                .clamp(Self::ZOOM_MIN, Self::ZOOM_MAX);
         }
-        if input.key_held(winit::keyboard::KeyCode::KeyQ) {
-            self.camera_state.zoom = (self.camera_state.zoom
-                - Self::ZOOM_SPEED * tick.as_secs_f32())
-            .clamp(Self::ZOOM_MIN, Self::ZOOM_MAX);
+        if context.input.held(input::Action::ZoomOut) {
+            camera_state.zoom = (camera_state.zoom - Self::ZOOM_SPEED * tick.as_secs_f32())
+                .clamp(Self::ZOOM_MIN, Self::ZOOM_MAX);
         }
 
         // NOTE: 視点の拡大・縮小の初期化
-        if input.mouse_held(winit::event::MouseButton::Middle) {
-            self.camera_state.zoom = Self::ZOOM_INIT;
+        if context.input.held(input::Action::ZoomReset) {
+            camera_state.zoom = Self::ZOOM_INIT;
         }
     }
 }
@@ -0,0 +1,99 @@
+//! ワールドの時計の機能に関するモジュール
+
+use glam::*;
+
+use crate::assets;
+
+/// ワールドの時計の機能
+///
+/// 時刻は`0.0`から`1.0`で表され、`0.0`が真夜中、`0.5`が正午である。
+/// 他のシステムは時刻を参照して、出現や成長、行動の予定を決めることができる。
+pub struct ClockSystem {
+    assets: std::rc::Rc<assets::Assets>,
+    /// 0日目の真夜中からの経過時間 (秒)
+    time: f64,
+}
+
+impl ClockSystem {
+    /// 新しい時計システムを作成する。
+    #[inline]
+    pub fn new(assets: std::rc::Rc<assets::Assets>) -> Self {
+        let clock_spec = &assets.clock_spec;
+        let time = clock_spec.start_time_of_day as f64 * clock_spec.day_length as f64;
+        Self { assets, time }
+    }
+
+    /// 時間を進める。
+    #[inline]
+    pub fn update(&mut self, tick: &std::time::Duration) {
+        self.time += tick.as_secs_f64();
+    }
+
+    /// 0日目の真夜中からの経過時間 (秒) を返す。
+    #[inline]
+    pub fn time(&self) -> f64 {
+        self.time
+    }
+
+    /// 経過時間を置き換える。
+    #[inline]
+    pub fn restore(&mut self, time: f64) {
+        self.time = time;
+    }
+
+    /// 経過した日数を返す。
+    #[inline]
+    pub fn day(&self) -> u64 {
+        (self.time / self.assets.clock_spec.day_length as f64) as u64
+    }
+
+    /// 時刻 (`0.0`から`1.0`) を返す。
+    #[inline]
+    pub fn time_of_day(&self) -> f32 {
+        (self.time / self.assets.clock_spec.day_length as f64).fract() as f32
+    }
+
+    /// 時刻が指定した範囲内にあるか真偽値を返す。
+    ///
+    /// `start`が`end`より大きい場合は真夜中をまたぐ範囲として扱う。
+    pub fn is_between(&self, start: f32, end: f32) -> bool {
+        let time_of_day = self.time_of_day();
+        if start <= end {
+            start <= time_of_day && time_of_day < end
+        } else {
+            start <= time_of_day || time_of_day < end
+        }
+    }
+
+    /// 現在の時刻における環境光の色を返す。
+    ///
+    /// 制御点の間は線形に補間し、最後の制御点から翌日の最初の制御点へ循環する。
+    pub fn ambient(&self) -> Vec3 {
+        let keys = &self.assets.clock_spec.ambient_keys;
+        let (Some(first), Some(last)) = (keys.first(), keys.last()) else {
+            return Vec3::ONE;
+        };
+
+        let time_of_day = self.time_of_day();
+        let next = keys
+            .iter()
+            .position(|key| time_of_day < key.time_of_day)
+            .unwrap_or(keys.len());
+
+        // NOTE: 前後の制御点 (日をまたぐ場合は1日分ずらす)
+        let (prev_time, prev_color) = match next {
+            0 => (last.time_of_day - 1.0, last.color),
+            _ => (keys[next - 1].time_of_day, keys[next - 1].color),
+        };
+        let (next_time, next_color) = match keys.get(next) {
+            Some(key) => (key.time_of_day, key.color),
+            None => (first.time_of_day + 1.0, first.color),
+        };
+
+        let span = next_time - prev_time;
+        if span <= 0.0 {
+            return next_color;
+        }
+        prev_color.lerp(next_color, (time_of_day - prev_time) / span)
+    }
+}
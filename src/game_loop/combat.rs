@@ -0,0 +1,318 @@
+//! 戦闘の機能に関するモジュール
+
+use glam::*;
+
+use crate::aabb::*;
+use crate::{
+    assets,
+    game_loop::{
+        component, drop, entity,
+        system::{Phase, System, WorldContext},
+    },
+};
+
+/// 戦闘の機能
+///
+/// 体力は[`component::Health`]を持つエンティティとして表される。
+pub struct CombatSystem {
+    assets: std::rc::Rc<assets::Assets>,
+}
+
+impl CombatSystem {
+    /// ダメージを受けた後の無敵時間 (秒)
+    const INVULNERABLE_TIME: f32 = 0.5;
+
+    /// ダメージを受けた時の点滅の長さ (秒)
+    const FLASH_TIME: f32 = 0.2;
+
+    /// ノックバックの初速
+    const KNOCKBACK_SPEED: f32 = 8.0;
+
+    /// 新しい戦闘システムを作成する。
+    #[inline]
+    pub fn new(assets: std::rc::Rc<assets::Assets>) -> Self {
+        Self { assets }
+    }
+
+    /// 攻撃の当たり判定を返す。
+    ///
+    /// 当たり判定は攻撃するエンティティの向いている方向に接する、一辺が`reach`の正方形である。
+    pub fn hitbox(
+        assets: &assets::Assets,
+        entity_storage: &entity::EntityStorage,
+        attacker_id: usize,
+        reach: f32,
+    ) -> Option<Aabb2> {
+        let attacker = entity_storage.get(attacker_id)?;
+        let spec = &assets.entity_specs[attacker.spec_id];
+        let facing = entity_storage
+            .components()
+            .get::<component::Facing>(attacker_id)
+            .copied()
+            .unwrap_or_default()
+            .0;
+
+        let half_size = spec.internal_size * 0.5;
+        let center = attacker.position + half_size;
+        let center = center + facing * (half_size.dot(facing.abs()) + reach * 0.5);
+        Some(aabb2(center - reach * 0.5, center + reach * 0.5))
+    }
+
+    /// 向いている方向へ攻撃し、ダメージを与えたエンティティの識別子を返す。
+    pub fn attack(
+        context: &mut WorldContext,
+        attacker_id: usize,
+        reach: f32,
+        damage: f32,
+    ) -> Vec<usize> {
+        let assets = context.assets;
+        let entity_storage = &mut *context.entity_storage;
+        let Some(hitbox) = Self::hitbox(assets, entity_storage, attacker_id, reach) else {
+            return vec![];
+        };
+        let attacker = entity_storage.get(attacker_id).unwrap();
+        let attacker_spec = &assets.entity_specs[attacker.spec_id];
+        let attacker_center = attacker.position + attacker_spec.internal_size * 0.5;
+
+        let targets = entity_storage
+            .get_internal_by_rect(hitbox)
+            .filter(|(target_id, _)| *target_id != attacker_id)
+            .filter(|(target_id, _)| {
+                entity_storage
+                    .components()
+                    .get::<component::Health>(*target_id)
+                    .is_some()
+            })
+            .map(|(target_id, target)| {
+                let spec = &assets.entity_specs[target.spec_id];
+                let center = target.position + spec.internal_size * 0.5;
+                (target_id, center - attacker_center)
+            })
+            .collect::<Vec<_>>();
+
+        targets
+            .into_iter()
+            .filter(|&(target_id, direction)| {
+                Self::damage(entity_storage, target_id, damage, direction)
+            })
+            .map(|(target_id, _)| target_id)
+            .collect()
+    }
+
+    /// エンティティにダメージを与え、与えられたか真偽値を返す。
+    ///
+    /// 無敵時間中はダメージを受けない。
+    /// ダメージを受けたエンティティは`direction`の方向へ弾き飛ばされる。
+    pub fn damage(
+        entity_storage: &mut entity::EntityStorage,
+        target_id: usize,
+        amount: f32,
+        direction: Vec2,
+    ) -> bool {
+        let components = entity_storage.components_mut();
+        let Some(health) = components.get_mut::<component::Health>(target_id) else {
+            return false;
+        };
+        if health.invulnerable > 0.0 || health.current <= 0.0 {
+            return false;
+        }
+
+        health.current -= amount;
+        health.invulnerable = Self::INVULNERABLE_TIME;
+        health.flash = Self::FLASH_TIME;
+
+        if let Some(direction) = direction.try_normalize() {
+            let knockback = direction * Self::KNOCKBACK_SPEED;
+            components.insert(target_id, component::Knockback(knockback));
+            if components.get::<component::Velocity>(target_id).is_none() {
+                components.insert(target_id, component::Velocity::default());
+            }
+        }
+
+        true
+    }
+
+    /// ダメージを受けた時の点滅の強さ (`0.0`から`1.0`) を返す。
+    pub fn flash(&self, entity_storage: &entity::EntityStorage, entity_id: usize) -> f32 {
+        entity_storage
+            .components()
+            .get::<component::Health>(entity_id)
+            .map_or(0.0, |health| health.flash / Self::FLASH_TIME)
+    }
+}
+
+impl System for CombatSystem {
+    #[inline]
+    fn phase(&self) -> Phase {
+        Phase::Simulation
+    }
+
+    /// 無敵時間を進め、体力が尽きたエンティティを倒す。
+    ///
+    /// 倒されたエンティティは取り除かれ、仕様に基づいてアイテムを落とす。
+    fn update(&mut self, context: &mut WorldContext) {
+        let delta_secs = context.tick.as_secs_f32();
+
+        let entity_ids = context
+            .entity_storage
+            .components()
+            .iter_with::<(component::Health,)>()
+            .collect::<Vec<_>>();
+
+        entity_ids.into_iter().for_each(|entity_id| {
+            let health = context
+                .entity_storage
+                .components_mut()
+                .get_mut::<component::Health>(entity_id)
+                .unwrap();
+            health.invulnerable = (health.invulnerable - delta_secs).max(0.0);
+            health.flash = (health.flash - delta_secs).max(0.0);
+            if health.current > 0.0 {
+                return;
+            }
+
+            let Some(entity) = context.entity_storage.remove(entity_id) else {
+                return;
+            };
+            let spec = &self.assets.entity_specs[entity.spec_id];
+            let center = entity.position + spec.internal_size * 0.5;
+            drop::ItemDropSystem::spawn_loot(context, &spec.loot, center);
+        });
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::game_loop::GameLoop;
+
+    /// 生成されたブロックとプレイヤー以外のエンティティを取り除いたゲームループを返す。
+    fn empty_game_loop() -> (GameLoop, usize) {
+        let assets = assets::Assets::new("assets/assets.json");
+        let mut game_loop = GameLoop::new(std::rc::Rc::new(assets), 0);
+        game_loop.update(&Default::default(), &GameLoop::TICK);
+
+        let player_id = game_loop.player_state.as_ref().unwrap().entity_id;
+        game_loop.block_storage.restore(std::iter::empty());
+        let others = game_loop
+            .entity_storage
+            .iter()
+            .map(|(id, _)| id)
+            .filter(|id| *id != player_id)
+            .collect::<Vec<_>>();
+        others.into_iter().for_each(|id| {
+            game_loop.entity_storage.remove(id);
+        });
+        (game_loop, player_id)
+    }
+
+    /// 同じ入力で指定した回数のティックを実行する。
+    fn run(game_loop: &mut GameLoop, ticks: u32) {
+        (0..ticks).for_each(|_| {
+            game_loop.update(&Default::default(), &GameLoop::TICK);
+            game_loop.discard_changes();
+        });
+    }
+
+    fn health(game_loop: &GameLoop, entity_id: usize) -> component::Health {
+        *game_loop
+            .entity_storage
+            .components()
+            .get::<component::Health>(entity_id)
+            .unwrap()
+    }
+
+    #[test]
+    fn damage_knocks_back_with_invulnerability() {
+        let (mut game_loop, player_id) = empty_game_loop();
+        let max = health(&game_loop, player_id).max;
+        let start = game_loop.entity_storage.get(player_id).unwrap().position;
+
+        let storage = &mut game_loop.entity_storage;
+        assert!(CombatSystem::damage(storage, player_id, 3.0, Vec2::X));
+        assert!(storage
+            .components()
+            .get::<component::Knockback>(player_id)
+            .is_some());
+
+        // NOTE: 無敵時間中はダメージを受けない。
+        assert!(!CombatSystem::damage(storage, player_id, 3.0, Vec2::X));
+        assert_eq!(health(&game_loop, player_id).current, max - 3.0);
+        assert!(
+            game_loop
+                .combat_sys
+                .flash(&game_loop.entity_storage, player_id)
+                > 0.0
+        );
+
+        // NOTE: 無敵時間の間に弾き飛ばされる。
+        let ticks = (CombatSystem::INVULNERABLE_TIME / GameLoop::TICK.as_secs_f32()) as u32 + 1;
+        run(&mut game_loop, ticks);
+        let delta = game_loop.entity_storage.get(player_id).unwrap().position - start;
+        assert!(delta.x > 0.5, "moved {:?}", delta);
+        assert!(delta.y.abs() < 1e-3, "moved {:?}", delta);
+        assert_eq!(
+            game_loop
+                .combat_sys
+                .flash(&game_loop.entity_storage, player_id),
+            0.0
+        );
+
+        // NOTE: 無敵時間が過ぎると再びダメージを受け、弾き飛ばしは減速して停止する。
+        let storage = &mut game_loop.entity_storage;
+        assert!(CombatSystem::damage(storage, player_id, 3.0, Vec2::ZERO));
+        assert_eq!(health(&game_loop, player_id).current, max - 6.0);
+
+        run(&mut game_loop, 30);
+        assert!(game_loop
+            .entity_storage
+            .components()
+            .get::<component::Knockback>(player_id)
+            .is_none());
+    }
+
+    #[test]
+    fn defeated_entity_drops_loot() {
+        let (mut game_loop, player_id) = empty_game_loop();
+        let assets = game_loop.assets.clone();
+        let sheep = assets
+            .entity_specs
+            .iter()
+            .position(|spec| spec.label == "sheep")
+            .unwrap();
+        let wool = assets
+            .item_specs
+            .iter()
+            .position(|spec| spec.label == "wool")
+            .unwrap();
+
+        let position = game_loop.entity_storage.get(player_id).unwrap().position;
+        let entity = entity::Entity::new(sheep, position + vec2(20.0, 0.0));
+        let entity_id = game_loop.entity_storage.spawn(entity).unwrap();
+
+        let max = health(&game_loop, entity_id).max;
+        let storage = &mut game_loop.entity_storage;
+        assert!(CombatSystem::damage(storage, entity_id, max, Vec2::ZERO));
+        run(&mut game_loop, 1);
+
+        // NOTE: 識別子は落ちたアイテムに再利用され得るため、仕様で確認する。
+        assert!(game_loop
+            .entity_storage
+            .iter()
+            .all(|(_, entity)| entity.spec_id != sheep));
+        let drops = game_loop
+            .entity_storage
+            .components()
+            .iter_with::<(component::ItemDrop,)>()
+            .map(|entity_id| {
+                game_loop
+                    .entity_storage
+                    .components()
+                    .get::<component::ItemDrop>(entity_id)
+                    .unwrap()
+                    .stack
+            })
+            .collect::<Vec<_>>();
+        assert!(drops.iter().any(|stack| stack.item_spec_id == wool));
+    }
+}
@@ -0,0 +1,442 @@
+//! エンティティのコンポーネントに関するモジュール
+
+use std::any::{Any, TypeId};
+use std::collections::VecDeque;
+
+use ahash::HashMap;
+use glam::*;
+
+use crate::{assets, game_loop::inventory};
+
+/// プレイヤーが操作するエンティティを表すコンポーネント
+#[derive(Clone, Copy, Default)]
+pub struct PlayerControlled;
+
+/// エンティティの速度を表すコンポーネント
+#[derive(Clone, Copy, Default)]
+pub struct Velocity(pub Vec2);
+
+/// 速度の減衰率 (1秒あたり) を表すコンポーネント
+#[derive(Clone, Copy, Default)]
+pub struct Damping(pub f32);
+
+/// 向いている方向 (正規化済み) を表すコンポーネント
+#[derive(Clone, Copy)]
+pub struct Facing(pub Vec2);
+
+impl Default for Facing {
+    #[inline]
+    fn default() -> Self {
+        Self(Vec2::NEG_Y)
+    }
+}
+
+/// ノックバックによる速度を表すコンポーネント
+///
+/// 通常の速度に加算され、時間と共に減衰する。
+#[derive(Clone, Copy, Default)]
+pub struct Knockback(pub Vec2);
+
+/// 体力を表すコンポーネント
+#[derive(Clone, Copy)]
+pub struct Health {
+    pub current: f32,
+    pub max: f32,
+    /// 無敵時間の残り (秒)
+    pub invulnerable: f32,
+    /// ダメージを受けた時の点滅の残り (秒)
+    pub flash: f32,
+}
+
+impl Health {
+    /// 最大体力の新しい体力を作成する。
+    #[inline]
+    pub fn new(max: f32) -> Self {
+        Self {
+            current: max,
+            max,
+            invulnerable: 0.0,
+            flash: 0.0,
+        }
+    }
+}
+
+/// 投射物を表すコンポーネント
+#[derive(Clone, Copy)]
+pub struct Projectile {
+    pub projectile_spec_id: usize,
+    /// 発射したエンティティ (命中の対象から除く)
+    pub owner: Option<usize>,
+    pub velocity: Vec2,
+    /// 消滅するまでの残り時間 (秒)
+    pub remaining: f32,
+    /// 命中した位置に留まっているか
+    pub stuck: bool,
+    /// 刺さっているエンティティとその位置からの相対位置
+    pub attached: Option<(usize, Vec2)>,
+}
+
+/// 落ちているアイテムを表すコンポーネント
+#[derive(Clone, Copy)]
+pub struct ItemDrop {
+    pub stack: inventory::ItemStack,
+    /// 消滅するまでの残り時間 (秒)
+    pub remaining: f32,
+}
+
+/// 経路に沿って移動するエンティティを表すコンポーネント
+#[derive(Clone, Default)]
+pub struct PathFollow {
+    /// 残りの通過点 (エンティティの位置)
+    pub waypoints: VecDeque<Vec2>,
+    /// 移動速度
+    pub speed: f32,
+}
+
+/// 振る舞いの状態機械に従って行動するエンティティを表すコンポーネント
+#[derive(Clone, Copy)]
+pub struct Behaviour {
+    pub behaviour_spec_id: usize,
+    pub state_id: usize,
+    /// 現在の状態に入ってからの経過時間 (秒)
+    pub elapsed: f32,
+    /// 徘徊・巡回の起点
+    pub origin: Vec2,
+    /// 現在の状態の行動を開始したか
+    pub started: bool,
+    /// 現在の状態の行動が完了したか
+    pub done: bool,
+    /// 行動の対象のブロック
+    pub target_block: Option<usize>,
+    /// 行動毎のタイマー (秒)
+    pub timer: f32,
+    /// 次に巡回する位置の番号
+    pub patrol_index: usize,
+}
+
+impl Behaviour {
+    /// 初期状態の新しい振る舞いを作成する。
+    #[inline]
+    pub fn new(behaviour_spec: &assets::BehaviourSpec, origin: Vec2) -> Self {
+        Self {
+            behaviour_spec_id: behaviour_spec.id,
+            state_id: behaviour_spec.initial_state_id,
+            elapsed: 0.0,
+            origin,
+            started: false,
+            done: false,
+            target_block: None,
+            timer: 0.0,
+            patrol_index: 0,
+        }
+    }
+
+    /// 指定した状態へ遷移する。
+    ///
+    /// 行動の進行は全て初期化される。
+    pub fn enter(&mut self, state_id: usize) {
+        self.state_id = state_id;
+        self.elapsed = 0.0;
+        self.started = false;
+        self.done = false;
+        self.target_block = None;
+        self.timer = 0.0;
+    }
+}
+
+/// 1種類のコンポーネントを密に保持する列
+pub struct Column<T> {
+    ids: Vec<usize>,
+    values: Vec<T>,
+    sparse: HashMap<usize, usize>,
+}
+
+impl<T> Default for Column<T> {
+    #[inline]
+    fn default() -> Self {
+        Self {
+            ids: Default::default(),
+            values: Default::default(),
+            sparse: Default::default(),
+        }
+    }
+}
+
+impl<T> Column<T> {
+    /// コンポーネントを追加し、置き換えられたコンポーネントを返す。
+    pub fn insert(&mut self, id: usize, value: T) -> Option<T> {
+        if let Some(&index) = self.sparse.get(&id) {
+            return Some(std::mem::replace(&mut self.values[index], value));
+        }
+
+        self.sparse.insert(id, self.values.len());
+        self.ids.push(id);
+        self.values.push(value);
+        None
+    }
+
+    /// コンポーネントを削除し、そのコンポーネントを返す。
+    pub fn remove(&mut self, id: usize) -> Option<T> {
+        let index = self.sparse.remove(&id)?;
+
+        // NOTE: 末尾の要素で穴を埋めて密な配置を保つ。
+        self.ids.swap_remove(index);
+        let value = self.values.swap_remove(index);
+        if let Some(&moved_id) = self.ids.get(index) {
+            self.sparse.insert(moved_id, index);
+        }
+
+        Some(value)
+    }
+
+    /// 指定した識別子のコンポーネントが存在するか真偽値を返す。
+    #[inline]
+    pub fn contains(&self, id: usize) -> bool {
+        self.sparse.contains_key(&id)
+    }
+
+    /// 指定した識別子のコンポーネントの参照を返す。
+    #[inline]
+    pub fn get(&self, id: usize) -> Option<&T> {
+        self.sparse.get(&id).map(|&index| &self.values[index])
+    }
+
+    /// 指定した識別子のコンポーネントの可変参照を返す。
+    #[inline]
+    pub fn get_mut(&mut self, id: usize) -> Option<&mut T> {
+        self.sparse.get(&id).map(|&index| &mut self.values[index])
+    }
+
+    /// 全てのコンポーネントの識別子と参照を返す。
+    #[inline]
+    pub fn iter(&self) -> impl Iterator<Item = (usize, &T)> {
+        self.ids.iter().copied().zip(self.values.iter())
+    }
+
+    /// 全てのコンポーネントの識別子と可変参照を返す。
+    #[inline]
+    pub fn iter_mut(&mut self) -> impl Iterator<Item = (usize, &mut T)> {
+        self.ids.iter().copied().zip(self.values.iter_mut())
+    }
+
+    /// コンポーネントの数を返す。
+    #[inline]
+    pub fn len(&self) -> usize {
+        self.values.len()
+    }
+
+    /// コンポーネントが存在しないか真偽値を返す。
+    #[inline]
+    pub fn is_empty(&self) -> bool {
+        self.values.is_empty()
+    }
+}
+
+/// 型を隠蔽した列
+trait AnyColumn {
+    fn remove_any(&mut self, id: usize);
+
+    fn contains_any(&self, id: usize) -> bool;
+
+    fn ids(&self) -> &[usize];
+
+    fn as_any(&self) -> &dyn Any;
+
+    fn as_any_mut(&mut self) -> &mut dyn Any;
+}
+
+impl<T: 'static> AnyColumn for Column<T> {
+    #[inline]
+    fn remove_any(&mut self, id: usize) {
+        self.remove(id);
+    }
+
+    #[inline]
+    fn contains_any(&self, id: usize) -> bool {
+        self.contains(id)
+    }
+
+    #[inline]
+    fn ids(&self) -> &[usize] {
+        &self.ids
+    }
+
+    #[inline]
+    fn as_any(&self) -> &dyn Any {
+        self
+    }
+
+    #[inline]
+    fn as_any_mut(&mut self) -> &mut dyn Any {
+        self
+    }
+}
+
+/// コンポーネントの型の組
+///
+/// [`ComponentStorage::iter_with`]で全てのコンポーネントを持つエンティティを探すために使用する。
+pub trait ComponentSet {
+    /// 組に含まれる型の識別子を返す。
+    fn type_ids() -> Vec<TypeId>;
+}
+
+macro_rules! impl_component_set {
+    ($($t:ident),+) => {
+        impl<$($t: 'static),+> ComponentSet for ($($t,)+) {
+            #[inline]
+            fn type_ids() -> Vec<TypeId> {
+                vec![$(TypeId::of::<$t>()),+]
+            }
+        }
+    };
+}
+
+impl_component_set!(A);
+impl_component_set!(A, B);
+impl_component_set!(A, B, C);
+impl_component_set!(A, B, C, D);
+
+/// エンティティのコンポーネントを型毎の列で保持する機能
+#[derive(Default)]
+pub struct ComponentStorage {
+    columns: HashMap<TypeId, Box<dyn AnyColumn>>,
+}
+
+impl ComponentStorage {
+    /// コンポーネントを追加し、置き換えられたコンポーネントを返す。
+    pub fn insert<T: 'static>(&mut self, id: usize, value: T) -> Option<T> {
+        self.columns
+            .entry(TypeId::of::<T>())
+            .or_insert_with(|| Box::<Column<T>>::default())
+            .as_any_mut()
+            .downcast_mut::<Column<T>>()
+            .unwrap()
+            .insert(id, value)
+    }
+
+    /// コンポーネントを削除し、そのコンポーネントを返す。
+    #[inline]
+    pub fn remove<T: 'static>(&mut self, id: usize) -> Option<T> {
+        self.column_mut::<T>()?.remove(id)
+    }
+
+    /// 指定した識別子の全てのコンポーネントを削除する。
+    pub fn remove_all(&mut self, id: usize) {
+        self.columns
+            .values_mut()
+            .for_each(|column| column.remove_any(id));
+    }
+
+    /// 全てのコンポーネントを削除する。
+    #[inline]
+    pub fn clear(&mut self) {
+        self.columns.clear();
+    }
+
+    /// 指定した識別子のコンポーネントの参照を返す。
+    #[inline]
+    pub fn get<T: 'static>(&self, id: usize) -> Option<&T> {
+        self.column::<T>()?.get(id)
+    }
+
+    /// 指定した識別子のコンポーネントの可変参照を返す。
+    #[inline]
+    pub fn get_mut<T: 'static>(&mut self, id: usize) -> Option<&mut T> {
+        self.column_mut::<T>()?.get_mut(id)
+    }
+
+    /// 指定した型の列の参照を返す。
+    #[inline]
+    pub fn column<T: 'static>(&self) -> Option<&Column<T>> {
+        self.columns
+            .get(&TypeId::of::<T>())?
+            .as_any()
+            .downcast_ref::<Column<T>>()
+    }
+
+    /// 指定した型の列の可変参照を返す。
+    #[inline]
+    pub fn column_mut<T: 'static>(&mut self) -> Option<&mut Column<T>> {
+        self.columns
+            .get_mut(&TypeId::of::<T>())?
+            .as_any_mut()
+            .downcast_mut::<Column<T>>()
+    }
+
+    /// 指定した型のコンポーネントを持つ全ての識別子と参照を返す。
+    #[inline]
+    pub fn iter<T: 'static>(&self) -> impl Iterator<Item = (usize, &T)> {
+        self.column::<T>()
+            .into_iter()
+            .flat_map(|column| column.iter())
+    }
+
+    /// 指定した型のコンポーネントを持つ全ての識別子と可変参照を返す。
+    #[inline]
+    pub fn iter_mut<T: 'static>(&mut self) -> impl Iterator<Item = (usize, &mut T)> {
+        self.column_mut::<T>()
+            .into_iter()
+            .flat_map(|column| column.iter_mut())
+    }
+
+    /// 指定した組の全てのコンポーネントを持つ識別子を返す。
+    ///
+    /// 最も要素の少ない列を走査し、残りの列で絞り込む。
+    pub fn iter_with<S: ComponentSet>(&self) -> impl Iterator<Item = usize> + '_ {
+        let columns = S::type_ids()
+            .into_iter()
+            .map(|type_id| self.columns.get(&type_id).map(|column| column.as_ref()))
+            .collect::<Option<Vec<_>>>()
+            .unwrap_or_default();
+
+        let smallest = columns
+            .iter()
+            .copied()
+            .min_by_key(|column| column.ids().len());
+
+        smallest
+            .into_iter()
+            .flat_map(|column| column.ids().iter().copied())
+            .filter(move |&id| columns.iter().all(|column| column.contains_any(id)))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn remove_keeps_column_dense() {
+        let mut components = ComponentStorage::default();
+        (0..4).for_each(|id| {
+            components.insert(id, Damping(id as f32));
+        });
+        components.insert(1, Facing(Vec2::X));
+        components.insert(3, Facing(Vec2::Y));
+
+        // NOTE: 末尾の要素が削除した位置へ移動しても識別子から参照できる。
+        assert_eq!(components.remove::<Damping>(1).map(|d| d.0), Some(1.0));
+        assert_eq!(components.remove::<Damping>(1).map(|d| d.0), None);
+        let column = components.column::<Damping>().unwrap();
+        assert_eq!(column.len(), 3);
+        assert_eq!(
+            column.iter().map(|(id, d)| (id, d.0)).collect::<Vec<_>>(),
+            vec![(0, 0.0), (3, 3.0), (2, 2.0)]
+        );
+        [0, 2, 3].into_iter().for_each(|id| {
+            assert_eq!(components.get::<Damping>(id).map(|d| d.0), Some(id as f32));
+        });
+
+        // NOTE: 末尾の要素を削除しても他の要素は移動しない。
+        assert!(components.remove::<Damping>(2).is_some());
+        assert_eq!(components.get::<Damping>(3).map(|d| d.0), Some(3.0));
+
+        components.remove_all(3);
+        assert_eq!(
+            components.iter_with::<(Damping,)>().collect::<Vec<_>>(),
+            vec![0]
+        );
+        assert_eq!(components.iter_with::<(Damping, Facing)>().count(), 0);
+        assert_eq!(components.get::<Facing>(1).map(|f| f.0), Some(Vec2::X));
+    }
+}
@@ -0,0 +1,460 @@
+//! 開発者コンソールに関するモジュール
+
+use std::collections::VecDeque;
+
+use crate::assets;
+
+/// コマンドの引数の種類
+#[derive(Clone, Debug)]
+pub enum ArgKind {
+    Int,
+    Float,
+    Text,
+    /// ブロックの仕様のラベル
+    BlockSpec,
+    /// エンティティの仕様のラベル
+    EntitySpec,
+    /// アイテムの仕様のラベル
+    ItemSpec,
+    /// レシピの仕様のラベル
+    RecipeSpec,
+    /// 列挙された語のいずれか
+    Choice(Vec<String>),
+}
+
+impl ArgKind {
+    /// 補完の候補を返す。
+    fn candidates(&self, assets: &assets::Assets) -> Vec<String> {
+        match self {
+            ArgKind::BlockSpec => assets.block_specs.iter().map(|s| s.label.clone()).collect(),
+            ArgKind::EntitySpec => assets
+                .entity_specs
+                .iter()
+                .map(|s| s.label.clone())
+                .collect(),
+            ArgKind::ItemSpec => assets.item_specs.iter().map(|s| s.label.clone()).collect(),
+            ArgKind::RecipeSpec => assets
+                .recipe_specs
+                .iter()
+                .map(|s| s.label.clone())
+                .collect(),
+            ArgKind::Choice(choices) => choices.clone(),
+            ArgKind::Int | ArgKind::Float | ArgKind::Text => vec![],
+        }
+    }
+
+    /// 文字列を引数の値へ変換する。
+    fn parse(&self, assets: &assets::Assets, token: &str) -> Option<Arg> {
+        match self {
+            ArgKind::Int => token.parse().ok().map(Arg::Int),
+            ArgKind::Float => token.parse().ok().map(Arg::Float),
+            ArgKind::Text => Some(Arg::Text(token.to_string())),
+            // NOTE: 仕様のラベルの候補は仕様の識別子の順に並ぶ。
+            ArgKind::BlockSpec | ArgKind::EntitySpec | ArgKind::ItemSpec | ArgKind::RecipeSpec => {
+                self.candidates(assets)
+                    .iter()
+                    .position(|label| label == token)
+                    .map(Arg::Spec)
+            }
+            ArgKind::Choice(choices) => choices
+                .iter()
+                .any(|choice| choice == token)
+                .then(|| Arg::Text(token.to_string())),
+        }
+    }
+}
+
+/// コマンドの引数の仕様
+#[derive(Clone, Debug)]
+pub struct ArgSpec {
+    pub name: String,
+    pub kind: ArgKind,
+    pub optional: bool,
+}
+
+/// コマンドの仕様
+#[derive(Clone, Debug)]
+pub struct CommandSpec {
+    pub name: String,
+    pub description: String,
+    pub args: Vec<ArgSpec>,
+}
+
+impl CommandSpec {
+    /// 引数の無いコマンドの仕様を作成する。
+    pub fn new(name: &str, description: &str) -> Self {
+        Self {
+            name: name.to_string(),
+            description: description.to_string(),
+            args: vec![],
+        }
+    }
+
+    /// 必須の引数を追加する。
+    pub fn arg(mut self, name: &str, kind: ArgKind) -> Self {
+        let optional = false;
+        let name = name.to_string();
+        self.args.push(ArgSpec {
+            name,
+            kind,
+            optional,
+        });
+        self
+    }
+
+    /// 省略可能な引数を追加する。(必須の引数の後に並べる)
+    pub fn optional(mut self, name: &str, kind: ArgKind) -> Self {
+        let optional = true;
+        let name = name.to_string();
+        self.args.push(ArgSpec {
+            name,
+            kind,
+            optional,
+        });
+        self
+    }
+
+    /// 使い方を表す文字列を返す。(例: `spawn <label> [x] [y]`)
+    pub fn usage(&self) -> String {
+        self.args.iter().fold(self.name.clone(), |usage, arg| {
+            if arg.optional {
+                format!("{} [{}]", usage, arg.name)
+            } else {
+                format!("{} <{}>", usage, arg.name)
+            }
+        })
+    }
+
+    /// 引数を解析する。
+    pub fn parse(&self, assets: &assets::Assets, tokens: &[&str]) -> Result<Args, CommandError> {
+        let usage = || CommandError::Usage(self.usage());
+        if tokens.len() > self.args.len() {
+            return Err(usage());
+        }
+
+        let args = self
+            .args
+            .iter()
+            .enumerate()
+            .map(|(i, arg_spec)| match tokens.get(i) {
+                Some(token) => arg_spec.kind.parse(assets, token).map(Some).ok_or_else(|| {
+                    CommandError::Failed(format!("invalid {}: {}", arg_spec.name, token))
+                }),
+                None if arg_spec.optional => Ok(None),
+                None => Err(usage()),
+            })
+            .collect::<Result<Vec<_>, _>>()?;
+        Ok(Args(args))
+    }
+}
+
+/// 解析された引数の値
+#[derive(Clone, Debug)]
+pub enum Arg {
+    Int(i64),
+    Float(f32),
+    Text(String),
+    /// 仕様の識別子
+    Spec(usize),
+}
+
+/// 解析されたコマンドの引数 (省略された引数は`None`)
+#[derive(Clone, Debug, Default)]
+pub struct Args(Vec<Option<Arg>>);
+
+impl Args {
+    #[inline]
+    pub fn int(&self, index: usize) -> Option<i64> {
+        match self.0.get(index)? {
+            Some(Arg::Int(value)) => Some(*value),
+            _ => None,
+        }
+    }
+
+    /// 小数を返す。(整数の引数も受け付ける)
+    #[inline]
+    pub fn float(&self, index: usize) -> Option<f32> {
+        match self.0.get(index)? {
+            Some(Arg::Float(value)) => Some(*value),
+            Some(Arg::Int(value)) => Some(*value as f32),
+            _ => None,
+        }
+    }
+
+    #[inline]
+    pub fn text(&self, index: usize) -> Option<&str> {
+        match self.0.get(index)? {
+            Some(Arg::Text(value)) => Some(value),
+            _ => None,
+        }
+    }
+
+    #[inline]
+    pub fn spec(&self, index: usize) -> Option<usize> {
+        match self.0.get(index)? {
+            Some(Arg::Spec(value)) => Some(*value),
+            _ => None,
+        }
+    }
+}
+
+/// コマンドの実行に関するエラー
+#[derive(Debug)]
+pub enum CommandError {
+    /// 登録されていないコマンド
+    Unknown(String),
+    /// 引数の数が誤っている (使い方を含む)
+    Usage(String),
+    /// 引数が不正、または実行に失敗した
+    Failed(String),
+}
+
+impl std::fmt::Display for CommandError {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            CommandError::Unknown(name) => write!(f, "unknown command: {}", name),
+            CommandError::Usage(usage) => write!(f, "usage: {}", usage),
+            CommandError::Failed(message) => write!(f, "{}", message),
+        }
+    }
+}
+
+impl std::error::Error for CommandError {}
+
+/// 入力を補完する。
+///
+/// 最初の語はコマンドの名前、以降の語は引数の種類に応じた候補で補完する。
+/// 候補が一意に決まらない場合は共通する部分まで補完し、候補を返す。
+pub fn complete(
+    assets: &assets::Assets,
+    command_specs: &[CommandSpec],
+    line: &str,
+) -> (String, Vec<String>) {
+    let (head, prefix) = match line.rfind(' ') {
+        Some(index) => line.split_at(index + 1),
+        None => ("", line),
+    };
+    let tokens = head.split_whitespace().collect::<Vec<_>>();
+
+    let candidates = match tokens.split_first() {
+        None => command_specs.iter().map(|spec| spec.name.clone()).collect(),
+        Some((name, args)) => command_specs
+            .iter()
+            .find(|spec| spec.name == *name)
+            .and_then(|spec| spec.args.get(args.len()))
+            .map_or(vec![], |arg_spec| arg_spec.kind.candidates(assets)),
+    };
+    let mut candidates = candidates
+        .into_iter()
+        .filter(|candidate| candidate.starts_with(prefix))
+        .collect::<Vec<_>>();
+    candidates.sort();
+    candidates.dedup();
+
+    match candidates.as_slice() {
+        [] => (line.to_string(), vec![]),
+        [candidate] => (format!("{}{} ", head, candidate), vec![]),
+        [first, rest @ ..] => {
+            // NOTE: 全ての候補に共通する接頭辞
+            let common = rest.iter().fold(first.as_str(), |common, candidate| {
+                let len = common
+                    .char_indices()
+                    .zip(candidate.chars())
+                    .take_while(|((_, a), b)| a == b)
+                    .last()
+                    .map_or(0, |((i, a), _)| i + a.len_utf8());
+                &common[..len]
+            });
+            (format!("{}{}", head, common), candidates.clone())
+        }
+    }
+}
+
+/// 開発者コンソールの状態
+#[derive(Default)]
+pub struct Console {
+    open: bool,
+    line: String,
+    output: VecDeque<String>,
+    history: Vec<String>,
+    /// 参照中の履歴の位置 (入力中の行を編集している場合は`None`)
+    history_index: Option<usize>,
+}
+
+impl Console {
+    /// 保持する出力の最大行数
+    const MAX_OUTPUT: usize = 256;
+
+    #[inline]
+    pub fn is_open(&self) -> bool {
+        self.open
+    }
+
+    #[inline]
+    pub fn set_open(&mut self, open: bool) {
+        self.open = open;
+    }
+
+    /// 入力中の行を返す。
+    #[inline]
+    pub fn line(&self) -> &str {
+        &self.line
+    }
+
+    /// 入力中の行を置き換える。
+    #[inline]
+    pub fn set_line(&mut self, line: String) {
+        self.line = line;
+        self.history_index = None;
+    }
+
+    /// 出力された行を古い順に返す。
+    #[inline]
+    pub fn output(&self) -> impl Iterator<Item = &str> {
+        self.output.iter().map(String::as_str)
+    }
+
+    /// 入力中の行の末尾に文字を追加する。
+    #[inline]
+    pub fn push(&mut self, c: char) {
+        self.line.push(c);
+        self.history_index = None;
+    }
+
+    /// 入力中の行の末尾の文字を削除する。
+    #[inline]
+    pub fn pop(&mut self) {
+        self.line.pop();
+        self.history_index = None;
+    }
+
+    /// 入力中の行を1つ前の履歴で置き換える。
+    pub fn previous(&mut self) {
+        let index = match self.history_index {
+            Some(index) => index.saturating_sub(1),
+            None if self.history.is_empty() => return,
+            None => self.history.len() - 1,
+        };
+        self.line = self.history[index].clone();
+        self.history_index = Some(index);
+    }
+
+    /// 入力中の行を1つ後の履歴で置き換える。(最新の履歴の後は空にする)
+    pub fn next(&mut self) {
+        let Some(index) = self.history_index else {
+            return;
+        };
+        if index + 1 < self.history.len() {
+            self.line = self.history[index + 1].clone();
+            self.history_index = Some(index + 1);
+        } else {
+            self.line.clear();
+            self.history_index = None;
+        }
+    }
+
+    /// 入力中の行を取り出し、履歴と出力へ記録する。
+    pub fn take_line(&mut self) -> String {
+        let line = std::mem::take(&mut self.line);
+        self.history_index = None;
+        if !line.trim().is_empty() && self.history.last() != Some(&line) {
+            self.history.push(line.clone());
+        }
+        self.print(&format!("> {}", line));
+        line
+    }
+
+    /// 出力へ文字列を追加する。(改行で複数行に分ける)
+    pub fn print(&mut self, text: &str) {
+        text.lines().for_each(|line| {
+            self.output.push_back(line.to_string());
+        });
+        while self.output.len() > Self::MAX_OUTPUT {
+            self.output.pop_front();
+        }
+    }
+}
+
+/// 描写に使用するコンソールの状態
+#[derive(Clone, Default)]
+pub struct ConsoleView {
+    /// 出力された行 (古い順)
+    pub output: Vec<String>,
+    /// 入力中の行
+    pub line: String,
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn command_specs() -> Vec<CommandSpec> {
+        let overlays = ["chunks", "colliders", "paths"].map(String::from).to_vec();
+        vec![
+            CommandSpec::new("help", "list commands"),
+            CommandSpec::new("save", "save the world"),
+            CommandSpec::new("seed", "change the seed").optional("seed", ArgKind::Int),
+            CommandSpec::new("place", "place a block").arg("label", ArgKind::BlockSpec),
+            CommandSpec::new("overlay", "toggle an overlay").arg("name", ArgKind::Choice(overlays)),
+        ]
+    }
+
+    #[test]
+    fn complete_command_name() {
+        let assets = assets::Assets::new("assets/assets.json");
+        let command_specs = command_specs();
+
+        assert_eq!(
+            complete(&assets, &command_specs, "he"),
+            ("help ".to_string(), vec![])
+        );
+        assert_eq!(
+            complete(&assets, &command_specs, "s"),
+            (
+                "s".to_string(),
+                vec!["save".to_string(), "seed".to_string()]
+            )
+        );
+        assert_eq!(
+            complete(&assets, &command_specs, "x"),
+            ("x".to_string(), vec![])
+        );
+    }
+
+    #[test]
+    fn complete_args() {
+        let assets = assets::Assets::new("assets/assets.json");
+        let command_specs = command_specs();
+
+        // NOTE: 共通する接頭辞まで補完し、候補を整列して返す。
+        assert_eq!(
+            complete(&assets, &command_specs, "overlay c"),
+            (
+                "overlay c".to_string(),
+                vec!["chunks".to_string(), "colliders".to_string()]
+            )
+        );
+        assert_eq!(
+            complete(&assets, &command_specs, "overlay ch"),
+            ("overlay chunks ".to_string(), vec![])
+        );
+        assert_eq!(
+            complete(&assets, &command_specs, "place mixR"),
+            ("place mixRock ".to_string(), vec![])
+        );
+
+        // NOTE: 候補を持たない引数・余分な引数・未知のコマンドは変更しない。
+        assert_eq!(
+            complete(&assets, &command_specs, "seed 1"),
+            ("seed 1".to_string(), vec![])
+        );
+        assert_eq!(
+            complete(&assets, &command_specs, "help x"),
+            ("help x".to_string(), vec![])
+        );
+        assert_eq!(
+            complete(&assets, &command_specs, "unknown x"),
+            ("unknown x".to_string(), vec![])
+        );
+    }
+}
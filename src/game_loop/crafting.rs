@@ -0,0 +1,232 @@
+//! クラフトの機能に関するモジュール
+
+use ahash::HashSet;
+use glam::*;
+
+use crate::aabb::*;
+use crate::{
+    assets,
+    game_loop::{block, inventory},
+};
+
+/// クラフトに関するエラー
+#[derive(Debug)]
+pub enum CraftError {
+    /// プレイヤーが存在しない。
+    Player,
+    /// 存在しないレシピである。
+    Recipe(usize),
+    /// 近くに必要なブロックが存在しない。
+    Block(usize),
+    /// 材料が足りない。
+    Items,
+    /// 生成したアイテムがインベントリに入りきらない。
+    Space,
+}
+
+impl std::fmt::Display for CraftError {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            CraftError::Player => write!(f, "no player"),
+            CraftError::Recipe(id) => write!(f, "unknown recipe: #{}", id),
+            CraftError::Block(id) => write!(f, "required block is not nearby: #{}", id),
+            CraftError::Items => write!(f, "not enough items"),
+            CraftError::Space => write!(f, "not enough inventory space"),
+        }
+    }
+}
+
+impl std::error::Error for CraftError {}
+
+/// クラフトの機能
+pub struct CraftingSystem {
+    assets: std::rc::Rc<assets::Assets>,
+}
+
+impl CraftingSystem {
+    /// 必要なブロックを探す範囲 (中心からの距離)
+    const NEARBY_DISTANCE: i32 = 4;
+
+    /// 新しいクラフトシステムを作成する。
+    #[inline]
+    pub fn new(assets: std::rc::Rc<assets::Assets>) -> Self {
+        Self { assets }
+    }
+
+    /// 指定した位置の近くに存在するブロックの仕様の識別子を返す。
+    pub fn nearby_block_spec_ids(
+        &self,
+        block_storage: &block::BlockStorage,
+        position: Vec2,
+    ) -> HashSet<usize> {
+        let center = position.floor().as_ivec2();
+        let rect = iaabb2(
+            center - Self::NEARBY_DISTANCE,
+            center + Self::NEARBY_DISTANCE + 1,
+        );
+        block_storage
+            .get_internal_by_rect(rect)
+            .map(|(_, block)| block.spec_id)
+            .collect()
+    }
+
+    /// レシピが実行可能か判定する。
+    pub fn check(
+        &self,
+        inventory: &inventory::Inventory,
+        nearby_block_spec_ids: &HashSet<usize>,
+        recipe_spec_id: usize,
+    ) -> Result<(), CraftError> {
+        let recipe_spec = self
+            .assets
+            .recipe_specs
+            .get(recipe_spec_id)
+            .ok_or(CraftError::Recipe(recipe_spec_id))?;
+
+        if let Some(block_spec_id) = recipe_spec.required_block_spec_id {
+            if !nearby_block_spec_ids.contains(&block_spec_id) {
+                return Err(CraftError::Block(block_spec_id));
+            }
+        }
+
+        // NOTE: 同じアイテムが複数回現れる場合も合計で判定する。
+        let enough = recipe_spec.inputs.iter().all(|input| {
+            let required = recipe_spec
+                .inputs
+                .iter()
+                .filter(|other| other.item_spec_id == input.item_spec_id)
+                .map(|other| other.count)
+                .sum::<u32>();
+            inventory.count(input.item_spec_id) >= required
+        });
+        if !enough {
+            return Err(CraftError::Items);
+        }
+
+        Ok(())
+    }
+
+    /// 実行可能な全てのレシピの仕様の識別子を返す。
+    pub fn craftable<'a>(
+        &'a self,
+        inventory: &'a inventory::Inventory,
+        nearby_block_spec_ids: &'a HashSet<usize>,
+    ) -> impl Iterator<Item = usize> + 'a {
+        self.assets
+            .recipe_specs
+            .iter()
+            .map(|recipe_spec| recipe_spec.id)
+            .filter(|&id| self.check(inventory, nearby_block_spec_ids, id).is_ok())
+    }
+
+    /// レシピを実行し、インベントリを更新する。
+    ///
+    /// 失敗した場合、インベントリは変更されない。
+    pub fn craft(
+        &self,
+        inventory: &mut inventory::Inventory,
+        nearby_block_spec_ids: &HashSet<usize>,
+        recipe_spec_id: usize,
+    ) -> Result<(), CraftError> {
+        self.check(inventory, nearby_block_spec_ids, recipe_spec_id)?;
+
+        // NOTE: 複製したインベントリ上で実行し、成功した場合のみ反映する。
+        let recipe_spec = &self.assets.recipe_specs[recipe_spec_id];
+        let mut result = inventory.clone();
+
+        for input in &recipe_spec.inputs {
+            if !result.remove(input.item_spec_id, input.count) {
+                return Err(CraftError::Items);
+            }
+        }
+        for output in &recipe_spec.outputs {
+            if result.insert(&self.assets, output.item_spec_id, output.count) > 0 {
+                return Err(CraftError::Space);
+            }
+        }
+
+        *inventory = result;
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn crafting_sys() -> CraftingSystem {
+        let assets = assets::Assets::new("assets/assets.json");
+        CraftingSystem::new(std::rc::Rc::new(assets))
+    }
+
+    fn item_spec_id(assets: &assets::Assets, label: &str) -> usize {
+        assets
+            .item_specs
+            .iter()
+            .position(|spec| spec.label == label)
+            .unwrap()
+    }
+
+    fn recipe_spec_id(assets: &assets::Assets, label: &str) -> usize {
+        assets
+            .recipe_specs
+            .iter()
+            .position(|spec| spec.label == label)
+            .unwrap()
+    }
+
+    #[test]
+    fn craft_consumes_inputs() {
+        let crafting_sys = crafting_sys();
+        let assets = &*crafting_sys.assets;
+        let pebbles = item_spec_id(assets, "mixPebbles");
+        let rock = item_spec_id(assets, "mixRock");
+        let recipe = recipe_spec_id(assets, "pebblesToRock");
+        let mut inventory = inventory::Inventory::new(2);
+        inventory.insert(assets, pebbles, 5);
+
+        crafting_sys
+            .craft(&mut inventory, &Default::default(), recipe)
+            .unwrap();
+        assert_eq!(inventory.count(pebbles), 1);
+        assert_eq!(inventory.count(rock), 1);
+
+        // NOTE: 失敗した場合はインベントリを変更しない。
+        let result = crafting_sys.craft(&mut inventory, &Default::default(), recipe);
+        assert!(matches!(result, Err(CraftError::Items)));
+        assert_eq!(inventory.count(pebbles), 1);
+        assert_eq!(inventory.count(rock), 1);
+    }
+
+    #[test]
+    fn craft_checks_block_and_space() {
+        let crafting_sys = crafting_sys();
+        let assets = &*crafting_sys.assets;
+        let branch = item_spec_id(assets, "fallenBranch");
+        let recipe = recipe_spec_id(assets, "branchesToLog");
+        let fallen_tree = assets
+            .block_specs
+            .iter()
+            .position(|spec| spec.label == "fallenTree")
+            .unwrap();
+
+        let mut inventory = inventory::Inventory::new(1);
+        inventory.insert(assets, branch, 4);
+        let result = crafting_sys.craft(&mut inventory, &Default::default(), recipe);
+        assert!(matches!(result, Err(CraftError::Block(id)) if id == fallen_tree));
+
+        let nearby = [fallen_tree].into_iter().collect();
+        crafting_sys.craft(&mut inventory, &nearby, recipe).unwrap();
+        assert_eq!(inventory.count(branch), 0);
+
+        // NOTE: 出力が入りきらない場合は材料も消費しない。
+        let mut inventory = inventory::Inventory::new(1);
+        inventory.insert(assets, branch, 5);
+        let result = crafting_sys.craft(&mut inventory, &nearby, recipe);
+        assert!(matches!(result, Err(CraftError::Space)));
+        assert_eq!(inventory.count(branch), 5);
+
+        let result = crafting_sys.check(&inventory, &nearby, assets.recipe_specs.len());
+        assert!(matches!(result, Err(CraftError::Recipe(_))));
+    }
+}
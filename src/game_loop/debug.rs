@@ -0,0 +1,264 @@
+//! 開発用のコマンドに関するモジュール
+
+use glam::*;
+
+use crate::aabb::*;
+use crate::{
+    assets,
+    game_loop::{
+        base, block, component,
+        console::{ArgKind, Args, CommandError, CommandSpec},
+        entity,
+        system::{Phase, System, WorldContext},
+    },
+};
+
+/// デバッグ表示の種類
+#[derive(Clone, Copy, PartialEq, Eq, Hash, Debug)]
+pub enum Overlay {
+    /// エンティティとブロックの当たり判定の範囲
+    Hitboxes,
+    /// ベースのチャンクの境界
+    Chunks,
+    /// ストレージの統計とプレイヤーの位置
+    Stats,
+}
+
+impl Overlay {
+    /// 全てのデバッグ表示とその名前
+    pub const ALL: [(&'static str, Overlay); 3] = [
+        ("hitboxes", Overlay::Hitboxes),
+        ("chunks", Overlay::Chunks),
+        ("stats", Overlay::Stats),
+    ];
+
+    /// 名前からデバッグ表示を返す。
+    #[inline]
+    pub fn parse(name: &str) -> Option<Self> {
+        Self::ALL
+            .iter()
+            .find(|(other, _)| *other == name)
+            .map(|(_, overlay)| *overlay)
+    }
+}
+
+/// 開発用のコマンドを提供するシステム
+///
+/// 位置を省略したコマンドはカーソルの位置を対象とする。
+pub struct DebugSystem {
+    assets: std::rc::Rc<assets::Assets>,
+}
+
+impl DebugSystem {
+    /// 新しい開発用のシステムを作成する。
+    #[inline]
+    pub fn new(assets: std::rc::Rc<assets::Assets>) -> Self {
+        Self { assets }
+    }
+
+    /// ストレージの統計を返す。
+    pub fn stats(
+        assets: &assets::Assets,
+        base_storage: &base::BaseStorage,
+        block_storage: &block::BlockStorage,
+        entity_storage: &entity::EntityStorage,
+    ) -> Vec<String> {
+        let (chunk_count, base_count) = base_storage
+            .iter_chunks()
+            .fold((0, 0), |(chunk_count, base_count), (_, _, chunk)| {
+                (chunk_count + 1, base_count + chunk.len())
+            });
+        let block_count = block_storage.iter().count();
+
+        // NOTE: エンティティは仕様毎に数える。
+        let mut entity_counts = vec![0usize; assets.entity_specs.len()];
+        entity_storage.iter().for_each(|(_, entity)| {
+            entity_counts[entity.spec_id] += 1;
+        });
+        let entity_count = entity_counts.iter().sum::<usize>();
+        let entity_breakdown = assets
+            .entity_specs
+            .iter()
+            .zip(&entity_counts)
+            .filter(|(_, count)| **count > 0)
+            .map(|(spec, count)| format!("{} {}", spec.label, count))
+            .collect::<Vec<_>>()
+            .join(", ");
+
+        vec![
+            format!("bases: {} in {} chunks", base_count, chunk_count),
+            format!("blocks: {}", block_count),
+            format!("entities: {} ({})", entity_count, entity_breakdown),
+            format!(
+                "pending changes: bases {}, blocks {}, entities {}",
+                base_storage.journal().iter().count(),
+                block_storage.journal().iter().count(),
+                entity_storage.journal().iter().count(),
+            ),
+        ]
+    }
+
+    /// 位置の引数を返す。(省略された場合はカーソルの位置)
+    fn position(args: &Args, index: usize, context: &WorldContext) -> Result<Vec2, CommandError> {
+        match (args.float(index), args.float(index + 1)) {
+            (Some(x), Some(y)) => Ok(vec2(x, y)),
+            (None, None) => context
+                .input
+                .cursor
+                .ok_or_else(|| CommandError::Failed("no cursor position".to_string())),
+            _ => Err(CommandError::Failed(
+                "both x and y are required".to_string(),
+            )),
+        }
+    }
+
+    fn teleport(&self, args: &Args, context: &mut WorldContext) -> Result<String, CommandError> {
+        let position = Self::position(args, 0, context)?;
+        let entity_id = context
+            .player_state
+            .as_ref()
+            .map(|player_state| player_state.entity_id)
+            .ok_or_else(|| CommandError::Failed("no player".to_string()))?;
+
+        // NOTE: エンティティの描写範囲の中心を指定した位置へ合わせる。
+        let Some(mut entity) = context.entity_storage.get(entity_id).cloned() else {
+            return Err(CommandError::Failed("no player entity".to_string()));
+        };
+        let entity_spec = &self.assets.entity_specs[entity.spec_id];
+        entity.position = position - entity_spec.rendering_size.center();
+
+        // NOTE: 移動できない場合は他の状態を変更しない。
+        if context.entity_storage.modify(entity_id, entity).is_none() {
+            return Err(CommandError::Failed(format!(
+                "cannot teleport to {:.1} {:.1}",
+                position.x, position.y
+            )));
+        }
+
+        let components = context.entity_storage.components_mut();
+        components.remove::<component::PathFollow>(entity_id);
+        components.insert(entity_id, component::Velocity::default());
+        context.camera_state.position = position;
+
+        Ok(format!("teleported to {:.1} {:.1}", position.x, position.y))
+    }
+
+    fn spawn(&self, args: &Args, context: &mut WorldContext) -> Result<String, CommandError> {
+        let spec = &self.assets.entity_specs[args.spec(0).unwrap()];
+        let position = Self::position(args, 1, context)? - spec.rendering_size.center();
+
+        let entity = entity::Entity::new(spec.id, position);
+        let entity_id = context
+            .entity_storage
+            .spawn(entity)
+            .ok_or_else(|| CommandError::Failed(format!("cannot spawn {} here", spec.label)))?;
+
+        Ok(format!("spawned {} (id {})", spec.label, entity_id))
+    }
+
+    fn set_block(&self, args: &Args, context: &mut WorldContext) -> Result<String, CommandError> {
+        let spec = &self.assets.block_specs[args.spec(0).unwrap()];
+        let position = Self::position(args, 1, context)?.floor().as_ivec2();
+
+        let block = block::Block::new(spec.id, position, 0, block::BlockState::initial(spec));
+        let block_id = context
+            .block_storage
+            .insert(block)
+            .ok_or_else(|| CommandError::Failed(format!("cannot place {} here", spec.label)))?;
+
+        Ok(format!("placed {} (id {})", spec.label, block_id))
+    }
+
+    fn break_block(&self, args: &Args, context: &mut WorldContext) -> Result<String, CommandError> {
+        let position = Self::position(args, 0, context)?.floor().as_ivec2();
+
+        let block_id = context
+            .block_storage
+            .get_internal_by_rect(iaabb2(position, position + IVec2::ONE))
+            .map(|(block_id, _)| block_id)
+            .next()
+            .ok_or_else(|| CommandError::Failed("no block here".to_string()))?;
+        let block = context.block_storage.remove(block_id).unwrap();
+
+        let spec = &self.assets.block_specs[block.spec_id];
+        Ok(format!("removed {} (id {})", spec.label, block_id))
+    }
+
+    fn time(&self, args: &Args, context: &mut WorldContext) -> Result<String, CommandError> {
+        let day_length = self.assets.clock_spec.day_length as f64;
+
+        if let Some(hour) = args.float(0) {
+            if !(0.0..24.0).contains(&hour) {
+                return Err(CommandError::Failed(format!("invalid hour: {}", hour)));
+            }
+            // NOTE: 日付を保ったまま時刻のみ変更する。
+            let day = context.clock_sys.day() as f64;
+            let time = (day + hour as f64 / 24.0) * day_length;
+            context.clock_sys.restore(time);
+        }
+
+        let hours = context.clock_sys.time_of_day() * 24.0;
+        Ok(format!(
+            "day {}, {:02}:{:02}",
+            context.clock_sys.day(),
+            hours as u32,
+            (hours.fract() * 60.0) as u32,
+        ))
+    }
+}
+
+impl System for DebugSystem {
+    #[inline]
+    fn phase(&self) -> Phase {
+        Phase::Input
+    }
+
+    /// NOTE: 毎ティックの処理は無い。
+    #[inline]
+    fn update(&mut self, _context: &mut WorldContext) {}
+
+    fn commands(&self) -> Vec<CommandSpec> {
+        vec![
+            CommandSpec::new("teleport", "move the player to a position")
+                .optional("x", ArgKind::Float)
+                .optional("y", ArgKind::Float),
+            CommandSpec::new("spawn", "spawn an entity")
+                .arg("label", ArgKind::EntitySpec)
+                .optional("x", ArgKind::Float)
+                .optional("y", ArgKind::Float),
+            CommandSpec::new("setblock", "place a block")
+                .arg("label", ArgKind::BlockSpec)
+                .optional("x", ArgKind::Float)
+                .optional("y", ArgKind::Float),
+            CommandSpec::new("breakblock", "remove a block without loot")
+                .optional("x", ArgKind::Float)
+                .optional("y", ArgKind::Float),
+            CommandSpec::new("time", "show or set the time of day")
+                .optional("hour", ArgKind::Float),
+            CommandSpec::new("stats", "dump storage stats"),
+        ]
+    }
+
+    fn command(
+        &mut self,
+        name: &str,
+        args: &Args,
+        context: &mut WorldContext,
+    ) -> Result<String, CommandError> {
+        match name {
+            "teleport" => self.teleport(args, context),
+            "spawn" => self.spawn(args, context),
+            "setblock" => self.set_block(args, context),
+            "breakblock" => self.break_block(args, context),
+            "time" => self.time(args, context),
+            "stats" => Ok(Self::stats(
+                &self.assets,
+                context.base_storage,
+                context.block_storage,
+                context.entity_storage,
+            )
+            .join("\n")),
+            _ => Err(CommandError::Unknown(name.to_string())),
+        }
+    }
+}
@@ -0,0 +1,385 @@
+//! 落ちているアイテムの機能に関するモジュール
+
+use glam::*;
+use rand::Rng;
+
+use crate::aabb::*;
+use crate::{
+    assets,
+    game_loop::{
+        component, entity, inventory,
+        system::{Phase, System, WorldContext},
+    },
+};
+
+/// 落ちているアイテムの機能
+///
+/// アイテムは[`component::ItemDrop`]を持つエンティティとして表される。
+/// 散らばる方向や数はワールドの乱数生成器から決まる。
+pub struct ItemDropSystem {
+    assets: std::rc::Rc<assets::Assets>,
+}
+
+impl ItemDropSystem {
+    /// 散らばる時の初速
+    const SCATTER_SPEED: f32 = 3.0;
+
+    /// 速度の減衰率
+    const DAMPING: f32 = 6.0;
+
+    /// 消滅するまでの時間 (秒)
+    const DESPAWN_TIME: f32 = 300.0;
+
+    /// 同じアイテムをまとめる距離
+    const MERGE_DISTANCE: f32 = 0.5;
+
+    /// プレイヤーへ引き寄せられる距離
+    const MAGNET_DISTANCE: f32 = 2.5;
+
+    /// プレイヤーへ引き寄せられる速さ
+    const MAGNET_SPEED: f32 = 6.0;
+
+    /// プレイヤーに拾われる距離
+    const PICKUP_DISTANCE: f32 = 0.25;
+
+    /// 新しいアイテムシステムを作成する。
+    #[inline]
+    pub fn new(assets: std::rc::Rc<assets::Assets>) -> Self {
+        Self { assets }
+    }
+
+    /// 指定した位置にアイテムを落とし、エンティティの識別子を返す。
+    ///
+    /// アイテムはランダムな方向へ散らばる。
+    pub fn spawn(
+        context: &mut WorldContext,
+        position: Vec2,
+        stack: inventory::ItemStack,
+    ) -> Option<usize> {
+        let item_spec = &context.assets.item_specs[stack.item_spec_id];
+        let entity_spec = &context.assets.entity_specs[item_spec.drop_entity_spec_id];
+
+        let position = position - entity_spec.internal_size * 0.5;
+        let entity = entity::Entity::new(entity_spec.id, position);
+        let entity_id = context.entity_storage.insert(entity)?;
+
+        let angle = context.rng.gen_range(0.0..std::f32::consts::TAU);
+        let speed = context.rng.gen_range(0.5..1.0) * Self::SCATTER_SPEED;
+        let components = context.entity_storage.components_mut();
+        components.insert(
+            entity_id,
+            component::ItemDrop {
+                stack,
+                remaining: Self::DESPAWN_TIME,
+            },
+        );
+        components.insert(
+            entity_id,
+            component::Velocity(Vec2::from_angle(angle) * speed),
+        );
+        components.insert(entity_id, component::Damping(Self::DAMPING));
+
+        Some(entity_id)
+    }
+
+    /// ブロックの破壊やエンティティが倒された時のアイテムを仕様に基づいて落とす。
+    pub fn spawn_loot(context: &mut WorldContext, loot: &[assets::LootSpec], position: Vec2) {
+        loot.iter().for_each(|loot| {
+            if !context
+                .rng
+                .gen_bool(loot.probability.clamp(0.0, 1.0) as f64)
+            {
+                return;
+            }
+
+            let count = context.rng.gen_range(loot.min..=loot.max.max(loot.min));
+            if count > 0 {
+                let stack = inventory::ItemStack::new(loot.item_spec_id, count);
+                Self::spawn(context, position, stack);
+            }
+        });
+    }
+
+    /// 近くにある同じアイテムを指定したエンティティへ統合する。
+    fn merge(&self, entity_storage: &mut entity::EntityStorage, entity_id: usize) {
+        let Some(entity) = entity_storage.get(entity_id) else {
+            return;
+        };
+        let Some(&item_drop) = entity_storage
+            .components()
+            .get::<component::ItemDrop>(entity_id)
+        else {
+            return;
+        };
+
+        let spec = &self.assets.entity_specs[entity.spec_id];
+        let max_stack = self.assets.item_specs[item_drop.stack.item_spec_id].max_stack;
+        let rect = aabb2(
+            entity.position - Self::MERGE_DISTANCE,
+            entity.position + spec.internal_size + Self::MERGE_DISTANCE,
+        );
+
+        let others = entity_storage
+            .get_internal_by_rect(rect)
+            .filter(|(other_id, _)| *other_id != entity_id)
+            .filter_map(|(other_id, _)| {
+                let other = entity_storage
+                    .components()
+                    .get::<component::ItemDrop>(other_id)?;
+                (other.stack.item_spec_id == item_drop.stack.item_spec_id)
+                    .then_some((other_id, *other))
+            })
+            .collect::<Vec<_>>();
+
+        let mut merged = item_drop;
+        for (other_id, other) in others {
+            if merged.stack.count + other.stack.count > max_stack {
+                continue;
+            }
+            merged.stack.count += other.stack.count;
+            merged.remaining = merged.remaining.max(other.remaining);
+            entity_storage.remove(other_id);
+        }
+
+        entity_storage.components_mut().insert(entity_id, merged);
+    }
+}
+
+impl System for ItemDropSystem {
+    #[inline]
+    fn phase(&self) -> Phase {
+        Phase::Simulation
+    }
+
+    /// 消滅・統合・引き寄せ・拾得を実行する。
+    fn update(&mut self, context: &mut WorldContext) {
+        let tick = &context.tick;
+        let entity_storage = &mut *context.entity_storage;
+        let player_state = context.player_state.as_mut();
+
+        let entity_ids = entity_storage
+            .components()
+            .iter_with::<(component::ItemDrop,)>()
+            .collect::<Vec<_>>();
+
+        // NOTE: 消滅
+        entity_ids.iter().for_each(|&entity_id| {
+            let components = entity_storage.components_mut();
+            let item_drop = components
+                .get_mut::<component::ItemDrop>(entity_id)
+                .unwrap();
+            item_drop.remaining -= tick.as_secs_f32();
+            let remaining = item_drop.remaining;
+            if remaining <= 0.0 {
+                entity_storage.remove(entity_id);
+            }
+        });
+
+        // NOTE: 近くの同じアイテムの統合
+        entity_ids.iter().for_each(|&entity_id| {
+            self.merge(entity_storage, entity_id);
+        });
+
+        // NOTE: プレイヤーへの引き寄せと拾得
+        let Some(player_state) = player_state else {
+            return;
+        };
+        let Some(player) = entity_storage.get(player_state.entity_id) else {
+            return;
+        };
+        let player_spec = &self.assets.entity_specs[player.spec_id];
+        let player_rect = aabb2(player.position, player.position + player_spec.internal_size);
+        let player_center = player_rect.center();
+
+        let magnet_rect = aabb2(
+            player_rect.min - Self::MAGNET_DISTANCE,
+            player_rect.max + Self::MAGNET_DISTANCE,
+        );
+        let pickup_rect = aabb2(
+            player_rect.min - Self::PICKUP_DISTANCE,
+            player_rect.max + Self::PICKUP_DISTANCE,
+        );
+
+        let nearby = entity_storage
+            .get_internal_by_rect(magnet_rect)
+            .filter(|(entity_id, _)| {
+                entity_storage
+                    .components()
+                    .get::<component::ItemDrop>(*entity_id)
+                    .is_some()
+            })
+            .map(|(entity_id, entity)| {
+                let spec = &self.assets.entity_specs[entity.spec_id];
+                let rect = aabb2(entity.position, entity.position + spec.internal_size);
+                (entity_id, rect)
+            })
+            .collect::<Vec<_>>();
+
+        nearby.into_iter().for_each(|(entity_id, rect)| {
+            if rect.intersects(pickup_rect) {
+                let components = entity_storage.components_mut();
+                let item_drop = components
+                    .get_mut::<component::ItemDrop>(entity_id)
+                    .unwrap();
+                let stack = item_drop.stack;
+                let rest =
+                    player_state
+                        .inventory
+                        .insert(&self.assets, stack.item_spec_id, stack.count);
+                item_drop.stack.count = rest;
+
+                if rest == 0 {
+                    entity_storage.remove(entity_id);
+                }
+            } else {
+                let direction = (player_center - rect.center()).normalize_or_zero();
+                entity_storage.components_mut().insert(
+                    entity_id,
+                    component::Velocity(direction * Self::MAGNET_SPEED),
+                );
+            }
+        });
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::game_loop::{input, GameLoop};
+
+    /// 生成されたブロックとプレイヤー以外のエンティティを取り除いたゲームループを返す。
+    fn empty_game_loop() -> (GameLoop, Vec2) {
+        let assets = assets::Assets::new("assets/assets.json");
+        let mut game_loop = GameLoop::new(std::rc::Rc::new(assets), 0);
+        game_loop.update(&Default::default(), &GameLoop::TICK);
+
+        let player_id = game_loop.player_state.as_ref().unwrap().entity_id;
+        game_loop.block_storage.restore(std::iter::empty());
+        let others = game_loop
+            .entity_storage
+            .iter()
+            .map(|(id, _)| id)
+            .filter(|id| *id != player_id)
+            .collect::<Vec<_>>();
+        others.into_iter().for_each(|id| {
+            game_loop.entity_storage.remove(id);
+        });
+
+        let position = game_loop.entity_storage.get(player_id).unwrap().position;
+        (game_loop, position)
+    }
+
+    /// ゲームループの状態からワールドの状態を組み立ててアイテムを落とす。
+    fn spawn(game_loop: &mut GameLoop, position: Vec2, stack: inventory::ItemStack) -> usize {
+        let input = input::InputState::default();
+        let mut context = WorldContext {
+            assets: &game_loop.assets,
+            tick: std::time::Duration::ZERO,
+            input: &input,
+            rng: &mut game_loop.rng,
+            clock_sys: &mut game_loop.clock_sys,
+            weather_state: &mut game_loop.weather_state,
+            pathfinding_sys: &mut game_loop.pathfinding_sys,
+            camera_state: &mut game_loop.camera_state,
+            base_storage: &mut game_loop.base_storage,
+            block_storage: &mut game_loop.block_storage,
+            entity_storage: &mut game_loop.entity_storage,
+            player_state: &mut game_loop.player_state,
+            generation_scripts: &mut game_loop.generation_scripts,
+        };
+        ItemDropSystem::spawn(&mut context, position, stack).unwrap()
+    }
+
+    fn item_spec_id(game_loop: &GameLoop, label: &str) -> usize {
+        game_loop
+            .assets
+            .item_specs
+            .iter()
+            .position(|spec| spec.label == label)
+            .unwrap()
+    }
+
+    fn item_drop(game_loop: &GameLoop, entity_id: usize) -> Option<component::ItemDrop> {
+        game_loop
+            .entity_storage
+            .components()
+            .get::<component::ItemDrop>(entity_id)
+            .copied()
+    }
+
+    #[test]
+    fn spawn_and_merge() {
+        let (mut game_loop, player_position) = empty_game_loop();
+        let position = player_position + vec2(20.0, 0.0);
+        let pebbles = item_spec_id(&game_loop, "mixPebbles");
+        let branch = item_spec_id(&game_loop, "fallenBranch");
+
+        let a = spawn(
+            &mut game_loop,
+            position,
+            inventory::ItemStack::new(pebbles, 40),
+        );
+        let b = spawn(
+            &mut game_loop,
+            position,
+            inventory::ItemStack::new(pebbles, 20),
+        );
+        let c = spawn(
+            &mut game_loop,
+            position,
+            inventory::ItemStack::new(pebbles, 10),
+        );
+        let other = spawn(
+            &mut game_loop,
+            position,
+            inventory::ItemStack::new(branch, 1),
+        );
+
+        // NOTE: 落ちたアイテムは散らばる。
+        let velocity = game_loop
+            .entity_storage
+            .components()
+            .get::<component::Velocity>(a)
+            .unwrap()
+            .0;
+        assert!(velocity.length() >= ItemDropSystem::SCATTER_SPEED * 0.5);
+
+        // NOTE: 最大数を超えない範囲で同じアイテムのみ統合する。
+        game_loop.update(&Default::default(), &GameLoop::TICK);
+        game_loop.discard_changes();
+        assert_eq!(item_drop(&game_loop, a).unwrap().stack.count, 60);
+        assert!(item_drop(&game_loop, b).is_none());
+        assert_eq!(item_drop(&game_loop, c).unwrap().stack.count, 10);
+        assert_eq!(item_drop(&game_loop, other).unwrap().stack.count, 1);
+    }
+
+    #[test]
+    fn pickup_near_player() {
+        let (mut game_loop, player_position) = empty_game_loop();
+        let pebbles = item_spec_id(&game_loop, "mixPebbles");
+        let player_state = game_loop.player_state.as_mut().unwrap();
+        player_state.inventory = inventory::Inventory::new(player_state.inventory.slots().len());
+
+        let near = spawn(
+            &mut game_loop,
+            player_position + vec2(2.5, 1.0),
+            inventory::ItemStack::new(pebbles, 3),
+        );
+        let far = spawn(
+            &mut game_loop,
+            player_position + vec2(20.0, 1.0),
+            inventory::ItemStack::new(pebbles, 5),
+        );
+
+        (0..60).for_each(|_| {
+            game_loop.update(&Default::default(), &GameLoop::TICK);
+            game_loop.discard_changes();
+        });
+
+        // NOTE: 近くのアイテムは引き寄せられて拾われ、遠くのアイテムは残る。
+        assert!(game_loop.entity_storage.get(near).is_none());
+        assert!(item_drop(&game_loop, far).is_some());
+        let player_state = game_loop.player_state.as_ref().unwrap();
+        assert_eq!(player_state.inventory.count(pebbles), 3);
+    }
+}
@@ -5,7 +5,10 @@ use glam::*;
 use slab::Slab;
 
 use crate::aabb::*;
-use crate::assets;
+use crate::{
+    assets,
+    game_loop::{component, journal},
+};
 
 #[derive(Clone)]
 pub struct Entity {
@@ -33,6 +36,8 @@ pub struct EntityStorage {
     entity_metas: Slab<EntityMeta>,
     internal_grid_index: HashMap<IVec2, Slab<usize>>,
     rendering_grid_index: HashMap<IVec2, Slab<usize>>,
+    components: component::ComponentStorage,
+    journal: journal::Journal<Entity>,
 }
 
 impl EntityStorage {
@@ -49,6 +54,8 @@ impl EntityStorage {
             entity_metas: Default::default(),
             internal_grid_index: Default::default(),
             rendering_grid_index: Default::default(),
+            components: Default::default(),
+            journal: Default::default(),
         }
     }
 
@@ -56,14 +63,133 @@ impl EntityStorage {
     pub fn insert(&mut self, entity: Entity) -> Option<usize> {
         let spec = &self.assets.entity_specs[entity.spec_id];
 
-        // 重複の回避
+        // 重複の回避 (固体のエンティティ同士のみ)
         let rect = aabb2(entity.position, entity.position + spec.internal_size);
-        if self.has_rendering_by_rect(rect) {
+        if spec.solid
+            && self
+                .get_rendering_by_rect(rect)
+                .any(|(_, other)| self.is_solid(other))
+        {
             return None;
         }
 
         let entity_id = self.entity_metas.vacant_key();
 
+        self.journal.record(journal::Change::Insert {
+            id: entity_id,
+            new: entity.clone(),
+        });
+
+        let entity_meta = self.build_index(entity_id, entity);
+        self.entity_metas.insert(entity_meta);
+        Some(entity_id)
+    }
+
+    /// エンティティを追加し、仕様に基づいた初期のコンポーネントを与える。
+    ///
+    /// 向き・体力・振る舞いを持つ、ワールドに出現する生物として追加する。
+    pub fn spawn(&mut self, entity: Entity) -> Option<usize> {
+        let assets = self.assets.clone();
+        let spec = &assets.entity_specs[entity.spec_id];
+        let position = entity.position;
+        let entity_id = self.insert(entity)?;
+
+        self.components
+            .insert(entity_id, component::Facing::default());
+        if let Some(health) = spec.health {
+            self.components
+                .insert(entity_id, component::Health::new(health));
+        }
+        if let Some(behaviour_spec_id) = spec.behaviour_spec_id {
+            let behaviour_spec = &assets.behaviour_specs[behaviour_spec_id];
+            self.components.insert(
+                entity_id,
+                component::Behaviour::new(behaviour_spec, position),
+            );
+        }
+        Some(entity_id)
+    }
+
+    /// エンティティを削除し、そのエンティティを返す。
+    pub fn remove(&mut self, id: usize) -> Option<Entity> {
+        let entity_meta = self.entity_metas.try_remove(id)?;
+        let entity = self.destroy_index(entity_meta);
+
+        // NOTE: コンポーネントはエンティティと共に破棄する。
+        self.components.remove_all(id);
+
+        self.journal.record(journal::Change::Remove {
+            id,
+            old: entity.clone(),
+        });
+
+        Some(entity)
+    }
+
+    /// エンティティを更新し、更新前のエンティティを返す。識別子は維持される。
+    pub fn modify(&mut self, id: usize, entity: Entity) -> Option<Entity> {
+        let spec = &self.assets.entity_specs[entity.spec_id];
+
+        // 重複の回避 (固体のエンティティ同士のみ)
+        let rect = aabb2(entity.position, entity.position + spec.internal_size);
+        if spec.solid
+            && self
+                .get_rendering_by_rect(rect)
+                .any(|(other_id, other)| other_id != id && self.is_solid(other))
+        {
+            return None;
+        }
+
+        if !self.entity_metas.contains(id) {
+            return None;
+        }
+
+        let entity_meta = self.build_index(id, entity.clone());
+        let entity_meta = std::mem::replace(&mut self.entity_metas[id], entity_meta);
+        let old = self.destroy_index(entity_meta);
+
+        self.journal.record(journal::Change::Modify {
+            id,
+            old: old.clone(),
+            new: entity,
+        });
+
+        Some(old)
+    }
+
+    /// 全てのエンティティを削除し、識別子を指定したエンティティで置き換える。
+    pub fn restore(&mut self, entities: impl IntoIterator<Item = (usize, Entity)>) {
+        let ids = self
+            .entity_metas
+            .iter()
+            .map(|(id, _)| id)
+            .collect::<Vec<_>>();
+        ids.into_iter().for_each(|id| {
+            self.remove(id);
+        });
+
+        let entity_metas = entities
+            .into_iter()
+            .map(|(entity_id, entity)| {
+                self.journal.record(journal::Change::Insert {
+                    id: entity_id,
+                    new: entity.clone(),
+                });
+
+                (entity_id, self.build_index(entity_id, entity))
+            })
+            .collect::<Vec<_>>();
+        self.entity_metas = entity_metas.into_iter().collect();
+    }
+
+    #[inline]
+    fn is_solid(&self, entity: &Entity) -> bool {
+        self.assets.entity_specs[entity.spec_id].solid
+    }
+
+    fn build_index(&mut self, entity_id: usize, entity: Entity) -> EntityMeta {
+        let spec = &self.assets.entity_specs[entity.spec_id];
+
         // インデクスを構築 (1)
         let rect = aabb2(entity.position, entity.position + spec.internal_size);
         let internal_grid_index_ref = rect
@@ -94,21 +220,19 @@ impl EntityStorage {
             })
             .collect::<Vec<_>>();
 
-        self.entity_metas.insert(EntityMeta {
+        EntityMeta {
             entity,
             internal_grid_index_ref,
             rendering_grid_index_ref,
-        });
-        Some(entity_id)
+        }
     }
 
-    /// エンティティを削除し、そのエンティティを返す。
-    pub fn remove(&mut self, id: usize) -> Option<Entity> {
+    fn destroy_index(&mut self, entity_meta: EntityMeta) -> Entity {
         let EntityMeta {
             entity,
             internal_grid_index_ref,
             rendering_grid_index_ref,
-        } = self.entity_metas.try_remove(id)?;
+        } = entity_meta;
 
         // インデクスを破棄 (1)
         internal_grid_index_ref
@@ -130,7 +254,7 @@ impl EntityStorage {
                     .remove(id);
             });
 
-        Some(entity)
+        entity
     }
 
     /// 指定した識別子に対応するエンティティの参照を返す。
@@ -139,6 +263,39 @@ impl EntityStorage {
             .get(id)
             .map(|entity_meta| &entity_meta.entity)
     }
+
+    /// 全てのエンティティの識別子と参照を返す。
+    #[inline]
+    pub fn iter(&self) -> impl Iterator<Item = (usize, &Entity)> {
+        self.entity_metas
+            .iter()
+            .map(|(id, entity_meta)| (id, &entity_meta.entity))
+    }
+
+    /// コンポーネントの参照を返す。
+    #[inline]
+    pub fn components(&self) -> &component::ComponentStorage {
+        &self.components
+    }
+
+    /// コンポーネントの可変参照を返す。
+    #[inline]
+    pub fn components_mut(&mut self) -> &mut component::ComponentStorage {
+        &mut self.components
+    }
+
+    /// 変更履歴の参照を返す。
+    #[inline]
+    pub fn journal(&self) -> &journal::Journal<Entity> {
+        &self.journal
+    }
+
+    /// 変更履歴の可変参照を返す。
+    #[inline]
+    pub fn journal_mut(&mut self) -> &mut journal::Journal<Entity> {
+        &mut self.journal
+    }
+
     /// 指定した範囲にエンティティが存在するか真偽値を返す。
     #[inline]
     pub fn has_internal_by_rect(&self, rect: Aabb2) -> bool {
@@ -183,3 +340,38 @@ impl EntityStorage {
             })
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn spawn_adds_initial_components() {
+        let assets = std::rc::Rc::new(assets::Assets::new("assets/assets.json"));
+        let mut entity_storage = EntityStorage::new(assets.clone());
+        let spec_id = |label: &str| {
+            assets
+                .entity_specs
+                .iter()
+                .position(|spec| spec.label == label)
+                .unwrap()
+        };
+
+        let sheep_id = entity_storage
+            .spawn(Entity::new(spec_id("sheep"), vec2(4.0, 4.0)))
+            .unwrap();
+        let components = entity_storage.components();
+        assert!(components.get::<component::Facing>(sheep_id).is_some());
+        assert!(components.get::<component::Health>(sheep_id).is_some());
+        assert!(components.get::<component::Behaviour>(sheep_id).is_some());
+
+        // NOTE: 振る舞いを持たない仕様には振る舞いを与えない。
+        let drop_id = entity_storage
+            .spawn(Entity::new(spec_id("itemDrop"), vec2(8.0, 4.0)))
+            .unwrap();
+        let components = entity_storage.components();
+        assert!(components.get::<component::Facing>(drop_id).is_some());
+        assert!(components.get::<component::Health>(drop_id).is_none());
+        assert!(components.get::<component::Behaviour>(drop_id).is_none());
+    }
+}
@@ -2,96 +2,216 @@
 
 use ahash::HashSet;
 use glam::*;
-use itertools::Itertools;
+use rand::{rngs::StdRng, Rng, SeedableRng};
 
 use crate::aabb::*;
 use crate::{
     assets,
-    game_loop::{base, block, entity},
+    game_loop::{
+        base, block, entity, random,
+        system::{Phase, System, WorldContext},
+    },
 };
 
+/// スクリプトによる生成の要求
+pub struct GenerationScript {
+    pub generation_spec_id: usize,
+    /// 生成したグリッドの範囲
+    pub rect: IAabb2,
+    /// グリッドと生成仕様の組に固有のシード値
+    pub seed: i64,
+}
+
 /// ワールド生成の機能
 pub struct GenerationSystem {
     assets: std::rc::Rc<assets::Assets>,
+    seed: u64,
     grid_flags: HashSet<IVec2>,
 }
 
 impl GenerationSystem {
-    /// 空間分割サイズ
-    const GRID_SIZE: i32 = 32;
+    /// 空間分割サイズ (ベースのチャンクと一致させる)
+    const GRID_SIZE: i32 = base::CHUNK_SIZE;
 
     /// 範囲の外側に余剰に生成するグリッドの大きさ
     const EXTEND_GRID: i32 = 2;
 
     #[inline]
-    pub fn new(assets: std::rc::Rc<assets::Assets>) -> Self {
+    pub fn new(assets: std::rc::Rc<assets::Assets>, seed: u64) -> Self {
         Self {
             assets,
+            seed,
             grid_flags: Default::default(),
         }
     }
 
-    /// 指定した範囲のワールドを生成する。
-    pub fn generate(
+    /// ワールド生成のシード値を返す。
+    #[inline]
+    pub fn seed(&self) -> u64 {
+        self.seed
+    }
+
+    /// 生成済みのグリッドを返す。
+    #[inline]
+    pub fn grid_flags(&self) -> &HashSet<IVec2> {
+        &self.grid_flags
+    }
+
+    /// シード値と生成済みのグリッドを置き換える。
+    pub fn restore(&mut self, seed: u64, grid_flags: impl IntoIterator<Item = IVec2>) {
+        self.seed = seed;
+        self.grid_flags = grid_flags.into_iter().collect();
+    }
+
+    /// グリッドと生成仕様の組に固有の乱数生成器を返す。
+    ///
+    /// 同じシード値からは生成の順序に依らず同じワールドが生成される。
+    fn rng(&self, grid_point: IVec2, generation_spec_id: usize) -> StdRng {
+        let state = random::combine(
+            self.seed,
+            [
+                grid_point.x as u32 as u64,
+                grid_point.y as u32 as u64,
+                generation_spec_id as u64,
+            ],
+        );
+        StdRng::seed_from_u64(state)
+    }
+
+    /// 指定した範囲のワールドを生成し、スクリプトによる生成の要求を返す。
+    ///
+    /// スクリプトはベース・ブロック・エンティティの生成の後に実行される。
+    fn generate(
         &mut self,
         base_storage: &mut base::BaseStorage,
         block_storage: &mut block::BlockStorage,
         entity_storage: &mut entity::EntityStorage,
         rect: Aabb2,
-    ) {
+    ) -> Vec<GenerationScript> {
         let grid_rect = rect
             .trunc_over()
             .as_iaabb2()
             .to_grid_space(Self::GRID_SIZE)
             .extends(Self::EXTEND_GRID);
 
+        let mut scripts = vec![];
         grid_rect
             .into_iter_points()
             .filter(|grid_point| !self.grid_flags.contains(grid_point))
-            .cartesian_product(&self.assets.generation_specs)
-            .for_each(|(grid_point, generation_spec)| match generation_spec {
-                assets::GenerationSpec::FillBase { base_spec_id, .. } => {
-                    grid_point
-                        .to_base_space(Self::GRID_SIZE)
-                        .into_iter_points()
-                        .for_each(|position| {
-                            let base = base::Base::new(*base_spec_id, position);
-                            base_storage.insert(base);
-                        });
-                }
-                assets::GenerationSpec::RandomBase {
-                    base_spec_id,
-                    probability,
-                    ..
-                } => {
-                    grid_point
-                        .to_base_space(Self::GRID_SIZE)
-                        .into_iter_points()
-                        .filter(|_| rand::random::<f32>() < *probability)
-                        .for_each(|position| {
-                            let base = base::Base::new(*base_spec_id, position);
-                            base_storage.insert(base);
-                        });
-                }
-                assets::GenerationSpec::RandomBlock {
-                    block_spec_id,
-                    probability,
-                    ..
-                } => {
-                    grid_point
-                        .to_base_space(Self::GRID_SIZE)
-                        .into_iter_points()
-                        .filter(|_| rand::random::<f32>() < *probability)
-                        .for_each(|position| {
-                            let z_random = rand::random();
-                            let block = block::Block::new(*block_spec_id, position, z_random);
-                            block_storage.insert(block);
-                        });
-                }
+            .for_each(|grid_point| {
+                // NOTE: ベースはチャンク単位でまとめて追加する。
+                let mut base_chunk = base::BaseChunk::default();
+
+                self.assets.generation_specs.iter().for_each(
+                    |generation_spec| match generation_spec {
+                        assets::GenerationSpec::FillBase { base_spec_id, .. } => {
+                            let layer = self.assets.base_specs[*base_spec_id].layer;
+                            base::BaseChunk::local_points().for_each(|local| {
+                                base_chunk.insert(local, layer, *base_spec_id);
+                            });
+                        }
+                        assets::GenerationSpec::RandomBase {
+                            id,
+                            base_spec_id,
+                            probability,
+                        } => {
+                            let layer = self.assets.base_specs[*base_spec_id].layer;
+                            let mut rng = self.rng(grid_point, *id);
+                            base::BaseChunk::local_points()
+                                .filter(|_| rng.gen::<f32>() < *probability)
+                                .for_each(|local| {
+                                    base_chunk.insert(local, layer, *base_spec_id);
+                                });
+                        }
+                        assets::GenerationSpec::RandomBlock {
+                            id,
+                            block_spec_id,
+                            probability,
+                        } => {
+                            let mut rng = self.rng(grid_point, *id);
+                            grid_point
+                                .to_base_space(Self::GRID_SIZE)
+                                .into_iter_points()
+                                .filter_map(|position| {
+                                    let z_random = rng.gen::<u8>();
+                                    (rng.gen::<f32>() < *probability)
+                                        .then_some((position, z_random))
+                                })
+                                .for_each(|(position, z_random)| {
+                                    let spec = &self.assets.block_specs[*block_spec_id];
+                                    let state = block::BlockState::initial(spec);
+                                    let block = block::Block::new(
+                                        *block_spec_id,
+                                        position,
+                                        z_random,
+                                        state,
+                                    );
+                                    block_storage.insert(block);
+                                });
+                        }
+                        assets::GenerationSpec::RandomEntity {
+                            id,
+                            entity_spec_id,
+                            probability,
+                        } => {
+                            let spec = &self.assets.entity_specs[*entity_spec_id];
+                            let mut rng = self.rng(grid_point, *id);
+                            grid_point
+                                .to_base_space(Self::GRID_SIZE)
+                                .into_iter_points()
+                                .filter(|_| rng.gen::<f32>() < *probability)
+                                .for_each(|position| {
+                                    // NOTE: 固体のブロックに埋まる位置には生成しない。
+                                    let size = spec.internal_size.ceil().as_ivec2();
+                                    let buried = block_storage
+                                        .get_internal_by_rect(iaabb2(position, position + size))
+                                        .any(|(_, block)| {
+                                            self.assets.block_specs[block.spec_id].solid
+                                        });
+                                    if buried {
+                                        return;
+                                    }
+
+                                    let position = position.as_vec2();
+                                    let entity = entity::Entity::new(*entity_spec_id, position);
+                                    entity_storage.spawn(entity);
+                                });
+                        }
+                        assets::GenerationSpec::Script { id, .. } => {
+                            scripts.push(GenerationScript {
+                                generation_spec_id: *id,
+                                rect: grid_point.to_base_space(Self::GRID_SIZE),
+                                seed: self.rng(grid_point, *id).gen(),
+                            });
+                        }
+                    },
+                );
+
+                base_storage.insert_chunk(grid_point, &base_chunk);
             });
 
         grid_rect.into_iter_points().for_each(|grid_point| {
             self.grid_flags.insert(grid_point);
         });
+        scripts
+    }
+}
+
+impl System for GenerationSystem {
+    #[inline]
+    fn phase(&self) -> Phase {
+        Phase::PostSimulation
+    }
+
+    /// カメラの周囲のワールドを生成する。
+    fn update(&mut self, context: &mut WorldContext) {
+        let rect = context.camera_state.clipping();
+        let scripts = self.generate(
+            context.base_storage,
+            context.block_storage,
+            context.entity_storage,
+            rect,
+        );
+        context.generation_scripts.extend(scripts);
     }
 }
@@ -0,0 +1,97 @@
+//! 入力の機能に関するモジュール
+//!
+//! ゲームループはウィンドウに依存せず、このモジュールの入力状態のみを参照する。
+//! ウィンドウからの入力の変換は呼び出し側で行う。
+
+use ahash::HashSet;
+use glam::*;
+
+/// ゲーム内の操作
+#[derive(Clone, Copy, PartialEq, Eq, Hash, Debug, serde::Serialize, serde::Deserialize)]
+pub enum Action {
+    MoveUp,
+    MoveDown,
+    MoveLeft,
+    MoveRight,
+    /// カーソルの位置への経路に沿った移動
+    MoveTo,
+    Sprint,
+    ZoomIn,
+    ZoomOut,
+    ZoomReset,
+    Break,
+    Place,
+    /// 向いている方向への攻撃
+    Attack,
+    /// カーソルの方向への投射物の発射
+    Shoot,
+    SelectNext,
+    SelectPrevious,
+    /// ホットバーの指定したスロットの選択 (`0`始まり)
+    Hotbar(u8),
+    Quicksave,
+    Quickload,
+    /// 開発者コンソールの表示の切り替え
+    Console,
+}
+
+/// 1フレームにおける入力の状態
+#[derive(Clone, Default)]
+pub struct InputState {
+    /// 押され続けている操作
+    pub held: HashSet<Action>,
+    /// このフレームで押された操作
+    pub pressed: HashSet<Action>,
+    /// ワールド座標系におけるカーソルの位置
+    pub cursor: Option<Vec2>,
+    /// このフレームでのスクロール量 (上方向が正)
+    pub scroll: f32,
+}
+
+impl InputState {
+    /// 操作が押され続けているか真偽値を返す。
+    #[inline]
+    pub fn held(&self, action: Action) -> bool {
+        self.held.contains(&action)
+    }
+
+    /// 操作がこのフレームで押されたか真偽値を返す。
+    #[inline]
+    pub fn pressed(&self, action: Action) -> bool {
+        self.pressed.contains(&action)
+    }
+
+    /// 操作を押した状態にする。
+    ///
+    /// 押され続けている操作は新たに押されたものとして扱わない。
+    pub fn press(&mut self, action: Action) {
+        if self.held.insert(action) {
+            self.pressed.insert(action);
+        }
+    }
+
+    /// 操作を離した状態にする。
+    #[inline]
+    pub fn release(&mut self, action: Action) {
+        self.held.remove(&action);
+    }
+
+    /// フレーム単位の入力を次のフレームへ持ち越す。
+    ///
+    /// 押され続けている操作とカーソルの位置は維持される。
+    #[inline]
+    pub fn advance(&mut self) {
+        self.pressed.clear();
+        self.scroll = 0.0;
+    }
+
+    /// 他の入力のフレーム単位の入力を取り込み、押され続けている操作とカーソルの位置を置き換える。
+    ///
+    /// ティックが実行されなかったフレームの入力を失わないために使用する。
+    pub fn merge(&mut self, other: &InputState) {
+        self.held.clone_from(&other.held);
+        self.pressed.extend(other.pressed.iter().copied());
+        self.cursor = other.cursor;
+        self.scroll += other.scroll;
+    }
+}
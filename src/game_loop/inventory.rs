@@ -0,0 +1,197 @@
+//! インベントリの機能に関するモジュール
+
+use crate::assets;
+
+/// 同じ種類のアイテムの集まり
+#[derive(Clone, Copy, PartialEq, Eq, Debug)]
+pub struct ItemStack {
+    pub item_spec_id: usize,
+    pub count: u32,
+}
+
+impl ItemStack {
+    /// 新しいアイテムの集まりを作成する。
+    #[inline]
+    pub fn new(item_spec_id: usize, count: u32) -> Self {
+        Self {
+            item_spec_id,
+            count,
+        }
+    }
+}
+
+/// 固定数のスロットからなるインベントリ
+///
+/// 先頭の[`Inventory::HOTBAR_SIZE`]個のスロットはホットバーとして扱われる。
+#[derive(Clone)]
+pub struct Inventory {
+    slots: Vec<Option<ItemStack>>,
+}
+
+impl Inventory {
+    /// ホットバーのスロット数
+    pub const HOTBAR_SIZE: usize = 9;
+
+    /// 新しい空のインベントリを作成する。
+    #[inline]
+    pub fn new(size: usize) -> Self {
+        Self {
+            slots: vec![None; size],
+        }
+    }
+
+    /// スロットの一覧からインベントリを作成する。
+    #[inline]
+    pub fn from_slots(slots: Vec<Option<ItemStack>>) -> Self {
+        Self { slots }
+    }
+
+    /// アイテムを追加し、入りきらなかった数を返す。
+    ///
+    /// 同じ種類のアイテムがあるスロットを優先し、次に空のスロットへ追加する。
+    pub fn insert(&mut self, assets: &assets::Assets, item_spec_id: usize, count: u32) -> u32 {
+        let max_stack = assets.item_specs[item_spec_id].max_stack;
+        let mut rest = count;
+
+        // NOTE: 既存のスロットへ重ねる。
+        for stack in self.slots.iter_mut().flatten() {
+            if rest == 0 {
+                break;
+            }
+            if stack.item_spec_id == item_spec_id && stack.count < max_stack {
+                let amount = rest.min(max_stack - stack.count);
+                stack.count += amount;
+                rest -= amount;
+            }
+        }
+
+        // NOTE: 空のスロットへ追加する。
+        for slot in self.slots.iter_mut().filter(|slot| slot.is_none()) {
+            if rest == 0 {
+                break;
+            }
+            let amount = rest.min(max_stack);
+            *slot = Some(ItemStack::new(item_spec_id, amount));
+            rest -= amount;
+        }
+
+        rest
+    }
+
+    /// 指定した数のアイテムを削除し、削除できたか真偽値を返す。
+    ///
+    /// 数が足りない場合は何も削除しない。
+    pub fn remove(&mut self, item_spec_id: usize, count: u32) -> bool {
+        if self.count(item_spec_id) < count {
+            return false;
+        }
+
+        // NOTE: 後方のスロットから優先して消費する。
+        let mut rest = count;
+        for slot in self.slots.iter_mut().rev() {
+            if rest == 0 {
+                break;
+            }
+            if let Some(stack) = slot.filter(|stack| stack.item_spec_id == item_spec_id) {
+                let amount = rest.min(stack.count);
+                rest -= amount;
+                *slot = (stack.count > amount)
+                    .then_some(ItemStack::new(item_spec_id, stack.count - amount));
+            }
+        }
+
+        true
+    }
+
+    /// 指定したスロットから1つのアイテムを削除し、そのアイテムの仕様の識別子を返す。
+    pub fn take_one(&mut self, slot_id: usize) -> Option<usize> {
+        let slot = self.slots.get_mut(slot_id)?;
+        let stack = (*slot)?;
+        *slot = (stack.count > 1).then_some(ItemStack::new(stack.item_spec_id, stack.count - 1));
+        Some(stack.item_spec_id)
+    }
+
+    /// 指定した種類のアイテムの総数を返す。
+    pub fn count(&self, item_spec_id: usize) -> u32 {
+        self.slots
+            .iter()
+            .flatten()
+            .filter(|stack| stack.item_spec_id == item_spec_id)
+            .map(|stack| stack.count)
+            .sum()
+    }
+
+    /// 指定したスロットのアイテムを返す。
+    #[inline]
+    pub fn get(&self, slot_id: usize) -> Option<&ItemStack> {
+        self.slots.get(slot_id)?.as_ref()
+    }
+
+    /// 全てのスロットを返す。
+    #[inline]
+    pub fn slots(&self) -> &[Option<ItemStack>] {
+        &self.slots
+    }
+
+    /// ホットバーのスロットを返す。
+    #[inline]
+    pub fn hotbar(&self) -> &[Option<ItemStack>] {
+        &self.slots[..Self::HOTBAR_SIZE.min(self.slots.len())]
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn item_spec_id(assets: &assets::Assets, label: &str) -> usize {
+        assets
+            .item_specs
+            .iter()
+            .position(|spec| spec.label == label)
+            .unwrap()
+    }
+
+    #[test]
+    fn insert_respects_max_stack() {
+        let assets = assets::Assets::new("assets/assets.json");
+        let rock = item_spec_id(&assets, "mixRock");
+        let log = item_spec_id(&assets, "log");
+        let max_stack = assets.item_specs[rock].max_stack;
+        let mut inventory = Inventory::new(3);
+
+        assert_eq!(inventory.insert(&assets, rock, max_stack + 1), 0);
+        assert_eq!(inventory.get(0), Some(&ItemStack::new(rock, max_stack)));
+        assert_eq!(inventory.get(1), Some(&ItemStack::new(rock, 1)));
+
+        // NOTE: 既存のスロットへ重ねてから空のスロットへ追加する。
+        assert_eq!(inventory.insert(&assets, log, 1), 0);
+        assert_eq!(inventory.insert(&assets, rock, max_stack), 1);
+        assert_eq!(inventory.get(1), Some(&ItemStack::new(rock, max_stack)));
+        assert_eq!(inventory.count(rock), max_stack * 2);
+        assert_eq!(inventory.count(log), 1);
+    }
+
+    #[test]
+    fn remove_and_take_one() {
+        let assets = assets::Assets::new("assets/assets.json");
+        let rock = item_spec_id(&assets, "mixRock");
+        let max_stack = assets.item_specs[rock].max_stack;
+        let mut inventory = Inventory::new(3);
+        inventory.insert(&assets, rock, max_stack + 2);
+
+        // NOTE: 足りない場合は何も削除しない。
+        assert!(!inventory.remove(rock, max_stack + 3));
+        assert_eq!(inventory.count(rock), max_stack + 2);
+
+        // NOTE: 後方のスロットから消費する。
+        assert!(inventory.remove(rock, 3));
+        assert_eq!(inventory.get(0), Some(&ItemStack::new(rock, max_stack - 1)));
+        assert_eq!(inventory.get(1), None);
+
+        assert_eq!(inventory.take_one(0), Some(rock));
+        assert_eq!(inventory.count(rock), max_stack - 2);
+        assert_eq!(inventory.take_one(1), None);
+        assert_eq!(inventory.take_one(3), None);
+    }
+}
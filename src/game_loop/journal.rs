@@ -0,0 +1,163 @@
+//! 変更履歴の機能に関するモジュール
+
+use slab::Slab;
+
+/// ストレージ上で発生した変更
+#[derive(Clone)]
+pub enum Change<T> {
+    /// 要素が追加された。
+    Insert { id: usize, new: T },
+    /// 要素が削除された。
+    Remove { id: usize, old: T },
+    /// 要素が更新された。
+    Modify { id: usize, old: T, new: T },
+}
+
+impl<T> Change<T> {
+    /// 変更が発生した要素の識別子を返す。
+    #[inline]
+    pub fn id(&self) -> usize {
+        match self {
+            Change::Insert { id, .. } => *id,
+            Change::Remove { id, .. } => *id,
+            Change::Modify { id, .. } => *id,
+        }
+    }
+
+    /// 変更前の要素を返す。
+    #[inline]
+    pub fn before(&self) -> Option<&T> {
+        match self {
+            Change::Insert { .. } => None,
+            Change::Remove { old, .. } => Some(old),
+            Change::Modify { old, .. } => Some(old),
+        }
+    }
+
+    /// 変更後の要素を返す。
+    #[inline]
+    pub fn after(&self) -> Option<&T> {
+        match self {
+            Change::Insert { new, .. } => Some(new),
+            Change::Remove { .. } => None,
+            Change::Modify { new, .. } => Some(new),
+        }
+    }
+}
+
+/// 変更を監視するコールバック
+pub type Observer<T> = Box<dyn FnMut(&Change<T>)>;
+
+/// 変更履歴の機能
+///
+/// 変更は記録と同時に監視者へ通知され、取り出されるまで保持される。
+pub struct Journal<T> {
+    changes: Vec<Change<T>>,
+    observers: Slab<Observer<T>>,
+}
+
+impl<T> Default for Journal<T> {
+    #[inline]
+    fn default() -> Self {
+        Self {
+            changes: Default::default(),
+            observers: Default::default(),
+        }
+    }
+}
+
+impl<T> Journal<T> {
+    /// 変更を記録し、監視者へ通知する。
+    pub fn record(&mut self, change: Change<T>) {
+        self.observers
+            .iter_mut()
+            .for_each(|(_, observer)| observer(&change));
+        self.changes.push(change);
+    }
+
+    /// 記録された変更の参照を返す。
+    #[inline]
+    pub fn iter(&self) -> impl Iterator<Item = &Change<T>> {
+        self.changes.iter()
+    }
+
+    /// 記録された変更の数を返す。
+    #[inline]
+    pub fn len(&self) -> usize {
+        self.changes.len()
+    }
+
+    /// 記録された変更が無いか真偽値を返す。
+    #[inline]
+    pub fn is_empty(&self) -> bool {
+        self.changes.is_empty()
+    }
+
+    /// 記録された変更を取り出す。
+    #[inline]
+    pub fn drain(&mut self) -> impl Iterator<Item = Change<T>> + '_ {
+        self.changes.drain(..)
+    }
+
+    /// 記録された変更を破棄する。
+    #[inline]
+    pub fn clear(&mut self) {
+        self.changes.clear();
+    }
+
+    /// 監視者を登録し、識別子を返す。
+    #[inline]
+    pub fn observe(&mut self, observer: impl FnMut(&Change<T>) + 'static) -> usize {
+        self.observers.insert(Box::new(observer))
+    }
+
+    /// 監視者の登録を解除し、解除できたか真偽値を返す。
+    #[inline]
+    pub fn unobserve(&mut self, id: usize) -> bool {
+        self.observers.try_remove(id).is_some()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::{cell::RefCell, rc::Rc};
+
+    use super::*;
+
+    #[test]
+    fn record_notifies_and_drains() {
+        let mut journal = Journal::default();
+        let observed = Rc::new(RefCell::new(vec![]));
+        let observer_id = journal.observe({
+            let observed = observed.clone();
+            move |change: &Change<u32>| observed.borrow_mut().push(change.id())
+        });
+
+        journal.record(Change::Insert { id: 0, new: 1 });
+        journal.record(Change::Modify {
+            id: 0,
+            old: 1,
+            new: 2,
+        });
+        journal.record(Change::Remove { id: 0, old: 2 });
+        assert_eq!(*observed.borrow(), vec![0, 0, 0]);
+        assert_eq!(journal.len(), 3);
+
+        let changes = journal.drain().collect::<Vec<_>>();
+        assert!(matches!(changes[0], Change::Insert { new: 1, .. }));
+        assert!(matches!(changes[1], Change::Modify { old: 1, new: 2, .. }));
+        assert!(matches!(changes[2], Change::Remove { old: 2, .. }));
+        assert_eq!(changes[1].before(), Some(&1));
+        assert_eq!(changes[1].after(), Some(&2));
+        assert!(journal.is_empty());
+
+        // NOTE: 登録を解除した監視者には通知されない。
+        assert!(journal.unobserve(observer_id));
+        assert!(!journal.unobserve(observer_id));
+        journal.record(Change::Insert { id: 1, new: 3 });
+        assert_eq!(observed.borrow().len(), 3);
+
+        journal.clear();
+        assert!(journal.is_empty());
+    }
+}
@@ -0,0 +1,166 @@
+//! 光源の機能に関するモジュール
+
+use glam::*;
+
+use crate::aabb::*;
+use crate::{
+    assets,
+    game_loop::{block, clock, component, entity, random},
+};
+
+/// 描写する光源
+#[derive(Clone, Copy)]
+pub struct Light {
+    /// 光源の中心の位置
+    pub position: Vec2,
+    /// 揺らぎを反映した光の色
+    pub color: Vec3,
+    pub radius: f32,
+    pub occlusion: bool,
+}
+
+/// 光源の機能
+///
+/// 光源はブロック・エンティティの仕様とプレイヤーのランタンから集められる。
+pub struct LightSystem {
+    assets: std::rc::Rc<assets::Assets>,
+}
+
+impl LightSystem {
+    /// 範囲の外側に余剰に光源を探す大きさ (光源の半径の最大値)
+    pub const MARGIN: f32 = 16.0;
+
+    /// 新しい光源システムを作成する。
+    #[inline]
+    pub fn new(assets: std::rc::Rc<assets::Assets>) -> Self {
+        Self { assets }
+    }
+
+    /// 指定した範囲を照らす光源を返す。
+    ///
+    /// エンティティの位置は`position`によって補間される。
+    pub fn lights(
+        &self,
+        clock_sys: &clock::ClockSystem,
+        block_storage: &block::BlockStorage,
+        entity_storage: &entity::EntityStorage,
+        rect: Aabb2,
+        position: impl Fn(usize, Vec2) -> Vec2,
+    ) -> Vec<Light> {
+        // NOTE: 精度を保つために一定の周期で折り返す。
+        let time = (clock_sys.time() % 3600.0) as f32;
+        let rect = aabb2(rect.min - Self::MARGIN, rect.max + Self::MARGIN);
+
+        let block_lights = block_storage
+            .get_internal_by_rect(rect.trunc_over().as_iaabb2())
+            .filter_map(|(_, block)| {
+                let spec = &self.assets.block_specs[block.spec_id];
+                let light_spec = spec.light.as_ref()?;
+                let center = block.position.as_vec2() + spec.internal_size.as_vec2() * 0.5;
+                let seed = Self::seed(block.position);
+                Some(Self::light(light_spec, center, time, seed))
+            });
+
+        let entity_lights =
+            entity_storage
+                .get_internal_by_rect(rect)
+                .flat_map(|(entity_id, entity)| {
+                    let spec = &self.assets.entity_specs[entity.spec_id];
+                    let center = position(entity_id, entity.position) + spec.internal_size * 0.5;
+                    let seed = entity_id as f32;
+
+                    // NOTE: プレイヤーはランタンを持つ。
+                    let lantern = entity_storage
+                        .components()
+                        .get::<component::PlayerControlled>(entity_id)
+                        .and_then(|_| self.assets.player_specs[0].lantern.as_ref());
+
+                    [spec.light.as_ref(), lantern]
+                        .into_iter()
+                        .flatten()
+                        .map(move |light_spec| Self::light(light_spec, center, time, seed))
+                });
+
+        block_lights.chain(entity_lights).collect()
+    }
+
+    /// 指定した範囲の光を遮る固体のブロックの範囲を返す。
+    pub fn occluders(&self, block_storage: &block::BlockStorage, rect: Aabb2) -> Vec<IAabb2> {
+        let rect = aabb2(rect.min - Self::MARGIN, rect.max + Self::MARGIN);
+        block_storage
+            .get_internal_by_rect(rect.trunc_over().as_iaabb2())
+            .map(|(_, block)| (block.position, &self.assets.block_specs[block.spec_id]))
+            .filter(|(_, spec)| spec.solid)
+            .map(|(position, spec)| iaabb2(position, position + spec.internal_size))
+            .collect()
+    }
+
+    /// 揺らぎを反映した光源を作成する。
+    fn light(light_spec: &assets::LightSpec, position: Vec2, time: f32, seed: f32) -> Light {
+        // NOTE: 周期の異なる正弦波の和による揺らぎ (`0.0`から`1.0`)
+        let wave = (time * 7.3 + seed).sin() * 0.5
+            + (time * 13.1 + seed * 1.7).sin() * 0.3
+            + (time * 23.7 + seed * 2.3).sin() * 0.2;
+        let intensity = 1.0 - light_spec.flicker * (wave * 0.5 + 0.5);
+
+        Light {
+            position,
+            color: light_spec.color * intensity,
+            radius: light_spec.radius,
+            occlusion: light_spec.occlusion,
+        }
+    }
+
+    /// 位置に固有の揺らぎの位相を返す。
+    #[inline]
+    fn seed(position: IVec2) -> f32 {
+        (random::hash_position(position) >> 16) as f32
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn light_spec(flicker: f32) -> assets::LightSpec {
+        assets::LightSpec {
+            color: vec3(1.0, 0.8, 0.6),
+            radius: 4.0,
+            flicker,
+            occlusion: true,
+        }
+    }
+
+    #[test]
+    fn flicker_stays_within_range() {
+        let times = (0..600).map(|i| i as f32 / 60.0).collect::<Vec<_>>();
+
+        // NOTE: 揺らぎが無い場合は常に同じ色になる。
+        let steady = light_spec(0.0);
+        times.iter().for_each(|&time| {
+            let light = LightSystem::light(&steady, Vec2::ZERO, time, 3.0);
+            assert_eq!(light.color, steady.color);
+        });
+
+        let flickering = light_spec(0.25);
+        let intensities = times
+            .iter()
+            .map(|&time| {
+                LightSystem::light(&flickering, Vec2::ZERO, time, 3.0)
+                    .color
+                    .x
+            })
+            .collect::<Vec<_>>();
+        assert!(intensities.iter().all(|x| (0.75..=1.0).contains(x)));
+        let min = intensities.iter().copied().fold(f32::INFINITY, f32::min);
+        let max = intensities.iter().copied().fold(0.0, f32::max);
+        assert!(max - min > 0.1, "range {} {}", min, max);
+
+        // NOTE: 同じ時刻でも位置が異なれば位相がずれる。
+        let a = LightSystem::light(&flickering, Vec2::ZERO, 1.0, LightSystem::seed(IVec2::ZERO));
+        let b = LightSystem::light(&flickering, Vec2::ZERO, 1.0, LightSystem::seed(IVec2::X));
+        let c = LightSystem::light(&flickering, Vec2::ZERO, 1.0, LightSystem::seed(IVec2::ZERO));
+        assert_ne!(a.color, b.color);
+        assert_eq!(a.color, c.color);
+    }
+}
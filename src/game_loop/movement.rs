@@ -0,0 +1,94 @@
+//! 移動の機能に関するモジュール
+
+use glam::*;
+
+use crate::game_loop::{
+    component,
+    system::{Phase, System, WorldContext},
+};
+
+/// 移動の機能
+#[derive(Default)]
+pub struct MovementSystem;
+
+impl MovementSystem {
+    /// ノックバックの減衰率 (1秒あたり)
+    const KNOCKBACK_DAMPING: f32 = 10.0;
+}
+
+impl System for MovementSystem {
+    #[inline]
+    fn phase(&self) -> Phase {
+        Phase::Simulation
+    }
+
+    /// 経路に沿った速度を求め、速度を持つ全てのエンティティを移動させる。
+    ///
+    /// 移動先が他のエンティティと重なる場合は移動しない。
+    /// 減衰率を持つエンティティは移動後に速度が減衰する。
+    /// 向きを持つエンティティは移動した方向を向く。
+    /// 速度は天候によって変化する。(ノックバックを除く)
+    fn update(&mut self, context: &mut WorldContext) {
+        let tick = &context.tick;
+        let entity_storage = &mut *context.entity_storage;
+
+        // NOTE: 経路に沿った移動
+        context.pathfinding_sys.follow(tick, entity_storage);
+
+        let entity_ids = entity_storage
+            .components()
+            .iter_with::<(component::Velocity,)>()
+            .collect::<Vec<_>>();
+
+        entity_ids.into_iter().for_each(|entity_id| {
+            let component::Velocity(velocity) = *entity_storage
+                .components()
+                .get::<component::Velocity>(entity_id)
+                .unwrap();
+            let knockback = entity_storage
+                .components()
+                .get::<component::Knockback>(entity_id)
+                .map_or(Vec2::ZERO, |knockback| knockback.0);
+            if velocity == Vec2::ZERO && knockback == Vec2::ZERO {
+                return;
+            }
+
+            let mut entity = entity_storage.get(entity_id).unwrap().clone();
+            let multiplier = context
+                .weather_state
+                .speed_multiplier(context.clock_sys, entity.position);
+            entity.position += (velocity * multiplier + knockback) * tick.as_secs_f32();
+            entity_storage.modify(entity_id, entity);
+
+            let components = entity_storage.components_mut();
+            if let Some(facing) = components.get_mut::<component::Facing>(entity_id) {
+                if let Some(direction) = velocity.try_normalize() {
+                    facing.0 = direction;
+                }
+            }
+
+            // NOTE: ノックバックは十分に遅くなったら取り除く。
+            if knockback != Vec2::ZERO {
+                let knockback = knockback * (-Self::KNOCKBACK_DAMPING * tick.as_secs_f32()).exp();
+                if knockback.length_squared() < 1e-4 {
+                    components.remove::<component::Knockback>(entity_id);
+                } else {
+                    components.insert(entity_id, component::Knockback(knockback));
+                }
+            }
+
+            if let Some(&component::Damping(damping)) =
+                components.get::<component::Damping>(entity_id)
+            {
+                let velocity = velocity * (-damping * tick.as_secs_f32()).exp();
+                // NOTE: 十分に遅くなったら停止させる。
+                let velocity = if velocity.length_squared() < 1e-4 {
+                    Vec2::ZERO
+                } else {
+                    velocity
+                };
+                components.insert(entity_id, component::Velocity(velocity));
+            }
+        });
+    }
+}
@@ -0,0 +1,419 @@
+//! 経路探索の機能に関するモジュール
+
+use std::{cell::RefCell, cmp::Reverse, collections::BinaryHeap, rc::Rc};
+
+use ahash::HashMap;
+use glam::*;
+
+use crate::aabb::*;
+use crate::{
+    assets,
+    game_loop::{block, component, entity, journal},
+};
+
+/// チャンクのサイズ (1行を`u32`のビット列で表す)
+const CHUNK_SIZE: i32 = 32;
+
+/// チャンク内の通行できないタイルのビット列
+///
+/// `rows[y]`の`x`番目のビットが立っている場合、そのタイルは通行できない。
+struct WalkChunk {
+    rows: [u32; CHUNK_SIZE as usize],
+}
+
+impl WalkChunk {
+    #[inline]
+    fn is_blocked(&self, local: IVec2) -> bool {
+        self.rows[local.y as usize] & (1 << local.x) != 0
+    }
+}
+
+/// 経路探索の機能
+///
+/// 固体のブロックを障害物として、タイル単位のA*で経路を探索する。
+/// 通行可否はチャンク単位でキャッシュされ、ブロックの変更時に破棄される。
+pub struct PathfindingSystem {
+    assets: Rc<assets::Assets>,
+    chunks: HashMap<IVec2, WalkChunk>,
+    dirty: Rc<RefCell<Vec<IAabb2>>>,
+}
+
+impl PathfindingSystem {
+    /// 1回の探索で展開するノードの最大数
+    ///
+    /// ワールドは無限であるため、到達できない目的地の探索を打ち切る。
+    const MAX_NODES: usize = 4096;
+
+    /// 縦横の移動コスト
+    const STRAIGHT_COST: u32 = 10;
+
+    /// 斜めの移動コスト
+    const DIAGONAL_COST: u32 = 14;
+
+    /// 目的地とみなす距離
+    const ARRIVAL_DISTANCE: f32 = 1e-3;
+
+    /// 新しい経路探索システムを作成する。
+    #[inline]
+    pub fn new(assets: Rc<assets::Assets>) -> Self {
+        Self {
+            assets,
+            chunks: Default::default(),
+            dirty: Default::default(),
+        }
+    }
+
+    /// ブロックの変更を監視し、キャッシュを破棄する監視者を返す。
+    ///
+    /// 返された監視者はブロックのジャーナルに登録する。
+    pub fn observer(&self) -> impl FnMut(&journal::Change<block::Block>) + 'static {
+        let assets = self.assets.clone();
+        let dirty = self.dirty.clone();
+        move |change| {
+            let mut dirty = dirty.borrow_mut();
+            [change.before(), change.after()]
+                .into_iter()
+                .flatten()
+                .for_each(|block| {
+                    let spec = &assets.block_specs[block.spec_id];
+                    dirty.push(iaabb2(block.position, block.position + spec.internal_size));
+                });
+        }
+    }
+
+    /// 全てのキャッシュを破棄する。
+    pub fn clear(&mut self) {
+        self.chunks.clear();
+        self.dirty.borrow_mut().clear();
+    }
+
+    /// 変更されたブロックを含むチャンクのキャッシュを破棄する。
+    fn flush(&mut self) {
+        let dirty = std::mem::take(&mut *self.dirty.borrow_mut());
+        dirty.into_iter().for_each(|rect| {
+            rect.to_grid_space(CHUNK_SIZE)
+                .into_iter_points()
+                .for_each(|chunk_point| {
+                    self.chunks.remove(&chunk_point);
+                });
+        });
+    }
+
+    /// 指定したチャンクの通行可否を返す。
+    ///
+    /// キャッシュが存在しない場合はブロックから構築する。
+    /// 未生成のチャンクは通行できるものとして扱われ、生成時に破棄される。
+    fn chunk(&mut self, block_storage: &block::BlockStorage, chunk_point: IVec2) -> &WalkChunk {
+        self.chunks.entry(chunk_point).or_insert_with(|| {
+            let chunk_rect =
+                iaabb2(chunk_point, chunk_point + IVec2::ONE).to_base_space(CHUNK_SIZE);
+
+            let mut rows = [0; CHUNK_SIZE as usize];
+            block_storage
+                .get_internal_by_rect(chunk_rect)
+                .for_each(|(_, block)| {
+                    let spec = &self.assets.block_specs[block.spec_id];
+                    if !spec.solid {
+                        return;
+                    }
+
+                    // NOTE: 複数のタイルを占めるブロックはチャンク内の部分のみを反映する。
+                    let rect = iaabb2(block.position, block.position + spec.internal_size);
+                    let min = rect.min.max(chunk_rect.min) - chunk_rect.min;
+                    let max = rect.max.min(chunk_rect.max) - chunk_rect.min;
+                    (min.y..max.y).for_each(|y| {
+                        (min.x..max.x).for_each(|x| rows[y as usize] |= 1 << x);
+                    });
+                });
+
+            WalkChunk { rows }
+        })
+    }
+
+    /// 指定したタイルが固体のブロックで塞がれているか真偽値を返す。
+    pub fn is_blocked(&mut self, block_storage: &block::BlockStorage, point: IVec2) -> bool {
+        self.flush();
+
+        let chunk_point = point.to_grid_space(CHUNK_SIZE);
+        let local = point - chunk_point * CHUNK_SIZE;
+        self.chunk(block_storage, chunk_point).is_blocked(local)
+    }
+
+    /// 指定した大きさの物体が指定したタイルに立てるか真偽値を返す。
+    ///
+    /// 物体は`point`を左下として`footprint`のタイルを占める。
+    pub fn is_walkable(
+        &mut self,
+        block_storage: &block::BlockStorage,
+        point: IVec2,
+        footprint: IVec2,
+    ) -> bool {
+        iaabb2(point, point + footprint.max(IVec2::ONE))
+            .into_iter_points()
+            .all(|point| !self.is_blocked(block_storage, point))
+    }
+
+    /// 無視する範囲を除いて、指定した大きさの物体が指定したタイルに立てるか真偽値を返す。
+    fn is_passable(
+        &mut self,
+        block_storage: &block::BlockStorage,
+        point: IVec2,
+        footprint: IVec2,
+        ignored: &[IAabb2],
+    ) -> bool {
+        iaabb2(point, point + footprint.max(IVec2::ONE))
+            .into_iter_points()
+            .all(|point| {
+                !self.is_blocked(block_storage, point)
+                    || ignored.iter().any(|rect| rect.contains_point(point))
+            })
+    }
+
+    /// 指定した大きさの物体が通る経路を探索する。
+    ///
+    /// 経路は出発地を含まず、目的地を含むタイルの位置の列として返される。
+    /// 斜め移動は隣接する縦横のタイルが両方とも通行できる場合のみ許される。
+    /// 出発地で既に重なっている固体のブロックは、抜け出せるように障害物として扱わない。
+    /// 経路が存在しない、または探索が打ち切られた場合は`None`を返す。
+    pub fn find_path(
+        &mut self,
+        block_storage: &block::BlockStorage,
+        start: IVec2,
+        goal: IVec2,
+        footprint: IVec2,
+    ) -> Option<Vec<IVec2>> {
+        if start == goal {
+            return Some(vec![]);
+        }
+
+        let ignored = block_storage
+            .get_internal_by_rect(iaabb2(start, start + footprint.max(IVec2::ONE)))
+            .map(|(_, block)| (block.position, &self.assets.block_specs[block.spec_id]))
+            .filter(|(_, spec)| spec.solid)
+            .map(|(position, spec)| iaabb2(position, position + spec.internal_size))
+            .collect::<Vec<_>>();
+
+        if !self.is_passable(block_storage, goal, footprint, &ignored) {
+            return None;
+        }
+
+        // NOTE: オクタイル距離 (斜め移動を含む最短距離)
+        let heuristic = |point: IVec2| {
+            let delta = (goal - point).abs();
+            let (min, max) = (delta.min_element() as u32, delta.max_element() as u32);
+            Self::DIAGONAL_COST * min + Self::STRAIGHT_COST * (max - min)
+        };
+
+        let mut open = BinaryHeap::new();
+        let mut costs = HashMap::<IVec2, u32>::default();
+        let mut parents = HashMap::<IVec2, IVec2>::default();
+
+        // NOTE: 同じコストのノードは位置で順序付けし、探索結果を決定的にする。
+        open.push(Reverse((heuristic(start), start.x, start.y)));
+        costs.insert(start, 0);
+
+        let mut count = 0;
+        while let Some(Reverse((score, x, y))) = open.pop() {
+            let point = ivec2(x, y);
+            let cost = costs[&point];
+            if score > cost + heuristic(point) {
+                continue;
+            }
+
+            if point == goal {
+                let mut path = vec![goal];
+                let mut current = goal;
+                while let Some(&parent) = parents.get(&current) {
+                    if parent == start {
+                        break;
+                    }
+                    path.push(parent);
+                    current = parent;
+                }
+                path.reverse();
+                return Some(path);
+            }
+
+            count += 1;
+            if count > Self::MAX_NODES {
+                return None;
+            }
+
+            for dy in -1..=1 {
+                for dx in -1..=1 {
+                    let direction = ivec2(dx, dy);
+                    if direction == IVec2::ZERO {
+                        continue;
+                    }
+
+                    let next = point + direction;
+                    if !self.is_passable(block_storage, next, footprint, &ignored) {
+                        continue;
+                    }
+
+                    let step = if dx != 0 && dy != 0 {
+                        // NOTE: 角をすり抜ける斜め移動を禁止する。
+                        let horizontal = point + ivec2(dx, 0);
+                        let vertical = point + ivec2(0, dy);
+                        if !self.is_passable(block_storage, horizontal, footprint, &ignored)
+                            || !self.is_passable(block_storage, vertical, footprint, &ignored)
+                        {
+                            continue;
+                        }
+                        Self::DIAGONAL_COST
+                    } else {
+                        Self::STRAIGHT_COST
+                    };
+
+                    let next_cost = cost + step;
+                    if costs.get(&next).is_none_or(|&old| next_cost < old) {
+                        costs.insert(next, next_cost);
+                        parents.insert(next, point);
+                        open.push(Reverse((next_cost + heuristic(next), next.x, next.y)));
+                    }
+                }
+            }
+        }
+
+        None
+    }
+
+    /// エンティティの内部の大きさから経路探索で占めるタイルの大きさを返す。
+    #[inline]
+    pub fn footprint(&self, entity_spec: &assets::EntitySpec) -> IVec2 {
+        entity_spec.internal_size.ceil().as_ivec2().max(IVec2::ONE)
+    }
+
+    /// 経路を持つ全てのエンティティを経路に沿って移動させる。
+    ///
+    /// 移動自体は速度として反映し、経路の終端に達したエンティティは停止する。
+    pub fn follow(
+        &mut self,
+        tick: &std::time::Duration,
+        entity_storage: &mut entity::EntityStorage,
+    ) {
+        let delta_secs = tick.as_secs_f32();
+
+        let entity_ids = entity_storage
+            .components()
+            .iter_with::<(component::PathFollow,)>()
+            .collect::<Vec<_>>();
+
+        entity_ids.into_iter().for_each(|entity_id| {
+            let Some(position) = entity_storage.get(entity_id).map(|entity| entity.position) else {
+                return;
+            };
+
+            let components = entity_storage.components_mut();
+            let path_follow = components
+                .get_mut::<component::PathFollow>(entity_id)
+                .unwrap();
+
+            let Some(&waypoint) = path_follow.waypoints.front() else {
+                components.remove::<component::PathFollow>(entity_id);
+                components.insert(entity_id, component::Velocity::default());
+                return;
+            };
+
+            // NOTE: 通過点を超えないように最後の1ティックは速度を抑える。
+            let remaining = waypoint - position;
+            let step = path_follow.speed * delta_secs;
+            let velocity = if remaining.length() <= step.max(Self::ARRIVAL_DISTANCE) {
+                path_follow.waypoints.pop_front();
+                remaining / delta_secs
+            } else {
+                remaining.normalize() * path_follow.speed
+            };
+
+            components.insert(entity_id, component::Velocity(velocity));
+        });
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    /// 岩で`x = 2..4, y = -6..6`を塞いだ壁を作成し、岩の識別子を返す。
+    fn wall(assets: &assets::Assets, block_storage: &mut block::BlockStorage) -> Vec<usize> {
+        let spec = assets
+            .block_specs
+            .iter()
+            .find(|spec| spec.label == "mixRock")
+            .unwrap();
+        (-3..3)
+            .map(|i| {
+                let state = block::BlockState::initial(spec);
+                let block = block::Block::new(spec.id, ivec2(2, i * 2), 0, state);
+                block_storage.insert(block).unwrap()
+            })
+            .collect()
+    }
+
+    /// 経路が出発地から連続し、塞がれたタイルを通らないことを確かめる。
+    fn assert_valid(
+        pathfinding_sys: &mut PathfindingSystem,
+        block_storage: &block::BlockStorage,
+        start: IVec2,
+        path: &[IVec2],
+    ) {
+        std::iter::once(start)
+            .chain(path.iter().copied())
+            .collect::<Vec<_>>()
+            .windows(2)
+            .for_each(|pair| {
+                assert_eq!((pair[1] - pair[0]).abs().max_element(), 1, "{:?}", path);
+                assert!(!pathfinding_sys.is_blocked(block_storage, pair[1]));
+            });
+    }
+
+    #[test]
+    fn find_path_in_open_field() {
+        let assets = Rc::new(assets::Assets::new("assets/assets.json"));
+        let block_storage = block::BlockStorage::new(assets.clone());
+        let mut pathfinding_sys = PathfindingSystem::new(assets);
+
+        let path = pathfinding_sys.find_path(&block_storage, IVec2::ZERO, ivec2(5, 0), IVec2::ONE);
+        assert_eq!(path, Some((1..=5).map(|x| ivec2(x, 0)).collect()));
+
+        let path = pathfinding_sys.find_path(&block_storage, IVec2::ZERO, ivec2(3, 3), IVec2::ONE);
+        assert_eq!(path, Some(vec![ivec2(1, 1), ivec2(2, 2), ivec2(3, 3)]));
+
+        let path = pathfinding_sys.find_path(&block_storage, IVec2::ZERO, IVec2::ZERO, IVec2::ONE);
+        assert_eq!(path, Some(vec![]));
+    }
+
+    #[test]
+    fn find_path_around_wall() {
+        let assets = Rc::new(assets::Assets::new("assets/assets.json"));
+        let mut block_storage = block::BlockStorage::new(assets.clone());
+        let mut pathfinding_sys = PathfindingSystem::new(assets.clone());
+        block_storage
+            .journal_mut()
+            .observe(pathfinding_sys.observer());
+        let block_ids = wall(&assets, &mut block_storage);
+
+        let (start, goal) = (IVec2::ZERO, ivec2(6, 0));
+        let path = pathfinding_sys
+            .find_path(&block_storage, start, goal, IVec2::ONE)
+            .unwrap();
+        assert_valid(&mut pathfinding_sys, &block_storage, start, &path);
+        assert_eq!(path.last(), Some(&goal));
+        assert!(path.len() > 6);
+
+        // NOTE: 塞がれた目的地へは探索しない。
+        let path = pathfinding_sys.find_path(&block_storage, start, ivec2(2, 0), IVec2::ONE);
+        assert_eq!(path, None);
+
+        // NOTE: 重なっている固体のブロックからは抜け出せる。
+        let path = pathfinding_sys
+            .find_path(&block_storage, ivec2(3, 0), goal, IVec2::ONE)
+            .unwrap();
+        assert_eq!(path.last(), Some(&goal));
+
+        // NOTE: ブロックの削除でキャッシュが破棄され、壁の穴を通る。
+        block_storage.remove(block_ids[3]);
+        let path = pathfinding_sys.find_path(&block_storage, start, goal, IVec2::ONE);
+        assert_eq!(path, Some((1..=6).map(|x| ivec2(x, 0)).collect()));
+    }
+}
@@ -5,20 +5,80 @@ use glam::*;
 use crate::aabb::*;
 use crate::{
     assets,
-    game_loop::{base, block, camera, entity},
+    game_loop::{
+        block, combat, component, drop, entity, input, inventory, projectile, random,
+        system::{Phase, System, WorldContext},
+    },
 };
 
+/// 破壊中のブロック
+#[derive(Clone, Copy)]
+pub struct Breaking {
+    pub block_id: usize,
+    /// 破壊を始めてからの経過時間 (秒)
+    pub elapsed: f32,
+}
+
 pub struct PlayerState {
     pub entity_id: usize,
     pub target_base: Option<usize>,
     pub target_block: Option<usize>,
     pub target_entity: Option<usize>,
+    /// カーソルが指しているタイルの位置 (届く範囲のみ)
+    pub target_tile: Option<IVec2>,
+    pub inventory: inventory::Inventory,
+    /// 選択中のホットバーのスロット
+    pub selected_slot: usize,
+    pub breaking: Option<Breaking>,
+    /// 次に攻撃できるまでの残り時間 (秒)
+    pub attack_cooldown: f32,
+}
+
+impl PlayerState {
+    /// インベントリのスロット数
+    pub const INVENTORY_SIZE: usize = 36;
+
+    /// 指定したエンティティを操作する新しい状態を作成する。
+    #[inline]
+    pub fn new(entity_id: usize) -> Self {
+        Self {
+            entity_id,
+            target_base: None,
+            target_block: None,
+            target_entity: None,
+            target_tile: None,
+            inventory: inventory::Inventory::new(Self::INVENTORY_SIZE),
+            selected_slot: 0,
+            breaking: None,
+            attack_cooldown: 0.0,
+        }
+    }
+
+    /// 描写に使用するホットバーの状態を返す。
+    #[inline]
+    pub fn hotbar_view(&self) -> HotbarView {
+        HotbarView {
+            slots: self.inventory.hotbar().to_vec(),
+            selected_slot: self.selected_slot,
+        }
+    }
+}
+
+/// 描写に使用するホットバーの状態
+#[derive(Clone, Default)]
+pub struct HotbarView {
+    /// ホットバーのスロット (左から順)
+    pub slots: Vec<Option<inventory::ItemStack>>,
+    /// 選択中のスロット
+    pub selected_slot: usize,
 }
 
 /// プレイヤーシステムの機能
+///
+/// プレイヤーは[`component::PlayerControlled`]を持つエンティティとして表される。
+/// プレイヤーの状態はワールドの状態として[`WorldContext::player_state`]で共有される。
 pub struct PlayerSystem {
     assets: std::rc::Rc<assets::Assets>,
-    player_state: Option<PlayerState>,
 }
 
 impl PlayerSystem {
@@ -28,95 +88,497 @@ impl PlayerSystem {
     /// スプリント時の移動速度
     const SPRINT_SPEED: f32 = 4.0;
 
+    /// ブロックの破壊・設置ができる距離
+    const REACH: f32 = 5.0;
+
+    /// 攻撃の当たり判定の大きさ
+    const ATTACK_REACH: f32 = 1.0;
+
+    /// 攻撃のダメージ
+    const ATTACK_DAMAGE: f32 = 2.0;
+
+    /// 攻撃の間隔 (秒)
+    const ATTACK_COOLDOWN: f32 = 0.4;
+
     /// 新しいプレイヤーシステムを作成する。
     #[inline]
     pub fn new(assets: std::rc::Rc<assets::Assets>) -> Self {
-        Self {
-            assets,
-            player_state: Default::default(),
-        }
-    }
-
-    /// ゲームサイクルにおける振る舞いを実行する。
-    pub fn update(
-        &mut self,
-        input: &winit_input_helper::WinitInputHelper,
-        tick: &std::time::Duration,
-        window_size: (u32, u32),
-        base_storage: &mut base::BaseStorage,
-        block_storage: &mut block::BlockStorage,
-        entity_storage: &mut entity::EntityStorage,
-        camera_sys: &camera::CameraSystem,
-    ) {
-        match &mut self.player_state {
-            None => {
-                // NOTE: プレイヤーをワールド上に作成する。
-                let player_spec = &self.assets.player_specs[0];
-                let entity_spec = &self.assets.entity_specs[player_spec.entity_spec_id];
-
-                // NOTE: シームレスな焦点位置
-                let position = Vec2::ZERO - entity_spec.rendering_size.center();
-
-                let entity = entity::Entity::new(entity_spec.id, position);
-                let entity_id = entity_storage.insert(entity).unwrap();
-
-                self.player_state = Some(PlayerState {
-                    entity_id,
-                    target_base: None,
-                    target_block: None,
-                    target_entity: None,
-                });
+        Self { assets }
+    }
+
+    /// プレイヤーが操作するエンティティを探し、存在しない場合は作成する。
+    ///
+    /// エンティティが既に存在していたか真偽値を返す。
+    fn find_or_spawn(&self, context: &mut WorldContext) -> bool {
+        let entity_storage = &mut *context.entity_storage;
+        let entity_id = entity_storage
+            .components()
+            .iter_with::<(component::PlayerControlled,)>()
+            .next();
+
+        let Some(entity_id) = entity_id else {
+            // NOTE: プレイヤーをワールド上に作成する。
+            let player_spec = &self.assets.player_specs[0];
+            let entity_spec = &self.assets.entity_specs[player_spec.entity_spec_id];
+
+            // NOTE: シームレスな焦点位置
+            let position = Vec2::ZERO - entity_spec.rendering_size.center();
+
+            // NOTE: 他のエンティティと重なる場合は次のゲームサイクルで再試行する。
+            let entity = entity::Entity::new(entity_spec.id, position);
+            let Some(entity_id) = entity_storage.insert(entity) else {
+                return false;
+            };
+            let components = entity_storage.components_mut();
+            components.insert(entity_id, component::PlayerControlled);
+            components.insert(entity_id, component::Velocity::default());
+            components.insert(entity_id, component::Facing::default());
+            if let Some(health) = entity_spec.health {
+                components.insert(entity_id, component::Health::new(health));
             }
-            Some(player) => {
-                let entity = entity_storage.remove(player.entity_id).unwrap();
 
-                // NOTE: スプリント or 通常
-                let speed = if input.key_held(winit::keyboard::KeyCode::ShiftLeft) {
-                    Self::SPRINT_SPEED
+            // NOTE: 倒されて再び作成された場合もインベントリは引き継ぐ。
+            let mut player_state = PlayerState::new(entity_id);
+            if let Some(previous) = context.player_state.take() {
+                player_state.inventory = previous.inventory;
+                player_state.selected_slot = previous.selected_slot;
+            }
+            *context.player_state = Some(player_state);
+            return false;
+        };
+
+        let player_state = context
+            .player_state
+            .get_or_insert_with(|| PlayerState::new(entity_id));
+        player_state.entity_id = entity_id;
+        true
+    }
+
+    /// 移動・選択・ブロックの破壊と設置の操作を実行する。
+    fn control(&self, context: &mut WorldContext, player_state: &mut PlayerState) {
+        let input = context.input;
+        let tick = context.tick;
+        let entity_id = player_state.entity_id;
+        let speed = Self::speed(input);
+
+        // NOTE: プレイヤーの移動 (移動自体は速度として反映する)
+        let mut velocity = Vec2::ZERO;
+        if input.held(input::Action::MoveUp) {
+            velocity.y += speed;
+        }
+        if input.held(input::Action::MoveDown) {
+            velocity.y -= speed;
+        }
+        if input.held(input::Action::MoveLeft) {
+            velocity.x -= speed;
+        }
+        if input.held(input::Action::MoveRight) {
+            velocity.x += speed;
+        }
+
+        // NOTE: 移動の操作は経路に沿った移動より優先する。
+        let components = context.entity_storage.components_mut();
+        if velocity != Vec2::ZERO {
+            components.remove::<component::PathFollow>(entity_id);
+        }
+        if components.get::<component::PathFollow>(entity_id).is_none() {
+            components.insert(entity_id, component::Velocity(velocity));
+        }
+
+        // NOTE: ホットバーのスロットの選択
+        let hotbar_size = inventory::Inventory::HOTBAR_SIZE;
+        if input.pressed(input::Action::SelectNext) {
+            player_state.selected_slot = (player_state.selected_slot + 1) % hotbar_size;
+        }
+        if input.pressed(input::Action::SelectPrevious) {
+            player_state.selected_slot =
+                (player_state.selected_slot + hotbar_size - 1) % hotbar_size;
+        }
+        (0..hotbar_size).for_each(|slot_id| {
+            if input.pressed(input::Action::Hotbar(slot_id as u8)) {
+                player_state.selected_slot = slot_id;
+            }
+        });
+
+        // NOTE: オブジェクトの選択
+        let rect = input.cursor.map(|position| aabb2(position, position));
+
+        player_state.target_base = rect.and_then(|rect| {
+            context
+                .base_storage
+                .get_rendering_by_rect(rect)
+                .map(|(id, _)| id)
+                .next()
+        });
+        player_state.target_entity = rect.and_then(|rect| {
+            context
+                .entity_storage
+                .get_rendering_by_rect(rect)
+                .map(|(id, _)| id)
+                .next()
+        });
+
+        // NOTE: 届く範囲の判定 (プレイヤーの中心からの距離)
+        let entity = context.entity_storage.get(entity_id).unwrap();
+        let entity_spec = &self.assets.entity_specs[entity.spec_id];
+        let center = entity.position + entity_spec.internal_size * 0.5;
+        let cursor = input
+            .cursor
+            .filter(|cursor| cursor.distance(center) <= Self::REACH);
+
+        player_state.target_tile = cursor.map(|cursor| cursor.floor().as_ivec2());
+        player_state.target_block = cursor.and_then(|cursor| {
+            context
+                .block_storage
+                .get_rendering_by_rect(aabb2(cursor, cursor))
+                .map(|(id, _)| id)
+                .next()
+        });
+
+        // NOTE: ブロックの破壊 (押し続けた時間が硬さに達したら破壊する)
+        match player_state.target_block {
+            Some(block_id) if input.held(input::Action::Break) => {
+                let elapsed = match player_state.breaking {
+                    Some(breaking) if breaking.block_id == block_id => breaking.elapsed,
+                    _ => 0.0,
+                } + tick.as_secs_f32();
+
+                let block = context.block_storage.get(block_id).unwrap();
+                let block_spec = &self.assets.block_specs[block.spec_id];
+
+                if elapsed >= block_spec.hardness {
+                    let block = context.block_storage.remove(block_id).unwrap();
+
+                    // NOTE: ブロックの中心にアイテムを落とす。
+                    let center =
+                        block.position.as_vec2() + block_spec.internal_size.as_vec2() * 0.5;
+                    drop::ItemDropSystem::spawn_loot(context, &block_spec.loot, center);
+
+                    player_state.target_block = None;
+                    player_state.breaking = None;
                 } else {
-                    Self::DEFAULT_SPEED
-                };
+                    // NOTE: 耐久値を持つブロックは破壊の進行を状態に反映する。
+                    if let Some(assets::BlockStateSpec::Durability { max }) = block_spec.state_spec
+                    {
+                        let progress = elapsed / block_spec.hardness;
+                        let durability = (max as f32 * (1.0 - progress)).ceil() as u32;
+                        if block.state != block::BlockState::Durability(durability) {
+                            let mut block = block.clone();
+                            block.state = block::BlockState::Durability(durability);
+                            context.block_storage.modify(block_id, block);
+                        }
+                    }
 
-                // NOTE: プレイヤーの移動
-                let mut move_entity = entity.clone();
-                if input.key_held(winit::keyboard::KeyCode::KeyW) {
-                    move_entity.position.y += speed * tick.as_secs_f32();
-                }
-                if input.key_held(winit::keyboard::KeyCode::KeyS) {
-                    move_entity.position.y -= speed * tick.as_secs_f32();
+                    player_state.breaking = Some(Breaking { block_id, elapsed });
                 }
-                if input.key_held(winit::keyboard::KeyCode::KeyA) {
-                    move_entity.position.x -= speed * tick.as_secs_f32();
+            }
+            _ => {
+                // NOTE: 破壊を中断したブロックの状態を元に戻す。
+                if let Some(breaking) = player_state.breaking.take() {
+                    if let Some(block) = context.block_storage.get(breaking.block_id) {
+                        let block_spec = &self.assets.block_specs[block.spec_id];
+                        let state = block::BlockState::initial(block_spec);
+                        if block.state != state {
+                            let mut block = block.clone();
+                            block.state = state;
+                            context.block_storage.modify(breaking.block_id, block);
+                        }
+                    }
                 }
-                if input.key_held(winit::keyboard::KeyCode::KeyD) {
-                    move_entity.position.x += speed * tick.as_secs_f32();
+            }
+        }
+
+        // NOTE: ブロックの設置 (選択中のアイテムを1つ消費する)
+        let block_spec_id = player_state
+            .inventory
+            .get(player_state.selected_slot)
+            .and_then(|stack| self.assets.item_specs[stack.item_spec_id].block_spec_id);
+        if let (Some(position), Some(block_spec_id)) = (player_state.target_tile, block_spec_id) {
+            if input.pressed(input::Action::Place) {
+                let block_spec = &self.assets.block_specs[block_spec_id];
+                let rect = aabb2(
+                    position.as_vec2(),
+                    (position + block_spec.internal_size).as_vec2(),
+                );
+
+                // NOTE: 他のブロック・エンティティと重なる場合は設置しない。
+                if !context.entity_storage.has_internal_by_rect(rect) {
+                    let z_random = Self::z_random(position);
+                    let state = block::BlockState::initial(block_spec);
+                    let block = block::Block::new(block_spec.id, position, z_random, state);
+                    if context.block_storage.insert(block).is_some() {
+                        player_state.inventory.take_one(player_state.selected_slot);
+                    }
                 }
-                player.entity_id = entity_storage.insert(move_entity).unwrap();
-
-                // NOTE: オブジェクトの選択
-                let (x, y) = input.mouse_diff();
-                let matrix = camera_sys.get().world_to_viewport(window_size).inverse();
-
-                let position = matrix.project_point3(vec3(x, y, 0.0)).xy();
-                let rect = aabb2(position, position);
-
-                player.target_base = base_storage
-                    .get_rendering_by_rect(rect)
-                    .map(|(id, _)| id)
-                    .next();
-                player.target_block = block_storage
-                    .get_rendering_by_rect(rect)
-                    .map(|(id, _)| id)
-                    .next();
-                player.target_entity = entity_storage
-                    .get_rendering_by_rect(rect)
-                    .map(|(id, _)| id)
-                    .next();
             }
         }
     }
 
-    pub fn get_player(&self) -> Option<&PlayerState> {
-        self.player_state.as_ref()
+    /// カーソルの位置への経路を探索し、経路に沿った移動を始める。
+    fn move_to(&self, context: &mut WorldContext, player_state: &PlayerState) {
+        let input = context.input;
+        let entity_id = player_state.entity_id;
+        let Some(entity) = context.entity_storage.get(entity_id) else {
+            return;
+        };
+        let Some(cursor) = input
+            .cursor
+            .filter(|_| input.pressed(input::Action::MoveTo))
+        else {
+            return;
+        };
+
+        let entity_spec = &self.assets.entity_specs[entity.spec_id];
+        let footprint = context.pathfinding_sys.footprint(entity_spec);
+        let start = entity.position.round().as_ivec2();
+        let goal = cursor.floor().as_ivec2();
+
+        let path = context
+            .pathfinding_sys
+            .find_path(context.block_storage, start, goal, footprint);
+        if let Some(path) = path {
+            let path_follow = component::PathFollow {
+                waypoints: path.into_iter().map(|point| point.as_vec2()).collect(),
+                speed: Self::speed(input),
+            };
+            context
+                .entity_storage
+                .components_mut()
+                .insert(entity_id, path_follow);
+        }
+    }
+
+    /// 向いている方向へ攻撃する。
+    fn attack(&self, context: &mut WorldContext, player_state: &mut PlayerState) {
+        let delta_secs = context.tick.as_secs_f32();
+        player_state.attack_cooldown = (player_state.attack_cooldown - delta_secs).max(0.0);
+        if player_state.attack_cooldown > 0.0 || !context.input.pressed(input::Action::Attack) {
+            return;
+        }
+
+        combat::CombatSystem::attack(
+            context,
+            player_state.entity_id,
+            Self::ATTACK_REACH,
+            Self::ATTACK_DAMAGE,
+        );
+        player_state.attack_cooldown = Self::ATTACK_COOLDOWN;
+    }
+
+    /// カーソルの方向へ投射物を発射する。
+    ///
+    /// カーソルがない場合は向いている方向へ発射する。攻撃と間隔を共有する。
+    fn shoot(&self, context: &mut WorldContext, player_state: &mut PlayerState) {
+        let input = context.input;
+        let entity_storage = &*context.entity_storage;
+        let Some(projectile_spec_id) = self.assets.player_specs[0].projectile_spec_id else {
+            return;
+        };
+        if player_state.attack_cooldown > 0.0 || !input.pressed(input::Action::Shoot) {
+            return;
+        }
+
+        let Some(entity) = entity_storage.get(player_state.entity_id) else {
+            return;
+        };
+        let spec = &self.assets.entity_specs[entity.spec_id];
+        let center = entity.position + spec.internal_size * 0.5;
+        let direction = input
+            .cursor
+            .and_then(|cursor| (cursor - center).try_normalize())
+            .unwrap_or_else(|| {
+                entity_storage
+                    .components()
+                    .get::<component::Facing>(player_state.entity_id)
+                    .copied()
+                    .unwrap_or_default()
+                    .0
+            });
+
+        projectile::ProjectileSystem::spawn(
+            context,
+            projectile_spec_id,
+            center,
+            direction,
+            Some(player_state.entity_id),
+        );
+        player_state.attack_cooldown = Self::ATTACK_COOLDOWN;
+    }
+
+    /// 移動速度を返す。(スプリント or 通常)
+    #[inline]
+    fn speed(input: &input::InputState) -> f32 {
+        if input.held(input::Action::Sprint) {
+            Self::SPRINT_SPEED
+        } else {
+            Self::DEFAULT_SPEED
+        }
+    }
+
+    /// 設置するブロックの位置に固有の乱数を返す。
+    ///
+    /// 同じ入力から同じワールドが再現されるように、位置のみから決定する。
+    fn z_random(position: IVec2) -> u8 {
+        (random::hash_position(position) >> 24) as u8
+    }
+}
+
+impl System for PlayerSystem {
+    #[inline]
+    fn phase(&self) -> Phase {
+        Phase::Input
+    }
+
+    /// プレイヤーの操作を実行する。
+    ///
+    /// 操作の間、プレイヤーの状態はワールドの状態から取り出される。
+    fn update(&mut self, context: &mut WorldContext) {
+        let existed = self.find_or_spawn(context);
+        let Some(mut player_state) = context.player_state.take() else {
+            return;
+        };
+
+        // NOTE: 作成したばかりのエンティティは次のティックから操作する。
+        if existed {
+            self.control(context, &mut player_state);
+        }
+        self.attack(context, &mut player_state);
+        self.shoot(context, &mut player_state);
+
+        // NOTE: カーソルの位置への経路に沿った移動
+        self.move_to(context, &player_state);
+
+        *context.player_state = Some(player_state);
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::game_loop::GameLoop;
+
+    /// 生成されたブロックとプレイヤー以外のエンティティを取り除いたゲームループを返す。
+    fn empty_game_loop() -> (GameLoop, Vec2) {
+        let assets = assets::Assets::new("assets/assets.json");
+        let mut game_loop = GameLoop::new(std::rc::Rc::new(assets), 0);
+        game_loop.update(&Default::default(), &GameLoop::TICK);
+
+        let player_id = game_loop.player_state.as_ref().unwrap().entity_id;
+        game_loop.block_storage.restore(std::iter::empty());
+        let others = game_loop
+            .entity_storage
+            .iter()
+            .map(|(id, _)| id)
+            .filter(|id| *id != player_id)
+            .collect::<Vec<_>>();
+        others.into_iter().for_each(|id| {
+            game_loop.entity_storage.remove(id);
+        });
+
+        let position = game_loop.entity_storage.get(player_id).unwrap().position;
+        (game_loop, position)
+    }
+
+    /// 同じ入力で指定した回数のティックを実行する。
+    fn run(game_loop: &mut GameLoop, input: &input::InputState, ticks: u32) {
+        (0..ticks).for_each(|_| {
+            game_loop.update(input, &GameLoop::TICK);
+            game_loop.discard_changes();
+        });
+    }
+
+    fn spec_id<T>(specs: &[T], label: impl Fn(&T) -> &str, target: &str) -> usize {
+        specs.iter().position(|spec| label(spec) == target).unwrap()
+    }
+
+    #[test]
+    fn hold_to_break() {
+        let (mut game_loop, player_position) = empty_game_loop();
+        let assets = game_loop.assets.clone();
+        let rock_spec = &assets.block_specs[spec_id(&assets.block_specs, |s| &s.label, "mixRock")];
+        let rock_item = spec_id(&assets.item_specs, |s| &s.label, "mixRock");
+
+        let position = player_position.floor().as_ivec2() + ivec2(3, 0);
+        let initial = block::BlockState::initial(rock_spec);
+        let block = block::Block::new(rock_spec.id, position, 0, initial);
+        let block_id = game_loop.block_storage.insert(block).unwrap();
+
+        let mut input = input::InputState {
+            cursor: Some(position.as_vec2() + 0.5),
+            ..Default::default()
+        };
+        input.press(input::Action::Break);
+        input.advance();
+
+        // NOTE: 硬さの半分の時間で耐久値が減る。
+        let half = (rock_spec.hardness * 0.5 / GameLoop::TICK.as_secs_f32()) as u32;
+        run(&mut game_loop, &input, half + 1);
+        let block = game_loop.block_storage.get(block_id).unwrap();
+        assert!(block.state == block::BlockState::Durability(2));
+
+        // NOTE: 離すと元に戻る。
+        input.release(input::Action::Break);
+        run(&mut game_loop, &input, 1);
+        let block = game_loop.block_storage.get(block_id).unwrap();
+        assert!(block.state == initial);
+        assert!(game_loop.player_state.as_ref().unwrap().breaking.is_none());
+
+        // NOTE: 硬さに達すると破壊され、落ちたアイテムが引き寄せられて拾われる。
+        input.press(input::Action::Break);
+        input.advance();
+        let full = (rock_spec.hardness / GameLoop::TICK.as_secs_f32()) as u32;
+        run(&mut game_loop, &input, full - 1);
+        assert!(game_loop.block_storage.get(block_id).is_some());
+        run(&mut game_loop, &input, 2);
+        assert!(game_loop.block_storage.get(block_id).is_none());
+
+        run(&mut game_loop, &Default::default(), 60);
+        let player_state = game_loop.player_state.as_ref().unwrap();
+        assert_eq!(player_state.inventory.count(rock_item), 1);
+    }
+
+    #[test]
+    fn place_consumes_selected_item() {
+        let (mut game_loop, player_position) = empty_game_loop();
+        let assets = game_loop.assets.clone();
+        let pebbles = spec_id(&assets.item_specs, |s| &s.label, "mixPebbles");
+        let block_spec_id = assets.item_specs[pebbles].block_spec_id.unwrap();
+
+        let player_state = game_loop.player_state.as_mut().unwrap();
+        player_state.inventory = inventory::Inventory::new(PlayerState::INVENTORY_SIZE);
+        player_state.inventory.insert(&assets, pebbles, 2);
+        player_state.selected_slot = 0;
+
+        let place = |game_loop: &mut GameLoop, tile: IVec2| {
+            let mut input = input::InputState {
+                cursor: Some(tile.as_vec2() + 0.5),
+                ..Default::default()
+            };
+            input.press(input::Action::Place);
+            run(game_loop, &input, 1);
+        };
+
+        let tile = player_position.floor().as_ivec2() + ivec2(2, 0);
+        place(&mut game_loop, tile);
+        let rect = aabb2(tile.as_vec2() + 0.5, tile.as_vec2() + 0.5);
+        let (_, block) = game_loop
+            .block_storage
+            .get_rendering_by_rect(rect)
+            .next()
+            .unwrap();
+        assert_eq!(block.spec_id, block_spec_id);
+        let player_state = game_loop.player_state.as_ref().unwrap();
+        assert_eq!(player_state.inventory.count(pebbles), 1);
+
+        // NOTE: プレイヤーと重なる位置には設置しない。
+        let player_tile = player_position.floor().as_ivec2();
+        place(&mut game_loop, player_tile);
+        let player_state = game_loop.player_state.as_ref().unwrap();
+        assert_eq!(player_state.inventory.count(pebbles), 1);
+
+        // NOTE: 届く範囲の外には設置しない。
+        place(&mut game_loop, tile + ivec2(10, 0));
+        let player_state = game_loop.player_state.as_ref().unwrap();
+        assert_eq!(player_state.inventory.count(pebbles), 1);
     }
 }
@@ -0,0 +1,365 @@
+//! 投射物の機能に関するモジュール
+
+use glam::*;
+
+use crate::aabb::*;
+use crate::{
+    assets,
+    game_loop::{
+        combat, component, drop, entity,
+        system::{Phase, System, WorldContext},
+    },
+};
+
+/// 投射物が命中した対象
+#[derive(Clone, Copy)]
+enum Hit {
+    Block(usize),
+    Entity(usize),
+}
+
+/// 投射物の機能
+///
+/// 投射物は[`component::Projectile`]を持つエンティティとして表される。
+/// 高速でもすり抜けないように、1ティックの移動範囲全体で衝突を判定する。
+pub struct ProjectileSystem {
+    assets: std::rc::Rc<assets::Assets>,
+}
+
+impl ProjectileSystem {
+    /// 新しい投射物システムを作成する。
+    #[inline]
+    pub fn new(assets: std::rc::Rc<assets::Assets>) -> Self {
+        Self { assets }
+    }
+
+    /// 指定した位置を中心として投射物を発射し、エンティティの識別子を返す。
+    pub fn spawn(
+        context: &mut WorldContext,
+        projectile_spec_id: usize,
+        center: Vec2,
+        direction: Vec2,
+        owner: Option<usize>,
+    ) -> Option<usize> {
+        let entity_storage = &mut *context.entity_storage;
+        let projectile_spec = &context.assets.projectile_specs[projectile_spec_id];
+        let entity_spec = &context.assets.entity_specs[projectile_spec.entity_spec_id];
+
+        let position = center - entity_spec.internal_size * 0.5;
+        let entity = entity::Entity::new(entity_spec.id, position);
+        let entity_id = entity_storage.insert(entity)?;
+
+        let projectile = component::Projectile {
+            projectile_spec_id,
+            owner,
+            velocity: direction.normalize_or_zero() * projectile_spec.speed,
+            remaining: projectile_spec.lifetime,
+            stuck: false,
+            attached: None,
+        };
+        entity_storage
+            .components_mut()
+            .insert(entity_id, projectile);
+
+        Some(entity_id)
+    }
+}
+
+impl System for ProjectileSystem {
+    #[inline]
+    fn phase(&self) -> Phase {
+        Phase::Simulation
+    }
+
+    /// 全ての投射物を移動させ、命中した時の効果を実行する。
+    fn update(&mut self, context: &mut WorldContext) {
+        let delta_secs = context.tick.as_secs_f32();
+
+        let entity_ids = context
+            .entity_storage
+            .components()
+            .iter_with::<(component::Projectile,)>()
+            .collect::<Vec<_>>();
+
+        entity_ids.into_iter().for_each(|entity_id| {
+            let Some(entity) = context.entity_storage.get(entity_id).cloned() else {
+                return;
+            };
+            let mut projectile = *context
+                .entity_storage
+                .components()
+                .get::<component::Projectile>(entity_id)
+                .unwrap();
+
+            // NOTE: 消滅
+            projectile.remaining -= delta_secs;
+            if projectile.remaining <= 0.0 {
+                context.entity_storage.remove(entity_id);
+                return;
+            }
+
+            // NOTE: 刺さっている投射物は対象と共に移動する。
+            if projectile.stuck {
+                if let Some((target_id, offset)) = projectile.attached {
+                    let Some(target) = context.entity_storage.get(target_id) else {
+                        context.entity_storage.remove(entity_id);
+                        return;
+                    };
+                    let mut entity = entity.clone();
+                    entity.position = target.position + offset;
+                    context.entity_storage.modify(entity_id, entity);
+                }
+                context
+                    .entity_storage
+                    .components_mut()
+                    .insert(entity_id, projectile);
+                return;
+            }
+
+            let entity_spec = &self.assets.entity_specs[entity.spec_id];
+            let rect = aabb2(entity.position, entity.position + entity_spec.internal_size);
+            let delta = projectile.velocity * delta_secs;
+            let swept_rect = rect.union(rect + delta);
+
+            // NOTE: 移動範囲内で最も早く命中する対象を探す。
+            let block_hits = context
+                .block_storage
+                .get_internal_by_rect(swept_rect.trunc_over().as_iaabb2())
+                .filter(|(_, block)| self.assets.block_specs[block.spec_id].solid)
+                .filter_map(|(block_id, block)| {
+                    let spec = &self.assets.block_specs[block.spec_id];
+                    let block_rect = aabb2(
+                        block.position.as_vec2(),
+                        (block.position + spec.internal_size).as_vec2(),
+                    );
+                    let time = rect.sweep(delta, block_rect)?;
+                    Some((time, Hit::Block(block_id)))
+                });
+            let entity_hits = context
+                .entity_storage
+                .get_internal_by_rect(swept_rect)
+                .filter(|(target_id, _)| {
+                    *target_id != entity_id && Some(*target_id) != projectile.owner
+                })
+                .filter(|(target_id, _)| {
+                    context
+                        .entity_storage
+                        .components()
+                        .get::<component::Health>(*target_id)
+                        .is_some()
+                })
+                .filter_map(|(target_id, target)| {
+                    let spec = &self.assets.entity_specs[target.spec_id];
+                    let target_rect = aabb2(target.position, target.position + spec.internal_size);
+                    let time = rect.sweep(delta, target_rect)?;
+                    Some((time, Hit::Entity(target_id)))
+                });
+            let hit = block_hits
+                .chain(entity_hits)
+                .min_by(|(a, _), (b, _)| a.total_cmp(b));
+
+            let Some((time, hit)) = hit else {
+                let mut entity = entity.clone();
+                entity.position += delta;
+                context.entity_storage.modify(entity_id, entity);
+                context
+                    .entity_storage
+                    .components_mut()
+                    .insert(entity_id, projectile);
+                return;
+            };
+
+            // NOTE: 命中した位置まで移動する。
+            let mut entity = entity.clone();
+            entity.position += delta * time;
+            let position = entity.position;
+            context.entity_storage.modify(entity_id, entity);
+
+            let projectile_spec = &self.assets.projectile_specs[projectile.projectile_spec_id];
+            projectile_spec
+                .effects
+                .iter()
+                .for_each(|effect| match (effect, hit) {
+                    (assets::ProjectileEffectSpec::Damage { amount }, Hit::Entity(target_id)) => {
+                        combat::CombatSystem::damage(
+                            context.entity_storage,
+                            target_id,
+                            *amount,
+                            projectile.velocity,
+                        );
+                    }
+                    (assets::ProjectileEffectSpec::Stick { duration }, hit) => {
+                        projectile.stuck = true;
+                        projectile.velocity = Vec2::ZERO;
+                        projectile.remaining = *duration;
+                        projectile.attached = match hit {
+                            Hit::Entity(target_id) => context
+                                .entity_storage
+                                .get(target_id)
+                                .map(|target| (target_id, position - target.position)),
+                            Hit::Block(_) => None,
+                        };
+                    }
+                    (assets::ProjectileEffectSpec::BreakBlock, Hit::Block(block_id)) => {
+                        if let Some(block) = context.block_storage.remove(block_id) {
+                            let spec = &self.assets.block_specs[block.spec_id];
+                            let center =
+                                block.position.as_vec2() + spec.internal_size.as_vec2() * 0.5;
+                            drop::ItemDropSystem::spawn_loot(context, &spec.loot, center);
+                        }
+                    }
+                    _ => {}
+                });
+
+            if projectile.stuck {
+                context
+                    .entity_storage
+                    .components_mut()
+                    .insert(entity_id, projectile);
+            } else {
+                context.entity_storage.remove(entity_id);
+            }
+        });
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::game_loop::{block, input, GameLoop};
+
+    /// 投射物を指定した速さに変更し、生成されたブロックとプレイヤー以外のエンティティを取り除いたゲームループを返す。
+    fn empty_game_loop(speed: f32) -> (GameLoop, usize) {
+        let mut assets = assets::Assets::new("assets/assets.json");
+        assets
+            .projectile_specs
+            .iter_mut()
+            .for_each(|spec| spec.speed = speed);
+        let mut game_loop = GameLoop::new(std::rc::Rc::new(assets), 0);
+        game_loop.update(&Default::default(), &GameLoop::TICK);
+
+        let player_id = game_loop.player_state.as_ref().unwrap().entity_id;
+        game_loop.block_storage.restore(std::iter::empty());
+        let others = game_loop
+            .entity_storage
+            .iter()
+            .map(|(id, _)| id)
+            .filter(|id| *id != player_id)
+            .collect::<Vec<_>>();
+        others.into_iter().for_each(|id| {
+            game_loop.entity_storage.remove(id);
+        });
+        (game_loop, player_id)
+    }
+
+    /// ゲームループの状態からワールドの状態を組み立てて投射物を発射する。
+    fn shoot(game_loop: &mut GameLoop, label: &str, center: Vec2, owner: Option<usize>) -> usize {
+        let projectile_spec_id = game_loop
+            .assets
+            .projectile_specs
+            .iter()
+            .position(|spec| spec.label == label)
+            .unwrap();
+        let input = input::InputState::default();
+        let mut context = WorldContext {
+            assets: &game_loop.assets,
+            tick: std::time::Duration::ZERO,
+            input: &input,
+            rng: &mut game_loop.rng,
+            clock_sys: &mut game_loop.clock_sys,
+            weather_state: &mut game_loop.weather_state,
+            pathfinding_sys: &mut game_loop.pathfinding_sys,
+            camera_state: &mut game_loop.camera_state,
+            base_storage: &mut game_loop.base_storage,
+            block_storage: &mut game_loop.block_storage,
+            entity_storage: &mut game_loop.entity_storage,
+            player_state: &mut game_loop.player_state,
+            generation_scripts: &mut game_loop.generation_scripts,
+        };
+        ProjectileSystem::spawn(&mut context, projectile_spec_id, center, Vec2::X, owner).unwrap()
+    }
+
+    fn center(game_loop: &GameLoop, entity_id: usize) -> Vec2 {
+        let entity = game_loop.entity_storage.get(entity_id).unwrap();
+        let spec = &game_loop.assets.entity_specs[entity.spec_id];
+        entity.position + spec.internal_size * 0.5
+    }
+
+    fn health(game_loop: &GameLoop, entity_id: usize) -> component::Health {
+        *game_loop
+            .entity_storage
+            .components()
+            .get::<component::Health>(entity_id)
+            .unwrap()
+    }
+
+    #[test]
+    fn fast_projectile_hits_target() {
+        // NOTE: 1ティックで10タイル進み、移動後の位置は対象を通り越す。
+        let (mut game_loop, player_id) = empty_game_loop(600.0);
+        let sheep = game_loop
+            .assets
+            .entity_specs
+            .iter()
+            .position(|spec| spec.label == "sheep")
+            .unwrap();
+        let origin = center(&game_loop, player_id);
+        let entity = entity::Entity::new(sheep, origin + vec2(4.0, -0.25));
+        let target_id = game_loop.entity_storage.spawn(entity).unwrap();
+        let player_health = health(&game_loop, player_id).current;
+        let target_health = health(&game_loop, target_id).current;
+
+        // NOTE: 発射したエンティティには命中しない。
+        let arrow_id = shoot(&mut game_loop, "arrow", origin, Some(player_id));
+        game_loop.update(&Default::default(), &GameLoop::TICK);
+        game_loop.discard_changes();
+
+        assert_eq!(health(&game_loop, player_id).current, player_health);
+        assert_eq!(health(&game_loop, target_id).current, target_health - 3.0);
+
+        // NOTE: 命中した位置で対象に刺さる。
+        let projectile = *game_loop
+            .entity_storage
+            .components()
+            .get::<component::Projectile>(arrow_id)
+            .unwrap();
+        assert!(projectile.stuck);
+        assert_eq!(projectile.attached.map(|(id, _)| id), Some(target_id));
+        let target = game_loop.entity_storage.get(target_id).unwrap();
+        let arrow = game_loop.entity_storage.get(arrow_id).unwrap();
+        assert!(arrow.position.x < target.position.x + 1.0);
+    }
+
+    #[test]
+    fn fast_projectile_breaks_nearest_block() {
+        let (mut game_loop, player_id) = empty_game_loop(600.0);
+        let assets = game_loop.assets.clone();
+        let rock = assets
+            .block_specs
+            .iter()
+            .find(|spec| spec.label == "mixRock")
+            .unwrap();
+        let origin = center(&game_loop, player_id);
+        let tile = origin.floor().as_ivec2() + ivec2(3, -1);
+
+        let mut place = |position: IVec2| {
+            let state = block::BlockState::initial(rock);
+            let block = block::Block::new(rock.id, position, 0, state);
+            game_loop.block_storage.insert(block).unwrap()
+        };
+        let near = place(tile);
+        let far = place(tile + ivec2(3, 0));
+
+        let bolt_id = shoot(&mut game_loop, "bolt", origin, Some(player_id));
+        game_loop.update(&Default::default(), &GameLoop::TICK);
+        game_loop.discard_changes();
+
+        assert!(game_loop.block_storage.get(near).is_none());
+        assert!(game_loop.block_storage.get(far).is_some());
+        assert!(game_loop
+            .entity_storage
+            .components()
+            .get::<component::Projectile>(bolt_id)
+            .is_none());
+    }
+}
@@ -0,0 +1,89 @@
+//! 決定的な乱数に関するモジュール
+//!
+//! 同じシード値と入力から同じワールドを再現するため、
+//! 乱数は実行環境に依らない固定の手順で導出する。
+
+use glam::*;
+
+/// SplitMix64 により値を混合する。
+#[inline]
+pub fn mix(state: u64) -> u64 {
+    let z = state.wrapping_add(0x9e3779b97f4a7c15);
+    let z = (z ^ (z >> 30)).wrapping_mul(0xbf58476d1ce4e5b9);
+    let z = (z ^ (z >> 27)).wrapping_mul(0x94d049bb133111eb);
+    z ^ (z >> 31)
+}
+
+/// シード値に値の列を順に混合する。
+#[inline]
+pub fn combine(seed: u64, values: impl IntoIterator<Item = u64>) -> u64 {
+    values
+        .into_iter()
+        .fold(seed, |state, value| mix(state ^ value))
+}
+
+/// タイルの位置に固有のハッシュ値を返す。
+#[inline]
+pub fn hash_position(position: IVec2) -> u32 {
+    (position.x as u32)
+        .wrapping_mul(0x9e3779b1)
+        .wrapping_add((position.y as u32).wrapping_mul(0x85ebca77))
+}
+
+/// FNV-1a によるバイト列のハッシュ値を返す。
+///
+/// 鍵を持たないため、実行毎・環境毎に同じ値となる。
+pub fn fnv1a(bytes: &[u8]) -> u64 {
+    bytes.iter().fold(0xcbf29ce484222325, |hash, byte| {
+        (hash ^ *byte as u64).wrapping_mul(0x100000001b3)
+    })
+}
+
+/// SplitMix64 による乱数生成器
+///
+/// 状態が1つの整数のため、そのままセーブデータへ保存できる。
+#[derive(Clone, Debug)]
+pub struct SplitMix64 {
+    state: u64,
+}
+
+impl SplitMix64 {
+    /// シード値から新しい乱数生成器を作成する。
+    #[inline]
+    pub fn new(seed: u64) -> Self {
+        Self { state: seed }
+    }
+
+    /// 現在の状態を返す。(`new`へ渡すと続きから再開できる)
+    #[inline]
+    pub fn state(&self) -> u64 {
+        self.state
+    }
+}
+
+impl rand::RngCore for SplitMix64 {
+    #[inline]
+    fn next_u32(&mut self) -> u32 {
+        (self.next_u64() >> 32) as u32
+    }
+
+    #[inline]
+    fn next_u64(&mut self) -> u64 {
+        let value = mix(self.state);
+        self.state = self.state.wrapping_add(0x9e3779b97f4a7c15);
+        value
+    }
+
+    fn fill_bytes(&mut self, dest: &mut [u8]) {
+        dest.chunks_mut(8).for_each(|chunk| {
+            let bytes = self.next_u64().to_le_bytes();
+            chunk.copy_from_slice(&bytes[..chunk.len()]);
+        });
+    }
+
+    #[inline]
+    fn try_fill_bytes(&mut self, dest: &mut [u8]) -> Result<(), rand::Error> {
+        self.fill_bytes(dest);
+        Ok(())
+    }
+}
@@ -0,0 +1,186 @@
+//! 入力の記録と再生の機能に関するモジュール
+//!
+//! 記録はワールド生成のシード値と、フレーム毎の入力と経過時間からなる。
+//! ゲームループは同じシード値と入力から同じ状態を再現するため、
+//! 記録を再生することで不具合の再現や回帰テストに使用できる。
+//!
+//! 記録は識別用のマジックナンバー、フォーマットのバージョン、
+//! [`bincode`]で符号化された[`Recording`]の順に書き込まれる。
+
+use std::io::{Read, Write};
+
+use glam::*;
+
+use crate::{
+    assets,
+    game_loop::{input, save::SaveError, GameLoop},
+};
+
+/// 記録を識別するためのマジックナンバー
+pub const MAGIC: [u8; 4] = *b"WTGR";
+
+/// 記録のフォーマットのバージョン
+pub const VERSION: u32 = 1;
+
+/// 入力の記録
+#[derive(serde::Serialize, serde::Deserialize)]
+pub struct Recording {
+    pub seed: u64,
+    pub frames: Vec<FrameData>,
+    /// 記録終了時のワールドの状態のハッシュ値
+    pub state_hash: Option<u64>,
+}
+
+/// 1フレームの入力と経過時間
+#[derive(serde::Serialize, serde::Deserialize)]
+pub struct FrameData {
+    pub elapsed_nanos: u64,
+    pub held: Vec<input::Action>,
+    pub pressed: Vec<input::Action>,
+    pub cursor: Option<[f32; 2]>,
+    pub scroll: f32,
+}
+
+impl From<&FrameData> for input::InputState {
+    fn from(data: &FrameData) -> Self {
+        input::InputState {
+            held: data.held.iter().copied().collect(),
+            pressed: data.pressed.iter().copied().collect(),
+            cursor: data.cursor.map(Vec2::from_array),
+            scroll: data.scroll,
+        }
+    }
+}
+
+impl Recording {
+    /// 新しい空の記録を作成する。
+    #[inline]
+    pub fn new(seed: u64) -> Self {
+        Self {
+            seed,
+            frames: Default::default(),
+            state_hash: None,
+        }
+    }
+
+    /// フレームの入力と経過時間を記録する。
+    pub fn record(&mut self, input: &input::InputState, elapsed: &std::time::Duration) {
+        self.frames.push(FrameData {
+            elapsed_nanos: elapsed.as_nanos() as u64,
+            held: input.held.iter().copied().collect(),
+            pressed: input.pressed.iter().copied().collect(),
+            cursor: input.cursor.map(|cursor| cursor.to_array()),
+            scroll: input.scroll,
+        });
+    }
+
+    /// 記録を再生したゲームループを返す。
+    ///
+    /// ウィンドウを必要とせず、記録時と同じ状態のゲームループが得られる。
+    pub fn replay(&self, assets: std::rc::Rc<assets::Assets>) -> GameLoop {
+        let mut game_loop = GameLoop::new(assets, self.seed);
+        self.frames.iter().for_each(|frame| {
+            let elapsed = std::time::Duration::from_nanos(frame.elapsed_nanos);
+            game_loop.update(&frame.into(), &elapsed);
+
+            // NOTE: 抽出しないため、変更履歴はここで破棄する。
+            game_loop.discard_changes();
+        });
+        game_loop
+    }
+}
+
+/// 記録を書き込む。
+pub fn write<W: Write>(mut writer: W, recording: &Recording) -> Result<(), SaveError> {
+    writer.write_all(&MAGIC)?;
+    writer.write_all(&VERSION.to_le_bytes())?;
+    bincode::serialize_into(&mut writer, recording)?;
+    writer.flush()?;
+    Ok(())
+}
+
+/// 記録を読み込む。
+pub fn read<R: Read>(mut reader: R) -> Result<Recording, SaveError> {
+    let mut magic = [0; 4];
+    reader.read_exact(&mut magic)?;
+    if magic != MAGIC {
+        return Err(SaveError::Magic);
+    }
+
+    let mut version = [0; 4];
+    reader.read_exact(&mut version)?;
+    let version = u32::from_le_bytes(version);
+    if version != VERSION {
+        return Err(SaveError::Version(version));
+    }
+
+    let recording = bincode::deserialize_from(&mut reader)?;
+    Ok(recording)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    /// 短い入力の列を記録し、記録終了時の状態のハッシュ値を保存する。
+    fn record(assets: std::rc::Rc<assets::Assets>, seed: u64) -> Recording {
+        let mut game_loop = GameLoop::new(assets, seed);
+        let mut recording = Recording::new(seed);
+        (0..90).for_each(|frame| {
+            let mut input = input::InputState::default();
+            if frame < 60 {
+                input.held.insert(input::Action::MoveRight);
+            }
+            if frame % 30 == 0 {
+                input.pressed.insert(input::Action::MoveUp);
+            }
+            // NOTE: フレームの経過時間はティックと一致しない。
+            let elapsed = std::time::Duration::from_millis(7 + frame % 20);
+
+            recording.record(&input, &elapsed);
+            game_loop.update(&input, &elapsed);
+            game_loop.discard_changes();
+        });
+        recording.state_hash = Some(game_loop.state_hash());
+        recording
+    }
+
+    #[test]
+    fn replay_reproduces_state_hash() {
+        let assets = std::rc::Rc::new(assets::Assets::new("assets/assets.json"));
+        let recording = record(assets.clone(), 3);
+
+        let mut bytes = vec![];
+        write(&mut bytes, &recording).unwrap();
+        let recording = read(bytes.as_slice()).unwrap();
+        let state_hash = recording.replay(assets.clone()).state_hash();
+        assert_eq!(Some(state_hash), recording.state_hash);
+
+        // NOTE: 異なるシード値では同じ状態を再現しない。
+        let mut recording = recording;
+        recording.seed += 1;
+        let state_hash = recording.replay(assets).state_hash();
+        assert_ne!(Some(state_hash), recording.state_hash);
+    }
+
+    #[test]
+    fn read_rejects_other_formats() {
+        let recording = Recording::new(0);
+        let mut bytes = vec![];
+        write(&mut bytes, &recording).unwrap();
+
+        let mut other_version = bytes.clone();
+        other_version[4..8].copy_from_slice(&(VERSION + 1).to_le_bytes());
+        assert!(matches!(
+            read(other_version.as_slice()),
+            Err(SaveError::Version(version)) if version == VERSION + 1
+        ));
+
+        let mut other_magic = bytes;
+        other_magic[..4].copy_from_slice(b"WTGS");
+        assert!(matches!(
+            read(other_magic.as_slice()),
+            Err(SaveError::Magic)
+        ));
+    }
+}
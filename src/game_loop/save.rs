@@ -0,0 +1,375 @@
+//! セーブデータの機能に関するモジュール
+//!
+//! セーブデータは識別用のマジックナンバー、フォーマットのバージョン、
+//! [`bincode`]で符号化された[`SaveData`]の順に書き込まれる。
+
+use std::io::{Read, Write};
+
+use crate::game_loop::block;
+
+/// セーブデータを識別するためのマジックナンバー
+pub const MAGIC: [u8; 4] = *b"WTGS";
+
+/// セーブデータのフォーマットのバージョン
+pub const VERSION: u32 = 17;
+
+/// セーブデータの読み書きに関するエラー
+#[derive(Debug)]
+pub enum SaveError {
+    /// 入出力に失敗した。
+    Io(std::io::Error),
+    /// 符号化・復号に失敗した。
+    Encoding(bincode::Error),
+    /// セーブデータではない。
+    Magic,
+    /// 対応していないバージョンである。
+    Version(u32),
+    /// 対応する仕様が存在しないラベルである。
+    Label(String),
+    /// 内容が矛盾している。
+    Corrupt(String),
+}
+
+impl std::fmt::Display for SaveError {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            SaveError::Io(e) => write!(f, "io error: {}", e),
+            SaveError::Encoding(e) => write!(f, "encoding error: {}", e),
+            SaveError::Magic => write!(f, "not a save data"),
+            SaveError::Version(version) => write!(f, "unsupported version: {}", version),
+            SaveError::Label(label) => write!(f, "unknown spec label: {}", label),
+            SaveError::Corrupt(reason) => write!(f, "corrupt save data: {}", reason),
+        }
+    }
+}
+
+impl std::error::Error for SaveError {}
+
+impl From<std::io::Error> for SaveError {
+    #[inline]
+    fn from(e: std::io::Error) -> Self {
+        SaveError::Io(e)
+    }
+}
+
+impl From<bincode::Error> for SaveError {
+    #[inline]
+    fn from(e: bincode::Error) -> Self {
+        SaveError::Encoding(e)
+    }
+}
+
+/// ワールドの全ての状態
+///
+/// 仕様は識別子ではなくラベルの一覧への添字で参照されるため、
+/// アセットの並びが変更されてもセーブデータは破損しない。
+#[derive(serde::Serialize, serde::Deserialize)]
+pub struct SaveData {
+    pub seed: u64,
+    /// ワールド全体の乱数生成器の状態
+    pub rng: u64,
+    pub grid_flags: Vec<[i32; 2]>,
+    /// 時計の経過時間 (秒)
+    pub time: f64,
+    /// チャンクを最後に更新した時計の経過時間
+    pub chunk_times: Vec<([i32; 2], f64)>,
+    pub base_spec_labels: Vec<String>,
+    pub block_spec_labels: Vec<String>,
+    pub entity_spec_labels: Vec<String>,
+    pub item_spec_labels: Vec<String>,
+    pub behaviour_spec_labels: Vec<String>,
+    /// 振る舞いの仕様毎の状態のラベルの一覧
+    pub behaviour_state_labels: Vec<Vec<String>>,
+    pub projectile_spec_labels: Vec<String>,
+    pub base_chunks: Vec<BaseChunkData>,
+    pub blocks: Vec<BlockData>,
+    pub entities: Vec<EntityData>,
+    pub player: Option<PlayerData>,
+    pub camera: CameraData,
+}
+
+/// チャンク単位のベース
+#[derive(serde::Serialize, serde::Deserialize)]
+pub struct BaseChunkData {
+    pub id: usize,
+    pub grid_point: [i32; 2],
+    pub bases: Vec<BaseData>,
+}
+
+#[derive(serde::Serialize, serde::Deserialize)]
+pub struct BaseData {
+    pub spec_label_id: usize,
+    pub local: [i32; 2],
+}
+
+#[derive(serde::Serialize, serde::Deserialize)]
+pub struct BlockData {
+    pub id: usize,
+    pub spec_label_id: usize,
+    pub position: [i32; 2],
+    pub z_random: u8,
+    pub state: block::BlockState,
+    /// 現在の仕様になってからの経過時間 (遷移するブロックのみ)
+    pub age: Option<f32>,
+}
+
+#[derive(serde::Serialize, serde::Deserialize)]
+pub struct EntityData {
+    pub id: usize,
+    pub spec_label_id: usize,
+    pub position: [f32; 2],
+    pub player_controlled: bool,
+    pub velocity: Option<[f32; 2]>,
+    pub damping: Option<f32>,
+    pub item_drop: Option<ItemDropData>,
+    pub facing: Option<[f32; 2]>,
+    pub knockback: Option<[f32; 2]>,
+    pub health: Option<HealthData>,
+    pub behaviour: Option<BehaviourData>,
+    pub projectile: Option<ProjectileData>,
+    pub path_follow: Option<PathFollowData>,
+}
+
+#[derive(serde::Serialize, serde::Deserialize)]
+pub struct HealthData {
+    pub current: f32,
+    pub max: f32,
+    pub invulnerable: f32,
+    pub flash: f32,
+}
+
+/// 振る舞いの状態
+///
+/// 状態が現在の仕様に存在しない場合は、行動の進行を含めて初期状態から再開する。
+#[derive(serde::Serialize, serde::Deserialize)]
+pub struct BehaviourData {
+    pub spec_label_id: usize,
+    /// 仕様毎の状態のラベルの一覧への添字
+    pub state_label_id: usize,
+    pub elapsed: f32,
+    pub started: bool,
+    pub done: bool,
+    pub target_block: Option<usize>,
+    pub timer: f32,
+    pub origin: [f32; 2],
+    pub patrol_index: usize,
+}
+
+/// 投射物の状態
+#[derive(serde::Serialize, serde::Deserialize)]
+pub struct ProjectileData {
+    pub spec_label_id: usize,
+    pub owner: Option<usize>,
+    pub velocity: [f32; 2],
+    pub remaining: f32,
+    pub stuck: bool,
+    pub attached: Option<(usize, [f32; 2])>,
+}
+
+/// 経路に沿った移動の状態
+#[derive(serde::Serialize, serde::Deserialize)]
+pub struct PathFollowData {
+    pub waypoints: Vec<[f32; 2]>,
+    pub speed: f32,
+}
+
+#[derive(serde::Serialize, serde::Deserialize)]
+pub struct ItemDropData {
+    pub stack: ItemStackData,
+    pub remaining: f32,
+}
+
+#[derive(serde::Serialize, serde::Deserialize)]
+pub struct PlayerData {
+    pub slots: Vec<Option<ItemStackData>>,
+    pub selected_slot: usize,
+}
+
+#[derive(serde::Serialize, serde::Deserialize)]
+pub struct ItemStackData {
+    pub spec_label_id: usize,
+    pub count: u32,
+}
+
+#[derive(serde::Serialize, serde::Deserialize)]
+pub struct CameraData {
+    pub position: [f32; 2],
+    pub zoom: f32,
+}
+
+/// セーブデータを書き込む。
+pub fn write<W: Write>(mut writer: W, save_data: &SaveData) -> Result<(), SaveError> {
+    writer.write_all(&MAGIC)?;
+    writer.write_all(&VERSION.to_le_bytes())?;
+    bincode::serialize_into(&mut writer, save_data)?;
+    writer.flush()?;
+    Ok(())
+}
+
+/// セーブデータを読み込む。
+pub fn read<R: Read>(mut reader: R) -> Result<SaveData, SaveError> {
+    let mut magic = [0; 4];
+    reader.read_exact(&mut magic)?;
+    if magic != MAGIC {
+        return Err(SaveError::Magic);
+    }
+
+    let mut version = [0; 4];
+    reader.read_exact(&mut version)?;
+    let version = u32::from_le_bytes(version);
+    if version != VERSION {
+        return Err(SaveError::Version(version));
+    }
+
+    let save_data = bincode::deserialize_from(&mut reader)?;
+    Ok(save_data)
+}
+
+/// セーブデータ上のラベルの一覧と現在のアセットにおける仕様の対応
+pub struct LabelTable<'a> {
+    labels: &'a [String],
+    spec_ids: Vec<Option<usize>>,
+}
+
+impl<'a> LabelTable<'a> {
+    /// ラベルの一覧と現在のアセットにおける仕様のラベルから対応を作成する。
+    pub fn new<'b>(
+        labels: &'a [String],
+        spec_labels: impl Iterator<Item = &'b str> + Clone,
+    ) -> Self {
+        let spec_ids = labels
+            .iter()
+            .map(|label| {
+                spec_labels
+                    .clone()
+                    .position(|spec_label| spec_label == label)
+            })
+            .collect();
+        Self { labels, spec_ids }
+    }
+
+    /// ラベルの一覧への添字から現在のアセットにおける仕様の識別子を返す。
+    pub fn spec_id(&self, spec_label_id: usize) -> Result<usize, SaveError> {
+        match self.spec_ids.get(spec_label_id) {
+            Some(Some(spec_id)) => Ok(*spec_id),
+            Some(None) => Err(SaveError::Label(self.labels[spec_label_id].clone())),
+            None => Err(SaveError::Label(format!("#{}", spec_label_id))),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use glam::*;
+
+    use super::*;
+    use crate::aabb::*;
+    use crate::{
+        assets,
+        game_loop::{base, entity, GameLoop},
+    };
+
+    fn game_loop(seed: u64) -> GameLoop {
+        let assets = assets::Assets::new("assets/assets.json");
+        GameLoop::new(std::rc::Rc::new(assets), seed)
+    }
+
+    fn temp_path(name: &str) -> std::path::PathBuf {
+        let file_name = format!("wgpu-tilemap-game-{}-{}.bin", name, std::process::id());
+        std::env::temp_dir().join(file_name)
+    }
+
+    /// 指定した範囲の全てのベース・ブロック・エンティティを返す。
+    fn query(game_loop: &GameLoop) -> (Vec<String>, Vec<String>, Vec<String>) {
+        let rect = iaabb2(IVec2::splat(-128), IVec2::splat(128));
+        let mut bases = game_loop
+            .base_storage
+            .get_internal_by_rect(rect)
+            .map(|(id, base)| format!("{} {} {}", id, base.spec_id, base.position))
+            .collect::<Vec<_>>();
+        bases.sort();
+        let mut blocks = game_loop
+            .block_storage
+            .get_internal_by_rect(rect)
+            .map(|(id, block)| format!("{} {} {}", id, block.spec_id, block.position))
+            .collect::<Vec<_>>();
+        blocks.sort();
+        blocks.dedup();
+        let mut entities = game_loop
+            .entity_storage
+            .get_internal_by_rect(rect.as_aabb2())
+            .map(|(id, entity)| format!("{} {} {}", id, entity.spec_id, entity.position))
+            .collect::<Vec<_>>();
+        entities.sort();
+        entities.dedup();
+        (bases, blocks, entities)
+    }
+
+    #[test]
+    fn save_and_load_round_trip() {
+        let mut source = game_loop(7);
+        (0..30).for_each(|_| {
+            source.update(&Default::default(), &GameLoop::TICK);
+            source.discard_changes();
+        });
+        let sheep = source
+            .assets
+            .entity_specs
+            .iter()
+            .position(|spec| spec.label == "sheep")
+            .unwrap();
+        source
+            .entity_storage
+            .spawn(entity::Entity::new(sheep, vec2(2.0, 2.0)));
+
+        let path = temp_path("round-trip");
+        source.save(&path).unwrap();
+        let mut target = game_loop(8);
+        let result = target.load(&path);
+        std::fs::remove_file(&path).unwrap();
+        result.unwrap();
+
+        let (bases, blocks, entities) = query(&source);
+        assert!(!bases.is_empty() && !entities.is_empty());
+        assert_eq!(query(&target), (bases, blocks, entities));
+        assert_eq!(target.state_hash(), source.state_hash());
+    }
+
+    #[test]
+    fn load_rejects_corrupt_data() {
+        let mut source = game_loop(7);
+        source.update(&Default::default(), &GameLoop::TICK);
+        let path = temp_path("corrupt");
+        source.save(&path).unwrap();
+        let read_file = || read(std::fs::File::open(&path).unwrap()).unwrap();
+        let write_file = |save_data: &SaveData| {
+            write(std::fs::File::create(&path).unwrap(), save_data).unwrap();
+        };
+
+        let mut target = game_loop(8);
+        target.update(&Default::default(), &GameLoop::TICK);
+        let hash = target.state_hash();
+
+        // NOTE: チャンクの外を指すベースの位置
+        let mut save_data = read_file();
+        save_data.base_chunks[0].bases[0].local = [base::CHUNK_SIZE, 0];
+        write_file(&save_data);
+        assert!(matches!(target.load(&path), Err(SaveError::Corrupt(_))));
+
+        // NOTE: 重複したチャンクの識別子
+        let mut save_data = read_file();
+        let chunk_id = save_data.base_chunks[0].id;
+        save_data.base_chunks[1].id = chunk_id;
+        write_file(&save_data);
+        assert!(matches!(target.load(&path), Err(SaveError::Corrupt(_))));
+
+        // NOTE: 範囲外のチャンクの識別子
+        let mut save_data = read_file();
+        save_data.base_chunks[0].id = usize::MAX;
+        write_file(&save_data);
+        assert!(matches!(target.load(&path), Err(SaveError::Corrupt(_))));
+
+        std::fs::remove_file(&path).unwrap();
+        assert_eq!(target.state_hash(), hash);
+    }
+}
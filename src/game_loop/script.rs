@@ -0,0 +1,907 @@
+//! スクリプトの機能に関するモジュール
+
+use std::{
+    cell::{Cell, Ref, RefCell, RefMut},
+    collections::BTreeMap,
+    path::{Path, PathBuf},
+    rc::Rc,
+};
+
+use ahash::HashSet;
+use glam::*;
+use rhai::{Array, CallFnOptions, Dynamic, Engine, EvalAltResult, FuncArgs, Map, Scope, AST};
+
+use crate::aabb::*;
+use crate::{
+    assets,
+    game_loop::{
+        block, component, entity, input, journal, random,
+        system::{Phase, System, WorldContext},
+    },
+};
+
+/// スクリプトの読み込み・実行で発生したエラー
+#[derive(Clone, PartialEq, Eq, Hash, Debug)]
+pub struct ScriptError {
+    /// エラーが発生したスクリプトのパス (スクリプトに依らない場合は`None`)
+    pub path: Option<PathBuf>,
+    /// エラーが発生した行 (不明な場合は`None`)
+    pub line: Option<usize>,
+    pub message: String,
+}
+
+impl ScriptError {
+    /// 実行時のエラーから作成する。(関数の呼び出しの内側で発生した位置を使用する)
+    fn from_eval(path: &Path, error: Box<EvalAltResult>) -> Self {
+        let mut error = error;
+        while let EvalAltResult::ErrorInFunctionCall(_, _, inner, _) = *error {
+            error = inner;
+        }
+        let line = error.take_position().line();
+        Self {
+            path: Some(path.to_path_buf()),
+            line,
+            message: error.to_string(),
+        }
+    }
+}
+
+impl std::fmt::Display for ScriptError {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match (&self.path, self.line) {
+            (Some(path), Some(line)) => write!(f, "{}:{}: {}", path.display(), line, self.message),
+            (Some(path), None) => write!(f, "{}: {}", path.display(), self.message),
+            (None, _) => write!(f, "{}", self.message),
+        }
+    }
+}
+
+impl std::error::Error for ScriptError {}
+
+/// スクリプトから操作できるワールドの状態
+///
+/// 関数を呼び出す間だけゲームループのストレージと入れ替えられる。
+struct World {
+    /// ゲームループのストレージと入れ替えられているか
+    attached: bool,
+    block_storage: block::BlockStorage,
+    entity_storage: entity::EntityStorage,
+    player_entity_id: Option<usize>,
+    time: f64,
+    time_of_day: f32,
+    day: u64,
+    /// プレイヤーへ渡すアイテム (呼び出しの後にインベントリへ追加する)
+    gives: Vec<(usize, u32)>,
+}
+
+/// スクリプトへ通知されるイベント
+struct Event {
+    name: String,
+    data: Dynamic,
+}
+
+/// 読み込まれたスクリプト
+struct Script {
+    path: PathBuf,
+    ast: AST,
+    functions: HashSet<String>,
+}
+
+type ApiResult<T> = Result<T, Box<EvalAltResult>>;
+
+/// スクリプトの機能
+///
+/// スクリプトは仕様から関数名で参照され、ブロックへの働きかけ・振る舞い・生成・コマンドを定義できる。
+/// `on_tick(dt)`と`on_event(name, data)`を定義したスクリプトは毎ティック呼び出される。
+/// エラーはゲームを止めずに記録され、同じエラーは一度だけ報告される。
+pub struct ScriptSystem {
+    assets: std::rc::Rc<assets::Assets>,
+    engine: Engine,
+    scripts: Vec<Script>,
+    world: Rc<RefCell<World>>,
+    events: Rc<RefCell<Vec<Event>>>,
+    commands: Rc<RefCell<BTreeMap<String, String>>>,
+    /// `on_event`を定義したスクリプトが存在するか (監視者と共有する)
+    on_event: Rc<Cell<bool>>,
+    errors: Vec<ScriptError>,
+    reported: HashSet<ScriptError>,
+}
+
+impl ScriptSystem {
+    /// 1回の呼び出しで実行できる演算の最大数 (無限ループの防止)
+    const MAX_OPERATIONS: u64 = 1_000_000;
+
+    /// 関数の呼び出しの深さの最大値
+    const MAX_CALL_LEVELS: usize = 32;
+
+    /// 範囲を指定して取得できる矩形の面積の最大値
+    const MAX_RECT_AREA: f32 = 64.0 * 64.0;
+
+    /// 新しいスクリプトシステムを作成し、仕様のスクリプトを読み込む。
+    pub fn new(assets: std::rc::Rc<assets::Assets>) -> Self {
+        let world = Rc::new(RefCell::new(World {
+            attached: false,
+            block_storage: block::BlockStorage::new(assets.clone()),
+            entity_storage: entity::EntityStorage::new(assets.clone()),
+            player_entity_id: None,
+            time: 0.0,
+            time_of_day: 0.0,
+            day: 0,
+            gives: vec![],
+        }));
+        let events = Rc::new(RefCell::new(vec![]));
+        let commands = Rc::new(RefCell::new(BTreeMap::new()));
+
+        let mut engine = Engine::new();
+        engine.set_max_operations(Self::MAX_OPERATIONS);
+        engine.set_max_call_levels(Self::MAX_CALL_LEVELS);
+        // NOTE: スクリプトから他のファイルを読み込ませない。
+        engine.set_module_resolver(rhai::module_resolvers::DummyModuleResolver::new());
+        register_api(&mut engine, &assets, &world, &events, &commands);
+
+        let mut script_sys = Self {
+            assets,
+            engine,
+            scripts: vec![],
+            world,
+            events,
+            commands,
+            on_event: Default::default(),
+            errors: vec![],
+            reported: Default::default(),
+        };
+        script_sys.reload();
+        script_sys
+    }
+
+    /// 仕様のスクリプトを読み込み直す。
+    ///
+    /// 登録されたコマンド・通知前のイベント・報告済みのエラーは破棄される。
+    pub fn reload(&mut self) {
+        self.scripts.clear();
+        self.commands.borrow_mut().clear();
+        self.events.borrow_mut().clear();
+        // NOTE: 読み込み直したスクリプトで再発したエラーも報告する。
+        self.reported.clear();
+
+        let assets = self.assets.clone();
+        assets.script_paths.iter().for_each(|path| self.load(path));
+        self.validate();
+        self.on_event.set(self.defines("on_event"));
+    }
+
+    /// ブロックの追加・削除をイベントとして通知する監視者を返す。
+    pub fn block_observer(&self) -> impl FnMut(&journal::Change<block::Block>) + 'static {
+        let assets = self.assets.clone();
+        let events = self.events.clone();
+        let on_event = self.on_event.clone();
+        move |change| {
+            let name = match change {
+                journal::Change::Insert { .. } => "block_inserted",
+                journal::Change::Remove { .. } => "block_removed",
+                journal::Change::Modify { .. } => return,
+            };
+            if let (true, Some(block)) = (on_event.get(), change.after().or(change.before())) {
+                let data = block_map(&assets, change.id(), block).into();
+                let name = name.to_string();
+                events.borrow_mut().push(Event { name, data });
+            }
+        }
+    }
+
+    /// エンティティの追加・削除をイベントとして通知する監視者を返す。
+    pub fn entity_observer(&self) -> impl FnMut(&journal::Change<entity::Entity>) + 'static {
+        let assets = self.assets.clone();
+        let events = self.events.clone();
+        let on_event = self.on_event.clone();
+        move |change| {
+            let name = match change {
+                journal::Change::Insert { .. } => "entity_inserted",
+                journal::Change::Remove { .. } => "entity_removed",
+                journal::Change::Modify { .. } => return,
+            };
+            if let (true, Some(entity)) = (on_event.get(), change.after().or(change.before())) {
+                let mut data = Map::new();
+                data.insert("id".into(), (change.id() as rhai::INT).into());
+                data.insert("label".into(), label(&assets, entity).into());
+                data.insert("x".into(), (entity.position.x as rhai::FLOAT).into());
+                data.insert("y".into(), (entity.position.y as rhai::FLOAT).into());
+                let name = name.to_string();
+                events.borrow_mut().push(Event {
+                    name,
+                    data: data.into(),
+                });
+            }
+        }
+    }
+
+    /// 記録されたエラーを取り出す。
+    #[inline]
+    pub fn drain_errors(&mut self) -> std::vec::Drain<'_, ScriptError> {
+        self.errors.drain(..)
+    }
+
+    /// スクリプトが登録したコマンドの名前を返す。
+    pub fn commands(&self) -> Vec<String> {
+        self.commands.borrow().keys().cloned().collect()
+    }
+
+    /// スクリプトによる生成・振る舞い・毎ティックの処理・イベントの通知を実行する。
+    ///
+    /// 生成の調整は前のティックで生成された範囲に対して実行される。
+    fn run(&mut self, context: &mut WorldContext) {
+        let delta_secs = context.tick.as_secs_f64();
+        let generation_scripts = context.generation_scripts.drain(..).collect::<Vec<_>>();
+
+        self.attach(context);
+
+        // NOTE: 生成したグリッドの調整
+        generation_scripts
+            .into_iter()
+            .for_each(|generation_script| {
+                let generation_spec =
+                    &self.assets.generation_specs[generation_script.generation_spec_id];
+                let assets::GenerationSpec::Script { function, .. } = generation_spec else {
+                    return;
+                };
+                let rect = generation_script.rect;
+                let args = (
+                    rect.min.x as rhai::INT,
+                    rect.min.y as rhai::INT,
+                    rect.max.x as rhai::INT,
+                    rect.max.y as rhai::INT,
+                    generation_script.seed,
+                );
+                self.call(&function.clone(), args);
+            });
+
+        // NOTE: スクリプトの行動を実行中のエンティティ
+        let behaviours = {
+            let world = self.world.borrow();
+            let components = world.entity_storage.components();
+            components
+                .iter_with::<(component::Behaviour,)>()
+                .filter_map(|entity_id| {
+                    let behaviour = components.get::<component::Behaviour>(entity_id).unwrap();
+                    let behaviour_spec = &self.assets.behaviour_specs[behaviour.behaviour_spec_id];
+                    match &behaviour_spec.states[behaviour.state_id].action {
+                        assets::BehaviourActionSpec::Script { function } => {
+                            Some((entity_id, function.clone(), behaviour.elapsed))
+                        }
+                        _ => None,
+                    }
+                })
+                .collect::<Vec<_>>()
+        };
+        behaviours
+            .into_iter()
+            .for_each(|(entity_id, function, elapsed)| {
+                let world = self.world.borrow();
+                let Some(mut entity) = entity_map(&self.assets, &world.entity_storage, entity_id)
+                else {
+                    return;
+                };
+                drop(world);
+                // NOTE: 状態に入ってからの経過時間を渡す。
+                entity.insert("elapsed".into(), (elapsed as rhai::FLOAT).into());
+
+                let result = self.call(&function, (entity, delta_secs));
+                if result.is_some_and(|result| result.as_bool() == Ok(true)) {
+                    let mut world = self.world.borrow_mut();
+                    let components = world.entity_storage.components_mut();
+                    if let Some(behaviour) = components.get_mut::<component::Behaviour>(entity_id) {
+                        behaviour.done = true;
+                    }
+                }
+            });
+
+        // NOTE: 毎ティックの処理
+        self.call_all("on_tick", (delta_secs,));
+
+        // NOTE: 処理中に発生したイベントは次のティックで通知する。
+        let events = std::mem::take(&mut *self.events.borrow_mut());
+        events.into_iter().for_each(|event| {
+            self.call_all("on_event", (event.name, event.data));
+        });
+
+        self.detach(context);
+    }
+
+    /// ブロックへの働きかけを実行し、スクリプトが呼び出されたか真偽値を返す。
+    fn interact(&mut self, context: &mut WorldContext, block_id: usize) -> bool {
+        let Some(block) = context.block_storage.get(block_id) else {
+            return false;
+        };
+        let Some(function) = self.assets.block_specs[block.spec_id].interaction.clone() else {
+            return false;
+        };
+        let block = block_map(&self.assets, block_id, block);
+
+        self.attach(context);
+        self.call(&function, (block,));
+        self.detach(context);
+        true
+    }
+
+    /// 登録されたコマンドを実行し、結果を文字列で返す。
+    ///
+    /// コマンドが登録されていない場合は`None`を返す。
+    pub fn command(
+        &mut self,
+        context: &mut WorldContext,
+        name: &str,
+        args: Vec<String>,
+    ) -> Option<Result<String, ScriptError>> {
+        let function = self.commands.borrow().get(name).cloned()?;
+        let script_id = self
+            .scripts
+            .iter()
+            .rposition(|script| script.functions.contains(&function))?;
+        let args = args.into_iter().map(Dynamic::from).collect::<Array>();
+
+        self.attach(context);
+        let result = self.call_script(script_id, &function, (args,));
+        self.detach(context);
+        Some(result.map(|value| {
+            if value.is_unit() {
+                String::new()
+            } else {
+                value.to_string()
+            }
+        }))
+    }
+
+    /// スクリプトを読み込み、最上位の文を実行する。
+    fn load(&mut self, path: &Path) {
+        let source = match std::fs::read_to_string(path) {
+            Ok(source) => source,
+            Err(e) => {
+                self.report(ScriptError {
+                    path: Some(path.to_path_buf()),
+                    line: None,
+                    message: e.to_string(),
+                });
+                return;
+            }
+        };
+
+        let ast = match self.engine.compile(source) {
+            Ok(ast) => ast,
+            Err(e) => {
+                self.report(ScriptError {
+                    path: Some(path.to_path_buf()),
+                    line: e.1.line(),
+                    message: e.0.to_string(),
+                });
+                return;
+            }
+        };
+
+        // NOTE: 最上位の文は読み込み時に一度だけ実行する。(コマンドの登録など)
+        if let Err(e) = self.engine.run_ast(&ast) {
+            self.report(ScriptError::from_eval(path, e));
+            return;
+        }
+
+        let functions = ast
+            .iter_functions()
+            .map(|function| function.name.to_string())
+            .collect();
+        self.scripts.push(Script {
+            path: path.to_path_buf(),
+            ast,
+            functions,
+        });
+    }
+
+    /// 仕様とコマンドが参照する関数が定義されているか検査する。
+    fn validate(&mut self) {
+        let block_functions = self
+            .assets
+            .block_specs
+            .iter()
+            .filter_map(|spec| spec.interaction.clone());
+        let behaviour_functions = self
+            .assets
+            .behaviour_specs
+            .iter()
+            .flat_map(|spec| &spec.states)
+            .filter_map(|state| match &state.action {
+                assets::BehaviourActionSpec::Script { function } => Some(function.clone()),
+                _ => None,
+            });
+        let generation_functions =
+            self.assets
+                .generation_specs
+                .iter()
+                .filter_map(|spec| match spec {
+                    assets::GenerationSpec::Script { function, .. } => Some(function.clone()),
+                    _ => None,
+                });
+        let command_functions = self.commands.borrow().values().cloned().collect::<Vec<_>>();
+
+        block_functions
+            .chain(behaviour_functions)
+            .chain(generation_functions)
+            .chain(command_functions)
+            .filter(|function| !self.defines(function))
+            .collect::<Vec<_>>()
+            .into_iter()
+            .for_each(|function| {
+                self.report(ScriptError {
+                    path: None,
+                    line: None,
+                    message: format!("script function not found: {}", function),
+                });
+            });
+    }
+
+    /// 関数を定義したスクリプトが存在するか真偽値を返す。
+    #[inline]
+    fn defines(&self, function: &str) -> bool {
+        self.scripts
+            .iter()
+            .any(|script| script.functions.contains(function))
+    }
+
+    /// 関数を最後に定義したスクリプトで呼び出す。
+    ///
+    /// 関数が存在しない、またはエラーが発生した場合は`None`を返す。
+    fn call(&mut self, function: &str, args: impl FuncArgs) -> Option<Dynamic> {
+        let script_id = self
+            .scripts
+            .iter()
+            .rposition(|script| script.functions.contains(function))?;
+        match self.call_script(script_id, function, args) {
+            Ok(value) => Some(value),
+            Err(e) => {
+                self.report(e);
+                None
+            }
+        }
+    }
+
+    /// 関数を定義した全てのスクリプトで呼び出す。
+    fn call_all(&mut self, function: &str, args: impl FuncArgs + Clone) {
+        (0..self.scripts.len())
+            .filter(|&script_id| self.scripts[script_id].functions.contains(function))
+            .collect::<Vec<_>>()
+            .into_iter()
+            .for_each(|script_id| {
+                if let Err(e) = self.call_script(script_id, function, args.clone()) {
+                    self.report(e);
+                }
+            });
+    }
+
+    fn call_script(
+        &self,
+        script_id: usize,
+        function: &str,
+        args: impl FuncArgs,
+    ) -> Result<Dynamic, ScriptError> {
+        let script = &self.scripts[script_id];
+        let options = CallFnOptions::new().eval_ast(false);
+        self.engine
+            .call_fn_with_options(options, &mut Scope::new(), &script.ast, function, args)
+            .map_err(|e| ScriptError::from_eval(&script.path, e))
+    }
+
+    /// エラーを記録する。(同じエラーは一度だけ記録する)
+    fn report(&mut self, error: ScriptError) {
+        if self.reported.insert(error.clone()) {
+            self.errors.push(error);
+        }
+    }
+
+    /// ゲームループのストレージをスクリプトから操作できるようにする。
+    fn attach(&mut self, context: &mut WorldContext) {
+        let mut world = self.world.borrow_mut();
+        std::mem::swap(&mut world.block_storage, context.block_storage);
+        std::mem::swap(&mut world.entity_storage, context.entity_storage);
+        world.player_entity_id = context
+            .player_state
+            .as_ref()
+            .map(|player_state| player_state.entity_id);
+        world.time = context.clock_sys.time();
+        world.time_of_day = context.clock_sys.time_of_day();
+        world.day = context.clock_sys.day();
+        world.attached = true;
+    }
+
+    /// ゲームループへストレージを戻し、プレイヤーへアイテムを渡す。
+    ///
+    /// インベントリに入りきらないアイテムは捨てられる。
+    fn detach(&mut self, context: &mut WorldContext) {
+        let mut world = self.world.borrow_mut();
+        std::mem::swap(&mut world.block_storage, context.block_storage);
+        std::mem::swap(&mut world.entity_storage, context.entity_storage);
+        world.attached = false;
+
+        let gives = world.gives.drain(..);
+        match context.player_state.as_mut() {
+            Some(player_state) => gives.for_each(|(item_spec_id, count)| {
+                player_state
+                    .inventory
+                    .insert(&self.assets, item_spec_id, count);
+            }),
+            None => gives.for_each(drop),
+        }
+    }
+}
+
+impl System for ScriptSystem {
+    #[inline]
+    fn phase(&self) -> Phase {
+        Phase::Simulation
+    }
+
+    /// ブロックへの働きかけとスクリプトの毎ティックの処理を実行する。
+    fn update(&mut self, context: &mut WorldContext) {
+        // NOTE: スクリプトを持つブロックへの働きかけ (ブロックが占める位置には設置できない)
+        let target_block = context
+            .player_state
+            .as_ref()
+            .and_then(|player_state| player_state.target_block);
+        if let (true, Some(block_id)) = (context.input.pressed(input::Action::Place), target_block)
+        {
+            self.interact(context, block_id);
+        }
+
+        self.run(context);
+    }
+}
+
+/// ブロックをスクリプトの値に変換する。
+fn block_map(assets: &assets::Assets, id: usize, block: &block::Block) -> Map {
+    let spec = &assets.block_specs[block.spec_id];
+    let mut map = Map::new();
+    map.insert("id".into(), (id as rhai::INT).into());
+    map.insert("label".into(), spec.label.clone().into());
+    map.insert("x".into(), (block.position.x as rhai::INT).into());
+    map.insert("y".into(), (block.position.y as rhai::INT).into());
+    map
+}
+
+/// エンティティをスクリプトの値に変換する。
+fn entity_map(
+    assets: &assets::Assets,
+    entity_storage: &entity::EntityStorage,
+    id: usize,
+) -> Option<Map> {
+    let entity = entity_storage.get(id)?;
+    let mut map = Map::new();
+    map.insert("id".into(), (id as rhai::INT).into());
+    map.insert("label".into(), label(assets, entity).into());
+    map.insert("x".into(), (entity.position.x as rhai::FLOAT).into());
+    map.insert("y".into(), (entity.position.y as rhai::FLOAT).into());
+    if let Some(health) = entity_storage.components().get::<component::Health>(id) {
+        map.insert("health".into(), (health.current as rhai::FLOAT).into());
+    }
+    Some(map)
+}
+
+#[inline]
+fn label(assets: &assets::Assets, entity: &entity::Entity) -> String {
+    assets.entity_specs[entity.spec_id].label.clone()
+}
+
+/// 整数・小数のどちらも受け付ける数値を変換する。
+fn number(value: &Dynamic) -> ApiResult<f32> {
+    match (value.as_float(), value.as_int()) {
+        (Ok(value), _) => Ok(value as f32),
+        (_, Ok(value)) => Ok(value as f32),
+        _ => Err(format!("expected a number, found {}", value.type_name()).into()),
+    }
+}
+
+/// 識別子を変換する。
+fn to_id(value: rhai::INT) -> ApiResult<usize> {
+    usize::try_from(value).map_err(|_| format!("invalid id: {}", value).into())
+}
+
+/// 範囲を指定する矩形の面積が上限以下か検査する。
+fn check_area(size: Vec2) -> ApiResult<()> {
+    let area = size.x.max(0.0) * size.y.max(0.0);
+    // NOTE: NaNも拒否する。
+    if area.is_nan() || area > ScriptSystem::MAX_RECT_AREA {
+        return Err(format!(
+            "rect is too large: {} (max {})",
+            area,
+            ScriptSystem::MAX_RECT_AREA
+        )
+        .into());
+    }
+    Ok(())
+}
+
+fn attached(world: &RefCell<World>) -> ApiResult<Ref<'_, World>> {
+    let world = world.borrow();
+    if !world.attached {
+        return Err("the world is not available here".into());
+    }
+    Ok(world)
+}
+
+fn attached_mut(world: &RefCell<World>) -> ApiResult<RefMut<'_, World>> {
+    let world = world.borrow_mut();
+    if !world.attached {
+        return Err("the world is not available here".into());
+    }
+    Ok(world)
+}
+
+/// スクリプトから呼び出せる関数を登録する。
+fn register_api(
+    engine: &mut Engine,
+    assets: &std::rc::Rc<assets::Assets>,
+    world: &Rc<RefCell<World>>,
+    events: &Rc<RefCell<Vec<Event>>>,
+    commands: &Rc<RefCell<BTreeMap<String, String>>>,
+) {
+    // NOTE: ブロック
+    let (a, w) = (assets.clone(), world.clone());
+    engine.register_fn("block", move |id: rhai::INT| -> ApiResult<Dynamic> {
+        let id = to_id(id)?;
+        let world = attached(&w)?;
+        let block = world.block_storage.get(id);
+        Ok(block.map_or(Dynamic::UNIT, |block| block_map(&a, id, block).into()))
+    });
+
+    let (a, w) = (assets.clone(), world.clone());
+    engine.register_fn(
+        "block_at",
+        move |x: rhai::INT, y: rhai::INT| -> ApiResult<Dynamic> {
+            let world = attached(&w)?;
+            let position = ivec2(x as i32, y as i32);
+            let mut blocks = world
+                .block_storage
+                .get_internal_by_rect(iaabb2(position, position + IVec2::ONE));
+            Ok(blocks
+                .next()
+                .map_or(Dynamic::UNIT, |(id, block)| block_map(&a, id, block).into()))
+        },
+    );
+
+    let (a, w) = (assets.clone(), world.clone());
+    engine.register_fn(
+        "blocks_in",
+        move |x0: rhai::INT, y0: rhai::INT, x1: rhai::INT, y1: rhai::INT| -> ApiResult<Array> {
+            let rect = iaabb2(ivec2(x0 as i32, y0 as i32), ivec2(x1 as i32, y1 as i32));
+            // NOTE: 桁あふれを避けるため小数で面積を求める。
+            check_area((rect.max - rect.min).as_vec2())?;
+            let world = attached(&w)?;
+            Ok(world
+                .block_storage
+                .get_internal_by_rect(rect)
+                .map(|(id, block)| block_map(&a, id, block).into())
+                .collect())
+        },
+    );
+
+    let (a, w) = (assets.clone(), world.clone());
+    engine.register_fn(
+        "insert_block",
+        move |label: &str, x: rhai::INT, y: rhai::INT| -> ApiResult<Dynamic> {
+            let spec = a
+                .block_specs
+                .iter()
+                .find(|spec| spec.label == label)
+                .ok_or_else(|| format!("unknown block spec label: {}", label))?;
+            let mut world = attached_mut(&w)?;
+            let state = block::BlockState::initial(spec);
+            let block = block::Block::new(spec.id, ivec2(x as i32, y as i32), 0, state);
+            let id = world.block_storage.insert(block);
+            Ok(id.map_or(Dynamic::UNIT, |id| (id as rhai::INT).into()))
+        },
+    );
+
+    let w = world.clone();
+    engine.register_fn("remove_block", move |id: rhai::INT| -> ApiResult<bool> {
+        let mut world = attached_mut(&w)?;
+        Ok(world.block_storage.remove(to_id(id)?).is_some())
+    });
+
+    // NOTE: エンティティ
+    let (a, w) = (assets.clone(), world.clone());
+    engine.register_fn("entity", move |id: rhai::INT| -> ApiResult<Dynamic> {
+        let id = to_id(id)?;
+        let world = attached(&w)?;
+        let entity = entity_map(&a, &world.entity_storage, id);
+        Ok(entity.map_or(Dynamic::UNIT, Into::into))
+    });
+
+    let (a, w) = (assets.clone(), world.clone());
+    engine.register_fn(
+        "entities_in",
+        move |x0: Dynamic, y0: Dynamic, x1: Dynamic, y1: Dynamic| -> ApiResult<Array> {
+            let rect = aabb2(
+                vec2(number(&x0)?, number(&y0)?),
+                vec2(number(&x1)?, number(&y1)?),
+            );
+            check_area(rect.max - rect.min)?;
+            let world = attached(&w)?;
+            Ok(world
+                .entity_storage
+                .get_internal_by_rect(rect)
+                .filter_map(|(id, _)| entity_map(&a, &world.entity_storage, id))
+                .map(Into::into)
+                .collect())
+        },
+    );
+
+    let (a, w) = (assets.clone(), world.clone());
+    engine.register_fn(
+        "insert_entity",
+        move |label: &str, x: Dynamic, y: Dynamic| -> ApiResult<Dynamic> {
+            let spec = a
+                .entity_specs
+                .iter()
+                .find(|spec| spec.label == label)
+                .ok_or_else(|| format!("unknown entity spec label: {}", label))?;
+            let position = vec2(number(&x)?, number(&y)?);
+            let mut world = attached_mut(&w)?;
+            let entity = entity::Entity::new(spec.id, position);
+            let Some(id) = world.entity_storage.spawn(entity) else {
+                return Ok(Dynamic::UNIT);
+            };
+            Ok((id as rhai::INT).into())
+        },
+    );
+
+    let w = world.clone();
+    engine.register_fn("remove_entity", move |id: rhai::INT| -> ApiResult<bool> {
+        let mut world = attached_mut(&w)?;
+        Ok(world.entity_storage.remove(to_id(id)?).is_some())
+    });
+
+    let w = world.clone();
+    engine.register_fn(
+        "move_entity",
+        move |id: rhai::INT, vx: Dynamic, vy: Dynamic| -> ApiResult<bool> {
+            let id = to_id(id)?;
+            let velocity = vec2(number(&vx)?, number(&vy)?);
+            let mut world = attached_mut(&w)?;
+            if world.entity_storage.get(id).is_none() {
+                return Ok(false);
+            }
+            let components = world.entity_storage.components_mut();
+            components.insert(id, component::Velocity(velocity));
+            Ok(true)
+        },
+    );
+
+    // NOTE: プレイヤー
+    let (a, w) = (assets.clone(), world.clone());
+    engine.register_fn("player", move || -> ApiResult<Dynamic> {
+        let world = attached(&w)?;
+        let entity = world
+            .player_entity_id
+            .and_then(|id| entity_map(&a, &world.entity_storage, id));
+        Ok(entity.map_or(Dynamic::UNIT, Into::into))
+    });
+
+    let (a, w) = (assets.clone(), world.clone());
+    engine.register_fn(
+        "give_item",
+        move |label: &str, count: rhai::INT| -> ApiResult<bool> {
+            let spec = a
+                .item_specs
+                .iter()
+                .find(|spec| spec.label == label)
+                .ok_or_else(|| format!("unknown item spec label: {}", label))?;
+            if count <= 0 {
+                return Ok(false);
+            }
+            let count = u32::try_from(count).map_err(|_| format!("too many items: {}", count))?;
+            let mut world = attached_mut(&w)?;
+            if world.player_entity_id.is_none() {
+                return Ok(false);
+            }
+            world.gives.push((spec.id, count));
+            Ok(true)
+        },
+    );
+
+    // NOTE: 時計
+    let w = world.clone();
+    engine.register_fn("time", move || -> ApiResult<rhai::FLOAT> {
+        Ok(attached(&w)?.time)
+    });
+
+    let w = world.clone();
+    engine.register_fn("time_of_day", move || -> ApiResult<rhai::FLOAT> {
+        Ok(attached(&w)?.time_of_day as rhai::FLOAT)
+    });
+
+    let w = world.clone();
+    engine.register_fn("day", move || -> ApiResult<rhai::INT> {
+        Ok(attached(&w)?.day as rhai::INT)
+    });
+
+    // NOTE: イベント
+    let e = events.clone();
+    engine.register_fn("emit", move |name: &str, data: Dynamic| {
+        let name = name.to_string();
+        e.borrow_mut().push(Event { name, data });
+    });
+
+    let e = events.clone();
+    engine.register_fn("emit", move |name: &str| {
+        let name = name.to_string();
+        let data = Dynamic::UNIT;
+        e.borrow_mut().push(Event { name, data });
+    });
+
+    // NOTE: コマンド
+    let c = commands.clone();
+    engine.register_fn("register_command", move |name: &str, function: &str| {
+        c.borrow_mut()
+            .insert(name.to_string(), function.to_string());
+    });
+
+    // NOTE: シード値と添字に固有の乱数 (`0.0`から`1.0`)
+    engine.register_fn("random", |seed: rhai::INT, index: rhai::INT| {
+        let state = random::mix(random::mix(seed as u64) ^ index as u64);
+        (state >> 11) as rhai::FLOAT / (1u64 << 53) as rhai::FLOAT
+    });
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    /// 一時ディレクトリにスクリプトを書き込み、そのスクリプトのみを読み込むスクリプトシステムを返す。
+    fn script_sys(name: &str, source: &str) -> (ScriptSystem, PathBuf) {
+        let path = std::env::temp_dir().join(format!("{}-{}.rhai", name, std::process::id()));
+        std::fs::write(&path, source).unwrap();
+
+        let mut assets = assets::Assets::new("assets/assets.json");
+        assets.script_paths = vec![path.clone()];
+        (ScriptSystem::new(std::rc::Rc::new(assets)), path)
+    }
+
+    /// スクリプトで発生したエラーを取り出す。(仕様が参照する関数が無いエラーは除く)
+    fn errors(script_sys: &mut ScriptSystem) -> Vec<ScriptError> {
+        script_sys
+            .drain_errors()
+            .filter(|error| error.path.is_some())
+            .collect()
+    }
+
+    #[test]
+    fn compile_error_reports_line() {
+        let (mut script_sys, path) =
+            script_sys("compile_error", "let a = 1;\nlet b = 2;\nlet = 3;\n");
+        let errors = errors(&mut script_sys);
+        std::fs::remove_file(&path).unwrap();
+
+        assert_eq!(errors.len(), 1);
+        assert_eq!(errors[0].path.as_deref(), Some(path.as_path()));
+        assert_eq!(errors[0].line, Some(3));
+        let prefix = format!("{}:3: ", path.display());
+        assert!(errors[0].to_string().starts_with(&prefix));
+    }
+
+    #[test]
+    fn runtime_error_reports_line_inside_function() {
+        let source = "fn helper() {\n    throw \"boom\";\n}\n\nfn fail() {\n    helper()\n}\n";
+        let (mut script_sys, path) = script_sys("runtime_error", source);
+        assert!(errors(&mut script_sys).is_empty());
+
+        // NOTE: 呼び出し元ではなく、エラーが発生した位置を報告する。
+        assert!(script_sys.call("fail", ()).is_none());
+        let errors_once = errors(&mut script_sys);
+        std::fs::remove_file(&path).unwrap();
+
+        assert_eq!(errors_once.len(), 1);
+        assert_eq!(errors_once[0].line, Some(2));
+        assert!(errors_once[0]
+            .to_string()
+            .starts_with(&format!("{}:2: ", path.display())));
+
+        // NOTE: 同じエラーは一度だけ報告される。
+        assert!(script_sys.call("fail", ()).is_none());
+        assert!(errors(&mut script_sys).is_empty());
+    }
+}
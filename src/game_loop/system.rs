@@ -0,0 +1,155 @@
+//! ゲームループで実行するシステムに関するモジュール
+
+use slab::Slab;
+
+use crate::{
+    assets,
+    game_loop::{
+        base, block, camera, clock, console, entity, generation, input, pathfinding, player,
+        random, weather,
+    },
+};
+
+/// システムが実行される段階
+///
+/// 各段階はティック毎に宣言順に実行され、同じ段階では組み込みのシステムの後に
+/// 登録されたシステムが登録順に実行される。
+#[derive(Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Hash, Debug)]
+pub enum Phase {
+    /// プレイヤーの操作
+    Input,
+    /// エンティティの振る舞い・移動・戦闘・アイテムの処理
+    Simulation,
+    /// カメラの追従・地形の生成・ブロックの遷移
+    PostSimulation,
+    /// 描写対象の抽出に備えたティックの最後の処理
+    ///
+    /// 抽出はフレーム毎に実行されるため、ワールドの変更はここで済ませる。
+    PreExtract,
+}
+
+impl Phase {
+    /// 実行順に並べた全ての段階
+    pub const ALL: [Phase; 4] = [
+        Phase::Input,
+        Phase::Simulation,
+        Phase::PostSimulation,
+        Phase::PreExtract,
+    ];
+}
+
+/// システムへ渡されるワールドの状態
+pub struct WorldContext<'a> {
+    pub assets: &'a assets::Assets,
+    /// 1回の実行で進める時間 (コマンドの実行時は`0`)
+    pub tick: std::time::Duration,
+    pub input: &'a input::InputState,
+    /// シード値から始まるワールド全体の乱数生成器
+    pub rng: &'a mut random::SplitMix64,
+    pub clock_sys: &'a mut clock::ClockSystem,
+    pub weather_state: &'a mut weather::WeatherState,
+    pub pathfinding_sys: &'a mut pathfinding::PathfindingSystem,
+    pub camera_state: &'a mut camera::CameraState,
+    pub base_storage: &'a mut base::BaseStorage,
+    pub block_storage: &'a mut block::BlockStorage,
+    pub entity_storage: &'a mut entity::EntityStorage,
+    /// プレイヤーの状態 (プレイヤーが存在しない場合は`None`)
+    pub player_state: &'a mut Option<player::PlayerState>,
+    /// 生成の後に実行するスクリプトによる生成の要求
+    pub generation_scripts: &'a mut Vec<generation::GenerationScript>,
+}
+
+/// ゲームループへ登録するシステム
+pub trait System {
+    /// システムが実行される段階を返す。
+    fn phase(&self) -> Phase;
+
+    /// 1回分の処理を実行する。
+    fn update(&mut self, context: &mut WorldContext);
+
+    /// システムが提供するコンソールのコマンドの仕様を返す。
+    fn commands(&self) -> Vec<console::CommandSpec> {
+        vec![]
+    }
+
+    /// コンソールのコマンドを実行し、結果を文字列で返す。
+    ///
+    /// 引数は仕様に従って解析された状態で渡される。
+    fn command(
+        &mut self,
+        name: &str,
+        args: &console::Args,
+        context: &mut WorldContext,
+    ) -> Result<String, console::CommandError> {
+        let _ = (args, context);
+        Err(console::CommandError::Unknown(name.to_string()))
+    }
+}
+
+/// 登録されたシステムを段階毎に実行する機能
+#[derive(Default)]
+pub struct Scheduler {
+    systems: Slab<Box<dyn System>>,
+    /// 実行順に並べたシステムの識別子
+    order: Vec<usize>,
+}
+
+impl Scheduler {
+    /// システムを登録し、識別子を返す。
+    pub fn insert(&mut self, system: Box<dyn System>) -> usize {
+        let phase = system.phase();
+        let id = self.systems.insert(system);
+
+        // NOTE: 同じ段階のシステムの末尾へ挿入する。
+        let index = self
+            .order
+            .partition_point(|other_id| self.systems[*other_id].phase() <= phase);
+        self.order.insert(index, id);
+        id
+    }
+
+    /// システムの登録を解除する。
+    pub fn remove(&mut self, id: usize) -> Option<Box<dyn System>> {
+        let system = self.systems.try_remove(id)?;
+        self.order.retain(|other_id| *other_id != id);
+        Some(system)
+    }
+
+    /// 登録されたシステムが提供するコマンドの仕様を返す。
+    pub fn commands(&self) -> Vec<console::CommandSpec> {
+        self.order
+            .iter()
+            .flat_map(|id| self.systems[*id].commands())
+            .collect()
+    }
+
+    /// コマンドを提供するシステムで引数を解析し、実行する。
+    ///
+    /// コマンドを提供するシステムが無い場合は`None`を返す。
+    pub fn command(
+        &mut self,
+        name: &str,
+        tokens: &[&str],
+        context: &mut WorldContext,
+    ) -> Option<Result<String, console::CommandError>> {
+        let (id, command_spec) = self.order.iter().find_map(|id| {
+            let command_specs = self.systems[*id].commands();
+            let command_spec = command_specs.into_iter().find(|spec| spec.name == name)?;
+            Some((*id, command_spec))
+        })?;
+        let result = command_spec
+            .parse(context.assets, tokens)
+            .and_then(|args| self.systems[id].command(name, &args, context));
+        Some(result)
+    }
+
+    /// 指定した段階の全てのシステムを実行する。
+    pub fn run(&mut self, phase: Phase, context: &mut WorldContext) {
+        self.order.iter().for_each(|id| {
+            let system = &mut self.systems[*id];
+            if system.phase() == phase {
+                system.update(context);
+            }
+        });
+    }
+}
@@ -0,0 +1,263 @@
+//! ブロックの時間経過による遷移の機能に関するモジュール
+
+use std::{cell::RefCell, rc::Rc};
+
+use ahash::HashMap;
+use glam::*;
+
+use crate::aabb::*;
+use crate::{
+    assets,
+    game_loop::{
+        base, block, journal,
+        system::{Phase, System, WorldContext},
+    },
+};
+
+/// ブロックの時間経過による遷移の機能
+///
+/// 遷移は読み込まれているチャンク (カメラの周囲) のブロックのみを対象とする。
+/// 範囲外にある間の経過時間は、チャンクが再び読み込まれた時にまとめて反映する。
+/// 時間はワールドの時計に従う。
+pub struct TransitionSystem {
+    assets: Rc<assets::Assets>,
+    /// チャンクを最後に更新した時計の経過時間
+    chunks: HashMap<IVec2, f64>,
+    /// ブロックが現在の仕様になってからの経過時間 (秒)
+    ages: Rc<RefCell<HashMap<usize, f32>>>,
+}
+
+impl TransitionSystem {
+    /// 空間分割サイズ (ベースのチャンクと一致させる)
+    const CHUNK_SIZE: i32 = base::CHUNK_SIZE;
+
+    /// 範囲の外側に余剰に更新するチャンクの大きさ
+    const EXTEND_CHUNK: i32 = 1;
+
+    /// 新しい遷移システムを作成する。
+    #[inline]
+    pub fn new(assets: Rc<assets::Assets>) -> Self {
+        Self {
+            assets,
+            chunks: Default::default(),
+            ages: Default::default(),
+        }
+    }
+
+    /// ブロックの追加・変更を監視し、経過時間を初期化する監視者を返す。
+    ///
+    /// 返された監視者はブロックのジャーナルに登録する。
+    pub fn observer(&self) -> impl FnMut(&journal::Change<block::Block>) + 'static {
+        let assets = self.assets.clone();
+        let ages = self.ages.clone();
+        move |change| {
+            let mut ages = ages.borrow_mut();
+            match (change.before(), change.after()) {
+                (_, None) => {
+                    ages.remove(&change.id());
+                }
+                (Some(before), Some(after)) if before.spec_id == after.spec_id => {}
+                (_, Some(after)) => {
+                    if assets.block_specs[after.spec_id].transition.is_some() {
+                        ages.insert(change.id(), 0.0);
+                    } else {
+                        ages.remove(&change.id());
+                    }
+                }
+            }
+        }
+    }
+
+    /// チャンクを最後に更新した時計の経過時間を返す。
+    #[inline]
+    pub fn chunks(&self) -> &HashMap<IVec2, f64> {
+        &self.chunks
+    }
+
+    /// ブロックが現在の仕様になってからの経過時間を返す。
+    #[inline]
+    pub fn age(&self, block_id: usize) -> Option<f32> {
+        self.ages.borrow().get(&block_id).copied()
+    }
+
+    /// 経過時間を置き換える。
+    ///
+    /// ブロックを復元した後に呼び出す。
+    pub fn restore(
+        &mut self,
+        chunks: impl IntoIterator<Item = (IVec2, f64)>,
+        ages: impl IntoIterator<Item = (usize, f32)>,
+    ) {
+        self.chunks = chunks.into_iter().collect();
+        self.ages.borrow_mut().extend(ages);
+    }
+
+    /// ブロックの経過時間を更新し、遷移できる限り遷移させる。
+    ///
+    /// 遷移先の仕様で占める範囲が他のブロックで塞がれている場合は、空くまで遷移を待つ。
+    fn advance(&mut self, block_storage: &mut block::BlockStorage, block_id: usize, mut age: f32) {
+        while let Some(block) = block_storage.get(block_id) {
+            let spec = &self.assets.block_specs[block.spec_id];
+            let Some(transition) = &spec.transition else {
+                return;
+            };
+            if age < transition.duration {
+                break;
+            }
+
+            let next_spec = &self.assets.block_specs[transition.block_spec_id];
+            let next = block::Block::new(
+                next_spec.id,
+                block.position,
+                block.z_random,
+                block::BlockState::initial(next_spec),
+            );
+
+            // NOTE: 大きくなる場合は新しい仕様で占める範囲が空いているか確認する。
+            let grows = next_spec.internal_size.cmpgt(spec.internal_size).any();
+            let rect = iaabb2(next.position, next.position + next_spec.internal_size);
+            let blocked = grows
+                && block_storage
+                    .get_internal_by_rect(rect)
+                    .any(|(other_id, _)| other_id != block_id);
+            if blocked || block_storage.modify(block_id, next).is_none() {
+                age = transition.duration;
+                break;
+            }
+
+            age -= transition.duration;
+        }
+
+        if self.ages.borrow().contains_key(&block_id) {
+            self.ages.borrow_mut().insert(block_id, age);
+        }
+    }
+}
+
+impl System for TransitionSystem {
+    #[inline]
+    fn phase(&self) -> Phase {
+        Phase::PostSimulation
+    }
+
+    /// カメラの周囲のチャンクのブロックを前回の更新からの経過時間だけ遷移させる。
+    fn update(&mut self, context: &mut WorldContext) {
+        let time = context.clock_sys.time();
+        let rect = context.camera_state.clipping();
+        let block_storage = &mut *context.block_storage;
+
+        let chunk_rect = rect
+            .trunc_over()
+            .as_iaabb2()
+            .to_grid_space(Self::CHUNK_SIZE)
+            .extends(Self::EXTEND_CHUNK);
+
+        chunk_rect.into_iter_points().for_each(|chunk_point| {
+            // NOTE: 初めて読み込まれたチャンクは経過時間を持たない。
+            let last = self.chunks.insert(chunk_point, time);
+            let elapsed = last.map_or(0.0, |last| (time - last) as f32);
+
+            // NOTE: 複数のチャンクにまたがるブロックは左下のチャンクでのみ更新する。
+            let rect =
+                iaabb2(chunk_point, chunk_point + IVec2::ONE).to_base_space(Self::CHUNK_SIZE);
+            let mut block_ids = block_storage
+                .get_internal_by_rect(rect)
+                .filter(|(_, block)| rect.contains_point(block.position))
+                .filter(|(_, block)| self.assets.block_specs[block.spec_id].transition.is_some())
+                .map(|(block_id, _)| block_id)
+                .collect::<Vec<_>>();
+            block_ids.sort_unstable();
+
+            block_ids.into_iter().for_each(|block_id| {
+                let age = self.age(block_id).unwrap_or_default() + elapsed;
+                self.advance(block_storage, block_id, age);
+            });
+        });
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::game_loop::GameLoop;
+
+    fn block_spec_id(assets: &assets::Assets, label: &str) -> usize {
+        assets
+            .block_specs
+            .iter()
+            .position(|spec| spec.label == label)
+            .unwrap()
+    }
+
+    /// 生成されたブロックを取り除いたゲームループと、プレイヤーの近くのタイルを返す。
+    fn empty_game_loop(assets: assets::Assets) -> (GameLoop, IVec2) {
+        let mut game_loop = GameLoop::new(Rc::new(assets), 0);
+        game_loop.update(&Default::default(), &GameLoop::TICK);
+        game_loop.block_storage.restore(std::iter::empty());
+        game_loop.discard_changes();
+
+        let player_id = game_loop.player_state.as_ref().unwrap().entity_id;
+        let position = game_loop.entity_storage.get(player_id).unwrap().position;
+        (game_loop, position.floor().as_ivec2() + ivec2(3, 0))
+    }
+
+    fn place(game_loop: &mut GameLoop, label: &str, position: IVec2) -> usize {
+        let spec = &game_loop.assets.block_specs[block_spec_id(&game_loop.assets, label)];
+        let block = block::Block::new(spec.id, position, 0, block::BlockState::initial(spec));
+        game_loop.block_storage.insert(block).unwrap()
+    }
+
+    /// 時計を進めてから1ティックを実行する。
+    fn skip(game_loop: &mut GameLoop, secs: f64) {
+        let time = game_loop.clock_sys.time();
+        game_loop.clock_sys.restore(time + secs);
+        game_loop.update(&Default::default(), &GameLoop::TICK);
+        game_loop.discard_changes();
+    }
+
+    fn spec_label(game_loop: &GameLoop, block_id: usize) -> &str {
+        let block = game_loop.block_storage.get(block_id).unwrap();
+        &game_loop.assets.block_specs[block.spec_id].label
+    }
+
+    #[test]
+    fn catch_up_chains_transitions() {
+        let mut assets = assets::Assets::new("assets/assets.json");
+        let oak = block_spec_id(&assets, "oakTree");
+        let dying = block_spec_id(&assets, "dyingTree");
+        assets.block_specs[oak].transition = Some(assets::BlockTransitionSpec {
+            block_spec_id: dying,
+            duration: 100.0,
+        });
+        let (mut game_loop, position) = empty_game_loop(assets);
+
+        let block_id = place(&mut game_loop, "sapling", position);
+        skip(&mut game_loop, 0.0);
+        assert!(game_loop.transition_sys.age(block_id).unwrap() < 0.1);
+
+        // NOTE: まとめて経過した時間で複数回遷移し、余りは次の遷移へ持ち越す。
+        skip(&mut game_loop, 450.0);
+        assert_eq!(spec_label(&game_loop, block_id), "dyingTree");
+        let age = game_loop.transition_sys.age(block_id).unwrap();
+        assert!((age - 50.0).abs() < 0.1, "age {}", age);
+    }
+
+    #[test]
+    fn blocked_footprint_waits() {
+        let assets = assets::Assets::new("assets/assets.json");
+        let (mut game_loop, position) = empty_game_loop(assets);
+
+        let block_id = place(&mut game_loop, "sapling", position);
+        let rock_id = place(&mut game_loop, "mixRock", position + ivec2(2, 0));
+        skip(&mut game_loop, 0.0);
+
+        // NOTE: 成長後の範囲が塞がれている間は遷移せず、経過時間は遷移の時間で止まる。
+        skip(&mut game_loop, 400.0);
+        assert_eq!(spec_label(&game_loop, block_id), "sapling");
+        assert_eq!(game_loop.transition_sys.age(block_id), Some(300.0));
+
+        game_loop.block_storage.remove(rock_id);
+        skip(&mut game_loop, 0.0);
+        assert_eq!(spec_label(&game_loop, block_id), "oakTree");
+    }
+}
@@ -0,0 +1,316 @@
+//! 天候の機能に関するモジュール
+
+use glam::*;
+use rand::{rngs::StdRng, Rng, SeedableRng};
+
+use crate::{
+    assets,
+    game_loop::{
+        clock, random,
+        system::{Phase, System, WorldContext},
+    },
+};
+
+/// ある地点の天候
+#[derive(Clone, Copy, Default, Debug)]
+pub struct Weather {
+    /// 天候の仕様の識別子 (該当する仕様が無い場合は`None`)
+    pub weather_spec_id: Option<usize>,
+    /// 天候の強さ (`0.0`から`1.0`、切り替わりの前後で変化する)
+    pub intensity: f32,
+}
+
+/// 地域毎の天候の状態
+///
+/// ワールドは一定の大きさの地域に分けられ、地域毎に一定の時間ずつ天候が選ばれる。
+/// 天候はシード値と時間から決まるため、保存する必要はない。
+/// 選ばれた天候は期間毎に地域毎に保持される。
+pub struct WeatherState {
+    assets: std::rc::Rc<assets::Assets>,
+    seed: u64,
+    /// 地域毎の期間と、その直前・現在・直後の期間の天候
+    choices: ahash::HashMap<IVec2, (i64, [Option<usize>; 3])>,
+}
+
+impl WeatherState {
+    /// 新しい天候の状態を作成する。
+    #[inline]
+    pub fn new(assets: std::rc::Rc<assets::Assets>, seed: u64) -> Self {
+        Self {
+            assets,
+            seed,
+            choices: Default::default(),
+        }
+    }
+
+    /// シード値を置き換える。
+    #[inline]
+    pub fn restore(&mut self, seed: u64) {
+        self.seed = seed;
+        self.choices.clear();
+    }
+
+    /// 指定した位置を含む地域を返す。
+    #[inline]
+    pub fn region(&self, position: Vec2) -> IVec2 {
+        let region_size = self.assets.climate_spec.region_size as f32;
+        position.div_euclid(Vec2::splat(region_size)).as_ivec2()
+    }
+
+    /// 指定した位置の現在の天候を返す。
+    pub fn weather(&mut self, clock_sys: &clock::ClockSystem, position: Vec2) -> Weather {
+        let region = self.region(position);
+
+        let period = self.period(clock_sys);
+        let duration = self.assets.climate_spec.duration as f64;
+        let elapsed = (clock_sys.time() - period as f64 * duration) as f32;
+
+        let [previous, weather_spec_id, next] = match self.choices.get(&region) {
+            Some((other, choices)) if *other == period => *choices,
+            _ => {
+                let choices = [
+                    self.choose(region, period - 1),
+                    self.choose(region, period),
+                    self.choose(region, period + 1),
+                ];
+                self.choices.insert(region, (period, choices));
+                choices
+            }
+        };
+
+        // NOTE: 前後で天候が異なる場合のみ、切り替わりの前後で強さを変化させる。
+        let climate_spec = &self.assets.climate_spec;
+        let mut intensity = 1.0f32;
+        if climate_spec.fade > 0.0 {
+            if previous != weather_spec_id {
+                intensity = intensity.min(elapsed / climate_spec.fade);
+            }
+            if next != weather_spec_id {
+                intensity = intensity.min((climate_spec.duration - elapsed) / climate_spec.fade);
+            }
+        }
+
+        Weather {
+            weather_spec_id,
+            intensity: intensity.clamp(0.0, 1.0),
+        }
+    }
+
+    /// 指定した位置で地上を移動するエンティティの速度の倍率を返す。
+    pub fn speed_multiplier(&mut self, clock_sys: &clock::ClockSystem, position: Vec2) -> f32 {
+        let weather = self.weather(clock_sys, position);
+        match weather.weather_spec_id {
+            Some(weather_spec_id) => {
+                let weather_spec = &self.assets.weather_specs[weather_spec_id];
+                1.0 + (weather_spec.speed_multiplier - 1.0) * weather.intensity
+            }
+            None => 1.0,
+        }
+    }
+
+    /// 指定した位置の地面が濡れているか真偽値を返す。
+    pub fn wets(
+        &mut self,
+        clock_sys: &clock::ClockSystem,
+        positions: impl IntoIterator<Item = Vec2>,
+    ) -> Vec<bool> {
+        positions
+            .into_iter()
+            .map(|position| {
+                let weather = self.weather(clock_sys, position);
+                weather.weather_spec_id.is_some_and(|weather_spec_id| {
+                    self.assets.weather_specs[weather_spec_id].wet && weather.intensity >= 0.5
+                })
+            })
+            .collect()
+    }
+
+    /// 現在の期間の番号を返す。
+    #[inline]
+    fn period(&self, clock_sys: &clock::ClockSystem) -> i64 {
+        let duration = self.assets.climate_spec.duration as f64;
+        (clock_sys.time() / duration).floor() as i64
+    }
+
+    /// 現在の期間ではない地域の天候を破棄する。
+    pub fn evict(&mut self, clock_sys: &clock::ClockSystem) {
+        let period = self.period(clock_sys);
+        self.choices.retain(|_, (other, _)| *other == period);
+    }
+
+    /// 地域と期間に固有の天候を選ぶ。
+    ///
+    /// 期間の開始時の時刻に発生しうる天候から重みに応じて選ばれる。
+    fn choose(&self, region: IVec2, period: i64) -> Option<usize> {
+        let climate_spec = &self.assets.climate_spec;
+        let clock_spec = &self.assets.clock_spec;
+
+        let start = period as f64 * climate_spec.duration as f64;
+        let time_of_day = (start / clock_spec.day_length as f64).rem_euclid(1.0) as f32;
+
+        let candidates = self
+            .assets
+            .weather_specs
+            .iter()
+            .filter(|weather_spec| match weather_spec.time_of_day {
+                // NOTE: 開始が終了より大きい場合は真夜中をまたぐ範囲として扱う。
+                Some((start, end)) if start <= end => start <= time_of_day && time_of_day < end,
+                Some((start, end)) => start <= time_of_day || time_of_day < end,
+                None => true,
+            })
+            .collect::<Vec<_>>();
+
+        let total = candidates
+            .iter()
+            .map(|weather_spec| weather_spec.weight)
+            .sum::<f32>();
+        if total <= 0.0 {
+            return None;
+        }
+
+        let mut value = self.rng(region, period).gen_range(0.0..total);
+        candidates.into_iter().find_map(|weather_spec| {
+            value -= weather_spec.weight;
+            (value < 0.0).then_some(weather_spec.id)
+        })
+    }
+
+    /// 地域と期間に固有の乱数生成器を返す。
+    fn rng(&self, region: IVec2, period: i64) -> StdRng {
+        let state = random::combine(
+            self.seed,
+            [
+                region.x as u32 as u64,
+                region.y as u32 as u64,
+                period as u64,
+            ],
+        );
+        StdRng::seed_from_u64(state)
+    }
+}
+
+/// 天候の機能
+///
+/// 天候の問い合わせは[`WeatherState`]が受け持ち、このシステムは期間の切り替わりを処理する。
+#[derive(Default)]
+pub struct WeatherSystem;
+
+impl System for WeatherSystem {
+    #[inline]
+    fn phase(&self) -> Phase {
+        Phase::Simulation
+    }
+
+    /// 過ぎた期間の天候を破棄する。
+    fn update(&mut self, context: &mut WorldContext) {
+        context.weather_state.evict(context.clock_sys);
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn weather_spec_id(assets: &assets::Assets, label: &str) -> usize {
+        assets
+            .weather_specs
+            .iter()
+            .position(|spec| spec.label == label)
+            .unwrap()
+    }
+
+    /// 期間の開始時の時刻 (`0.0`から`1.0`) を返す。
+    fn time_of_day(assets: &assets::Assets, period: i64) -> f32 {
+        let start = period as f64 * assets.climate_spec.duration as f64;
+        (start / assets.clock_spec.day_length as f64).rem_euclid(1.0) as f32
+    }
+
+    #[test]
+    fn choose_follows_seed_weight_and_time_of_day() {
+        let assets = std::rc::Rc::new(assets::Assets::new("assets/assets.json"));
+        let clear = weather_spec_id(&assets, "clear");
+        let fog = weather_spec_id(&assets, "fog");
+        let state = WeatherState::new(assets.clone(), 7);
+        let same = WeatherState::new(assets.clone(), 7);
+        let other = WeatherState::new(assets.clone(), 8);
+
+        let samples = (0..64)
+            .flat_map(|x| (0..64).map(move |period| (ivec2(x, -x), period)))
+            .map(|(region, period)| (period, state.choose(region, period)))
+            .collect::<Vec<_>>();
+
+        // NOTE: 同じシード値では同じ天候が選ばれる。
+        let differs = (0..64).any(|x| {
+            let region = ivec2(x, -x);
+            assert_eq!(
+                state.choose(region, x as i64),
+                same.choose(region, x as i64)
+            );
+            state.choose(region, x as i64) != other.choose(region, x as i64)
+        });
+        assert!(differs);
+
+        // NOTE: 時間帯が限られた天候はその時間帯に始まる期間でのみ選ばれる。
+        let (start, end) = assets.weather_specs[fog].time_of_day.unwrap();
+        let in_window = |period: i64| (start..end).contains(&time_of_day(&assets, period));
+        assert!(samples
+            .iter()
+            .all(|&(period, choice)| choice != Some(fog) || in_window(period)));
+        assert!(samples
+            .iter()
+            .any(|&(period, choice)| choice == Some(fog) && in_window(period)));
+
+        // NOTE: それ以外の期間では重みに比例して選ばれる。
+        let outside = samples
+            .iter()
+            .filter(|(period, _)| !in_window(*period))
+            .collect::<Vec<_>>();
+        let total = assets
+            .weather_specs
+            .iter()
+            .filter(|spec| spec.time_of_day.is_none())
+            .map(|spec| spec.weight)
+            .sum::<f32>();
+        let expected = assets.weather_specs[clear].weight / total;
+        let ratio = outside
+            .iter()
+            .filter(|(_, choice)| *choice == Some(clear))
+            .count() as f32
+            / outside.len() as f32;
+        assert!((ratio - expected).abs() < 0.05, "ratio {}", ratio);
+    }
+
+    #[test]
+    fn intensity_fades_between_different_weathers() {
+        let assets = std::rc::Rc::new(assets::Assets::new("assets/assets.json"));
+        let mut state = WeatherState::new(assets.clone(), 7);
+        let mut clock_sys = clock::ClockSystem::new(assets.clone());
+        let climate_spec = &assets.climate_spec;
+
+        // NOTE: 直前の期間と天候が異なる期間を探す。
+        let region = IVec2::ZERO;
+        let period = (1..1000)
+            .find(|&period| state.choose(region, period - 1) != state.choose(region, period))
+            .unwrap();
+        let start = period as f64 * climate_spec.duration as f64;
+        let position = Vec2::splat(0.5);
+
+        clock_sys.restore(start + climate_spec.fade as f64 * 0.5);
+        let weather = state.weather(&clock_sys, position);
+        assert_eq!(weather.weather_spec_id, state.choose(region, period));
+        assert!((weather.intensity - 0.5).abs() < 1e-3);
+
+        clock_sys.restore(start + climate_spec.fade as f64 * 2.0);
+        let weather = state.weather(&clock_sys, position);
+        assert_eq!(weather.intensity, 1.0);
+
+        // NOTE: 直後の期間と天候が同じ場合は弱まらない。
+        clock_sys.restore(start + (climate_spec.duration - climate_spec.fade * 0.25) as f64);
+        let weather = state.weather(&clock_sys, position);
+        let expected = match state.choose(region, period + 1) == weather.weather_spec_id {
+            true => 1.0,
+            false => 0.25,
+        };
+        assert!((weather.intensity - expected).abs() < 1e-3);
+    }
+}
@@ -0,0 +1,277 @@
+//! ウィンドウからの入力の変換に関するモジュール
+
+use std::{fs::File, path::Path};
+
+use glam::*;
+use winit::{event::MouseButton, keyboard::KeyCode};
+
+use crate::game_loop::{self, input::Action};
+
+/// 操作に割り当てられた入力
+#[derive(Clone, Copy, PartialEq, Eq, Debug)]
+pub enum Binding {
+    Key(KeyCode),
+    Mouse(MouseButton),
+    ScrollUp,
+    ScrollDown,
+}
+
+impl Binding {
+    /// キーマップ上の名前から入力を返す。
+    ///
+    /// キーの名前は[`KeyCode`]の名前と一致する。
+    fn parse(name: &str) -> Option<Self> {
+        macro_rules! parse_key_code {
+            ($($key_code:ident),* $(,)?) => {
+                match name {
+                    $(stringify!($key_code) => Some(Binding::Key(KeyCode::$key_code)),)*
+                    "MouseLeft" => Some(Binding::Mouse(MouseButton::Left)),
+                    "MouseRight" => Some(Binding::Mouse(MouseButton::Right)),
+                    "MouseMiddle" => Some(Binding::Mouse(MouseButton::Middle)),
+                    "ScrollUp" => Some(Binding::ScrollUp),
+                    "ScrollDown" => Some(Binding::ScrollDown),
+                    _ => None,
+                }
+            };
+        }
+
+        parse_key_code!(
+            KeyA,
+            KeyB,
+            KeyC,
+            KeyD,
+            KeyE,
+            KeyF,
+            KeyG,
+            KeyH,
+            KeyI,
+            KeyJ,
+            KeyK,
+            KeyL,
+            KeyM,
+            KeyN,
+            KeyO,
+            KeyP,
+            KeyQ,
+            KeyR,
+            KeyS,
+            KeyT,
+            KeyU,
+            KeyV,
+            KeyW,
+            KeyX,
+            KeyY,
+            KeyZ,
+            Digit0,
+            Digit1,
+            Digit2,
+            Digit3,
+            Digit4,
+            Digit5,
+            Digit6,
+            Digit7,
+            Digit8,
+            Digit9,
+            F1,
+            F2,
+            F3,
+            F4,
+            F5,
+            F6,
+            F7,
+            F8,
+            F9,
+            F10,
+            F11,
+            F12,
+            ArrowUp,
+            ArrowDown,
+            ArrowLeft,
+            ArrowRight,
+            ShiftLeft,
+            ShiftRight,
+            ControlLeft,
+            ControlRight,
+            AltLeft,
+            AltRight,
+            Space,
+            Enter,
+            Tab,
+            Escape,
+            Backspace,
+            Backquote,
+            Backslash,
+            BracketLeft,
+            BracketRight,
+            Comma,
+            Equal,
+            Minus,
+            Period,
+            Quote,
+            Semicolon,
+            Slash,
+        )
+    }
+}
+
+/// キーマップの読み込みに関するエラー
+#[derive(Debug)]
+pub enum KeymapError {
+    /// 入出力に失敗した。
+    Io(std::io::Error),
+    /// 形式が不正である。
+    Json(serde_json::Error),
+    /// 存在しない操作の名前である。
+    Action(String),
+    /// 存在しない入力の名前である。
+    Input(String),
+}
+
+impl std::fmt::Display for KeymapError {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            KeymapError::Io(e) => write!(f, "io error: {}", e),
+            KeymapError::Json(e) => write!(f, "json error: {}", e),
+            KeymapError::Action(name) => write!(f, "unknown action: {}", name),
+            KeymapError::Input(name) => write!(f, "unknown input: {}", name),
+        }
+    }
+}
+
+impl std::error::Error for KeymapError {}
+
+impl From<std::io::Error> for KeymapError {
+    #[inline]
+    fn from(e: std::io::Error) -> Self {
+        KeymapError::Io(e)
+    }
+}
+
+impl From<serde_json::Error> for KeymapError {
+    #[inline]
+    fn from(e: serde_json::Error) -> Self {
+        KeymapError::Json(e)
+    }
+}
+
+/// 操作と入力の割り当て
+///
+/// 1つの操作に複数の入力を割り当てることができる。
+pub struct Keymap {
+    bindings: Vec<(Action, Binding)>,
+}
+
+impl Keymap {
+    /// キーマップをファイルから読み込む。
+    ///
+    /// 存在しない操作・入力の名前が含まれる場合はその名前をエラーとして返す。
+    pub fn new<P: AsRef<Path>>(path: P) -> Result<Self, KeymapError> {
+        #[derive(serde::Deserialize)]
+        #[serde(rename_all = "camelCase")]
+        struct BindingIn {
+            action: String,
+            inputs: Vec<String>,
+        }
+
+        #[derive(serde::Deserialize)]
+        #[serde(rename_all = "camelCase")]
+        struct KeymapIn {
+            bindings: Vec<BindingIn>,
+        }
+
+        let reader = File::open(path)?;
+        let KeymapIn { bindings } = serde_json::from_reader(reader)?;
+
+        let mut entries = vec![];
+        for BindingIn { action, inputs } in bindings {
+            let action = match action.as_str() {
+                "moveUp" => Action::MoveUp,
+                "moveDown" => Action::MoveDown,
+                "moveLeft" => Action::MoveLeft,
+                "moveRight" => Action::MoveRight,
+                "moveTo" => Action::MoveTo,
+                "sprint" => Action::Sprint,
+                "zoomIn" => Action::ZoomIn,
+                "zoomOut" => Action::ZoomOut,
+                "zoomReset" => Action::ZoomReset,
+                "break" => Action::Break,
+                "place" => Action::Place,
+                "attack" => Action::Attack,
+                "shoot" => Action::Shoot,
+                "selectNext" => Action::SelectNext,
+                "selectPrevious" => Action::SelectPrevious,
+                "hotbar1" => Action::Hotbar(0),
+                "hotbar2" => Action::Hotbar(1),
+                "hotbar3" => Action::Hotbar(2),
+                "hotbar4" => Action::Hotbar(3),
+                "hotbar5" => Action::Hotbar(4),
+                "hotbar6" => Action::Hotbar(5),
+                "hotbar7" => Action::Hotbar(6),
+                "hotbar8" => Action::Hotbar(7),
+                "hotbar9" => Action::Hotbar(8),
+                "quicksave" => Action::Quicksave,
+                "quickload" => Action::Quickload,
+                "console" => Action::Console,
+                _ => return Err(KeymapError::Action(action)),
+            };
+
+            for input in inputs {
+                let binding = Binding::parse(&input).ok_or(KeymapError::Input(input))?;
+                entries.push((action, binding));
+            }
+        }
+
+        Ok(Self { bindings: entries })
+    }
+
+    /// 操作に割り当てられた全ての入力を返す。
+    #[inline]
+    pub fn bindings(&self, action: Action) -> impl Iterator<Item = Binding> + '_ {
+        self.bindings
+            .iter()
+            .filter(move |(other, _)| *other == action)
+            .map(|(_, binding)| *binding)
+    }
+
+    /// キーが操作に割り当てられているか真偽値を返す。
+    #[inline]
+    pub fn is_bound(&self, action: Action, key_code: KeyCode) -> bool {
+        self.bindings(action)
+            .any(|binding| binding == Binding::Key(key_code))
+    }
+
+    /// ウィンドウからの入力をゲームループの入力の状態へ変換する。
+    pub fn input_state(
+        &self,
+        input: &winit_input_helper::WinitInputHelper,
+        game_loop: &game_loop::GameLoop,
+        window_size: (u32, u32),
+    ) -> game_loop::input::InputState {
+        let mut input_state = game_loop::input::InputState::default();
+
+        let (_, scroll) = input.scroll_diff();
+
+        self.bindings.iter().for_each(|(action, binding)| {
+            // NOTE: スクロールは発生したフレームのみ押されたものとして扱う。
+            let (held, pressed) = match binding {
+                Binding::Key(key_code) => (input.key_held(*key_code), input.key_pressed(*key_code)),
+                Binding::Mouse(button) => (input.mouse_held(*button), input.mouse_pressed(*button)),
+                Binding::ScrollUp => (scroll > 0.0, scroll > 0.0),
+                Binding::ScrollDown => (scroll < 0.0, scroll < 0.0),
+            };
+            if held {
+                input_state.held.insert(*action);
+            }
+            if pressed {
+                input_state.pressed.insert(*action);
+            }
+        });
+
+        input_state.cursor = input
+            .cursor()
+            .map(|(x, y)| game_loop.viewport_to_world(vec2(x, y), window_size));
+        input_state.scroll = scroll;
+
+        input_state
+    }
+}
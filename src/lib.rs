@@ -0,0 +1,10 @@
+//! タイルマップのゲームのライブラリ
+//!
+//! ゲームループとワールドの格納庫を公開するため、クレートの外部から
+//! [`game_loop::system::System`]を実装してゲームループへ登録できる。
+
+pub mod aabb;
+pub mod assets;
+pub mod game_loop;
+pub mod input;
+pub mod renderer;
@@ -1,22 +1,50 @@
+use winit::event::ElementState;
 use winit::event::Event;
 use winit::event::WindowEvent;
+use winit::keyboard::{Key, NamedKey, PhysicalKey};
 
-mod aabb;
-mod assets;
-mod game_loop;
-mod renderer;
+use wgpu_tilemap_game::{assets, game_loop, input, renderer};
+
+/// クイックセーブの保存先
+const QUICKSAVE_PATH: &str = "quicksave.bin";
 
 fn main() {
     let assets = assets::Assets::new("assets/assets.json");
     let assets = std::rc::Rc::new(assets);
 
+    // NOTE: コマンドライン引数
+    // `--record <path>` 入力を記録し、終了時にファイルへ書き込む。
+    // `--replay <path>` 記録をウィンドウ無しで再生し、状態のハッシュ値を検証する。
+    let args = std::env::args().collect::<Vec<_>>();
+    let arg = |name: &str| {
+        args.iter()
+            .position(|arg| arg == name)
+            .and_then(|i| args.get(i + 1))
+            .cloned()
+    };
+
+    if let Some(path) = arg("--replay") {
+        replay(assets, &path);
+        return;
+    }
+    let record_path = arg("--record");
+
+    let keymap = input::Keymap::new("assets/keymap.json").unwrap_or_else(|e| {
+        eprintln!("failed to read keymap: {}", e);
+        std::process::exit(2);
+    });
+
     let event_loop = winit::event_loop::EventLoopBuilder::new().build().unwrap();
     let window = winit::window::WindowBuilder::new()
         .build(&event_loop)
         .unwrap();
     let window = std::rc::Rc::new(window);
 
-    let mut game_loop = game_loop::GameLoop::new(assets.clone());
+    let seed = rand::random();
+    let mut game_loop = game_loop::GameLoop::new(assets.clone(), seed);
+    let mut recording = record_path
+        .as_ref()
+        .map(|_| game_loop::replay::Recording::new(seed));
     let mut renderer = pollster::block_on(renderer::RenderingSystem::new_async(
         assets.clone(),
         window.clone(),
@@ -34,20 +62,104 @@ fn main() {
                 }
                 Event::WindowEvent { event, .. } => match event {
                     WindowEvent::RedrawRequested => {
-                        let tick =
+                        let elapsed =
                             std::mem::replace(&mut instant, std::time::Instant::now()).elapsed();
                         let window_size = (window.inner_size().width, window.inner_size().height);
 
-                        game_loop.update(&input, &tick, window_size);
+                        let mut input_state = keymap.input_state(&input, &game_loop, window_size);
+
+                        // NOTE: 開発者コンソール
+                        // 記録中のコマンドは再生で再現できないため無効にする。
+                        if input_state.pressed(game_loop::input::Action::Console) {
+                            let open = game_loop.console().is_open();
+                            if !open && recording.is_some() {
+                                eprintln!("console is disabled while recording");
+                            } else {
+                                game_loop.console_mut().set_open(!open);
+                            }
+                        }
+                        // NOTE: コンソールを開いている間はカーソル以外の入力を無視する。
+                        if game_loop.console().is_open() {
+                            input_state = game_loop::input::InputState {
+                                cursor: input_state.cursor,
+                                ..Default::default()
+                            };
+                        }
+
+                        game_loop.update(&input_state, &elapsed);
+
+                        // NOTE: スクリプトのエラーはゲームを止めずに報告する。
+                        for e in game_loop.drain_script_errors() {
+                            eprintln!("script error: {}", e);
+                        }
+
+                        if let Some(recording) = &mut recording {
+                            recording.record(&input_state, &elapsed);
+                        }
+
+                        // NOTE: クイックセーブ・クイックロード
+                        if input_state.pressed(game_loop::input::Action::Quicksave) {
+                            if let Err(e) = game_loop.save(QUICKSAVE_PATH) {
+                                eprintln!("failed to save: {}", e);
+                            }
+                        }
+                        if input_state.pressed(game_loop::input::Action::Quickload) {
+                            // NOTE: 記録中の読み込みは再生で再現できないため無効にする。
+                            if recording.is_some() {
+                                eprintln!("quickload is disabled while recording");
+                            } else if let Err(e) = game_loop.load(QUICKSAVE_PATH) {
+                                eprintln!("failed to load: {}", e);
+                            }
+                        }
 
                         let extract = game_loop.extract(window_size);
 
                         renderer.draw(&extract);
                     }
+                    WindowEvent::KeyboardInput { event, .. }
+                        if game_loop.console().is_open()
+                            && event.state == ElementState::Pressed =>
+                    {
+                        match event.logical_key {
+                            Key::Named(NamedKey::Enter) => game_loop.console_submit(),
+                            Key::Named(NamedKey::Backspace) => game_loop.console_mut().pop(),
+                            Key::Named(NamedKey::Tab) => game_loop.console_complete(),
+                            Key::Named(NamedKey::ArrowUp) => game_loop.console_mut().previous(),
+                            Key::Named(NamedKey::ArrowDown) => game_loop.console_mut().next(),
+                            Key::Named(NamedKey::Escape) => game_loop.console_mut().set_open(false),
+                            // NOTE: 切り替えのキーの文字は入力しない。
+                            _ if matches!(
+                                event.physical_key,
+                                PhysicalKey::Code(key_code)
+                                    if keymap.is_bound(game_loop::input::Action::Console, key_code)
+                            ) => {}
+                            _ => event
+                                .text
+                                .iter()
+                                .flat_map(|text| text.chars())
+                                .filter(|c| !c.is_control())
+                                .for_each(|c| game_loop.console_mut().push(c)),
+                        }
+                    }
                     WindowEvent::Resized(new_inner_size) => {
                         renderer.resize(new_inner_size);
                     }
                     WindowEvent::CloseRequested => {
+                        if let (Some(recording), Some(path)) = (&mut recording, &record_path) {
+                            recording.state_hash = Some(game_loop.state_hash());
+                            let result = std::fs::File::create(path)
+                                .map_err(game_loop::save::SaveError::from)
+                                .and_then(|file| {
+                                    game_loop::replay::write(
+                                        std::io::BufWriter::new(file),
+                                        recording,
+                                    )
+                                });
+                            if let Err(e) = result {
+                                eprintln!("failed to write recording: {}", e);
+                            }
+                        }
+
                         control_flow.exit();
                     }
                     _ => (),
@@ -57,3 +169,26 @@ fn main() {
         })
         .unwrap();
 }
+
+/// 記録をウィンドウ無しで再生し、状態のハッシュ値を出力する。
+///
+/// 記録時のハッシュ値と一致しない場合は異常終了する。
+fn replay(assets: std::rc::Rc<assets::Assets>, path: &str) {
+    let recording = std::fs::File::open(path)
+        .map_err(game_loop::save::SaveError::from)
+        .and_then(|file| game_loop::replay::read(std::io::BufReader::new(file)))
+        .unwrap_or_else(|e| {
+            eprintln!("failed to read recording: {}", e);
+            std::process::exit(2);
+        });
+
+    let state_hash = recording.replay(assets).state_hash();
+    println!("state hash: {:016x}", state_hash);
+
+    if let Some(expected) = recording.state_hash {
+        if state_hash != expected {
+            eprintln!("state hash mismatch: expected {:016x}", expected);
+            std::process::exit(1);
+        }
+    }
+}
@@ -6,6 +6,9 @@ mod base;
 mod block;
 mod camera;
 mod entity;
+mod light;
+mod overlay;
+mod weather;
 
 pub struct RenderingState {
     pub device: wgpu::Device,
@@ -65,6 +68,9 @@ pub struct RenderingSystem {
     base_renderer: base::BaseRenderer,
     block_renderer: block::BlockRenderer,
     entity_renderer: entity::EntityRenderer,
+    weather_renderer: weather::WeatherRenderer,
+    light_renderer: light::LightRenderer,
+    overlay_renderer: overlay::OverlayRenderer,
 }
 
 impl RenderingSystem {
@@ -85,6 +91,10 @@ impl RenderingSystem {
             block::BlockRenderer::new(assets.clone(), &rendering_state, &camera_resource);
         let entity_renderer =
             entity::EntityRenderer::new(assets.clone(), &rendering_state, &camera_resource);
+        let weather_renderer =
+            weather::WeatherRenderer::new(assets.clone(), &rendering_state, &camera_resource);
+        let light_renderer = light::LightRenderer::new(&rendering_state, &camera_resource);
+        let overlay_renderer = overlay::OverlayRenderer::new(assets.clone(), &rendering_state);
 
         Self {
             rendering_state,
@@ -92,12 +102,16 @@ impl RenderingSystem {
             base_renderer,
             block_renderer,
             entity_renderer,
+            weather_renderer,
+            light_renderer,
+            overlay_renderer,
         }
     }
 
     pub fn resize(&mut self, new_inner_size: winit::dpi::PhysicalSize<u32>) {
         self.rendering_state.resize(new_inner_size);
         self.camera_resource.resize(&self.rendering_state);
+        self.light_renderer.resize(&self.rendering_state);
     }
 
     /// 描写サイクルを実行する。
@@ -119,6 +133,12 @@ impl RenderingSystem {
             .upload(&mut self.rendering_state, &mut encoder, extract);
         self.entity_renderer
             .upload(&mut self.rendering_state, &mut encoder, extract);
+        self.weather_renderer
+            .upload(&mut self.rendering_state, &mut encoder, extract);
+        self.light_renderer
+            .upload(&mut self.rendering_state, &mut encoder, extract);
+        self.overlay_renderer
+            .upload(&mut self.rendering_state, &mut encoder, extract);
 
         let frame = self.rendering_state.surface.get_current_texture().unwrap();
         let frame_view = frame
@@ -132,8 +152,15 @@ impl RenderingSystem {
             .render(&mut render_pass, &self.camera_resource);
         self.entity_renderer
             .render(&mut render_pass, &self.camera_resource);
+        self.weather_renderer
+            .render(&mut render_pass, &self.camera_resource);
         drop(render_pass);
 
+        self.light_renderer
+            .render(&mut encoder, &self.camera_resource);
+        self.light_renderer.composite(&mut encoder, &frame_view);
+        self.overlay_renderer.render(&mut encoder, &frame_view);
+
         self.rendering_state.staging_belt.finish();
         self.rendering_state.queue.submit([encoder.finish()]);
         self.rendering_state.staging_belt.recall();
@@ -45,6 +45,7 @@ struct BatchBuffer {
 pub struct BaseRenderer {
     assets: std::rc::Rc<assets::Assets>,
     texcoords: Vec<image_atlas::Texcoord32>,
+    wet_texcoords: Vec<Option<image_atlas::Texcoord32>>,
     batch_buffers: Vec<BatchBuffer>,
     pipeline: wgpu::RenderPipeline,
 }
@@ -81,12 +82,17 @@ impl BaseRenderer {
             ],
         });
 
+        // NOTE: 濡れている時のテクスチャは全ての仕様のテクスチャの後に並べる。
         let entries = assets
             .base_specs
             .iter()
-            .map(|spec| {
-                let texture = image::open(&spec.texture_path).unwrap();
-                let mip = spec.texture_mip_option;
+            .map(|spec| (&spec.texture_path, spec.texture_mip_option))
+            .chain(assets.base_specs.iter().filter_map(|spec| {
+                let path = spec.wet_texture_path.as_ref()?;
+                Some((path, spec.texture_mip_option))
+            }))
+            .map(|(path, mip)| {
+                let texture = image::open(path).unwrap();
                 image_atlas::AtlasEntry { texture, mip }
             })
             .collect::<Vec<_>>();
@@ -101,12 +107,23 @@ impl BaseRenderer {
         })
         .unwrap();
 
-        let texcoords = texture_atlas
+        let mut texcoords = texture_atlas
             .texcoords
             .into_iter()
             .map(|texcoord| texcoord.to_f32())
             .collect::<Vec<_>>();
 
+        let mut wet_texcoords = texcoords.split_off(assets.base_specs.len()).into_iter();
+        let wet_texcoords = assets
+            .base_specs
+            .iter()
+            .map(|spec| {
+                spec.wet_texture_path
+                    .as_ref()
+                    .map(|_| wet_texcoords.next().unwrap())
+            })
+            .collect::<Vec<_>>();
+
         let batch_buffers = texture_atlas
             .textures
             .into_iter()
@@ -214,6 +231,7 @@ impl BaseRenderer {
         Self {
             assets,
             texcoords,
+            wet_texcoords,
             batch_buffers,
             pipeline,
         }
@@ -228,9 +246,15 @@ impl BaseRenderer {
         let device = &rendering_state.device;
         let staging_belt = &mut rendering_state.staging_belt;
 
-        extract.bases.iter().for_each(|base| {
+        let bases = extract.bases.iter().zip(&extract.base_wets);
+        bases.for_each(|(base, &wet)| {
+            let spec = &self.assets.base_specs[base.spec_id];
+
             let rect = iaabb2(base.position, base.position + IVec2::ONE).as_aabb2();
-            let texcoord = &self.texcoords[base.spec_id];
+            let texcoord = match &self.wet_texcoords[base.spec_id] {
+                Some(wet_texcoord) if wet => wet_texcoord,
+                _ => &self.texcoords[base.spec_id],
+            };
             let batch = &mut self.batch_buffers[texcoord.page as usize];
 
             let vertex_count = batch.vertices.len() as u32;
@@ -241,21 +265,24 @@ impl BaseRenderer {
             batch.indices.push(vertex_count + 3);
             batch.indices.push(vertex_count);
 
+            // NOTE: 層の順に手前へ配置し、全ての層をブロックより奥に収める。
             const BASE_Z: f32 = -0.00390625; // z = -2^(-8)
+            const LAYER_Z: f32 = 0.0009765625; // z = 2^(-10)
+            let base_z = BASE_Z + spec.layer as usize as f32 * LAYER_Z;
             batch.vertices.push(Vertex {
-                position: [rect.min.x, rect.min.y, BASE_Z],
+                position: [rect.min.x, rect.min.y, base_z],
                 texcoord: [texcoord.min_x, texcoord.max_y],
             });
             batch.vertices.push(Vertex {
-                position: [rect.max.x, rect.min.y, BASE_Z],
+                position: [rect.max.x, rect.min.y, base_z],
                 texcoord: [texcoord.max_x, texcoord.max_y],
             });
             batch.vertices.push(Vertex {
-                position: [rect.max.x, rect.max.y, BASE_Z],
+                position: [rect.max.x, rect.max.y, base_z],
                 texcoord: [texcoord.max_x, texcoord.min_y],
             });
             batch.vertices.push(Vertex {
-                position: [rect.min.x, rect.max.y, BASE_Z],
+                position: [rect.min.x, rect.max.y, base_z],
                 texcoord: [texcoord.min_x, texcoord.min_y],
             });
         });
@@ -45,6 +45,7 @@ struct BatchBuffer {
 pub struct BlockRenderer {
     assets: std::rc::Rc<assets::Assets>,
     texcoords: Vec<image_atlas::Texcoord32>,
+    texcoord_offsets: Vec<usize>,
     batch_buffers: Vec<BatchBuffer>,
     pipeline: wgpu::RenderPipeline,
 }
@@ -81,13 +82,27 @@ impl BlockRenderer {
             ],
         });
 
+        // NOTE: 状態によって切り替わるスプライトを仕様毎に連続して配置する。
         let entries = assets
             .block_specs
             .iter()
-            .map(|spec| {
-                let texture = image::open(&spec.texture_path).unwrap();
-                let mip = spec.texture_mip_option;
-                image_atlas::AtlasEntry { texture, mip }
+            .flat_map(|spec| {
+                std::iter::once(&spec.texture_path)
+                    .chain(&spec.state_texture_paths)
+                    .map(|texture_path| {
+                        let texture = image::open(texture_path).unwrap();
+                        let mip = spec.texture_mip_option;
+                        image_atlas::AtlasEntry { texture, mip }
+                    })
+            })
+            .collect::<Vec<_>>();
+        let texcoord_offsets = assets
+            .block_specs
+            .iter()
+            .scan(0, |offset, spec| {
+                let texcoord_offset = *offset;
+                *offset += 1 + spec.state_texture_paths.len();
+                Some(texcoord_offset)
             })
             .collect::<Vec<_>>();
         let texture_atlas = image_atlas::create_atlas(&image_atlas::AtlasDescriptor {
@@ -214,6 +229,7 @@ impl BlockRenderer {
         Self {
             assets,
             texcoords,
+            texcoord_offsets,
             batch_buffers,
             pipeline,
         }
@@ -232,7 +248,8 @@ impl BlockRenderer {
             let spec = &self.assets.block_specs[block.spec_id];
 
             let rect = iaabb2(block.position, block.position).as_aabb2() + spec.rendering_size;
-            let texcoord = &self.texcoords[block.spec_id];
+            let sprite = block.state.sprite(spec);
+            let texcoord = &self.texcoords[self.texcoord_offsets[block.spec_id] + sprite];
             let batch = &mut self.batch_buffers[texcoord.page as usize];
 
             let vertex_count = batch.vertices.len() as u32;
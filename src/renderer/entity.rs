@@ -16,11 +16,13 @@ use crate::{
 struct Vertex {
     pub position: [f32; 3],
     pub texcoord: [f32; 2],
+    /// ダメージを受けた時の点滅の強さ
+    pub flash: f32,
 }
 
 impl Vertex {
     const ATTRIBUTES: &'static [wgpu::VertexAttribute] =
-        &wgpu::vertex_attr_array![0 => Float32x3, 1 => Float32x2];
+        &wgpu::vertex_attr_array![0 => Float32x3, 1 => Float32x2, 2 => Float32];
 
     #[inline]
     fn layout<'a>() -> wgpu::VertexBufferLayout<'a> {
@@ -228,7 +230,8 @@ impl EntityRenderer {
         let device = &rendering_state.device;
         let staging_belt = &mut rendering_state.staging_belt;
 
-        extract.entities.iter().for_each(|entity| {
+        let entities = extract.entities.iter().zip(&extract.entity_flashes);
+        entities.for_each(|(entity, &flash)| {
             let spec = &self.assets.entity_specs[entity.spec_id];
 
             let rect = aabb2(entity.position, entity.position) + spec.rendering_size;
@@ -251,18 +254,22 @@ impl EntityRenderer {
             batch.vertices.push(Vertex {
                 position: [rect.min.x, rect.min.y, negative_y2z],
                 texcoord: [texcoord.min_x, texcoord.max_y],
+                flash,
             });
             batch.vertices.push(Vertex {
                 position: [rect.max.x, rect.min.y, negative_y2z],
                 texcoord: [texcoord.max_x, texcoord.max_y],
+                flash,
             });
             batch.vertices.push(Vertex {
                 position: [rect.max.x, rect.max.y, positive_y2z],
                 texcoord: [texcoord.max_x, texcoord.min_y],
+                flash,
             });
             batch.vertices.push(Vertex {
                 position: [rect.min.x, rect.max.y, positive_y2z],
                 texcoord: [texcoord.min_x, texcoord.min_y],
+                flash,
             });
         });
 
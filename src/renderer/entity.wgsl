@@ -4,11 +4,13 @@ var<uniform> view_matrix: mat4x4<f32>;
 struct VertexInput {
     @location(0) position: vec3<f32>,
     @location(1) texcoord: vec2<f32>,
+    @location(2) flash: f32,
 };
 
 struct VertexOutput {
     @builtin(position) clip_position: vec4<f32>,
     @location(0) texcoord: vec2<f32>,
+    @location(1) flash: f32,
 };
 
 @vertex
@@ -18,6 +20,7 @@ fn vs_main(
     var out: VertexOutput;
     out.clip_position = view_matrix * vec4<f32>(vertex.position, 1.0);
     out.texcoord = vertex.texcoord;
+    out.flash = vertex.flash;
     return out;
 }
 
@@ -34,5 +37,8 @@ fn fs_main(in: VertexOutput) -> @location(0) vec4<f32> {
         discard;
     }
 
+    // NOTE: ダメージを受けた時は赤く点滅させる。
+    color = vec4<f32>(mix(color.rgb, vec3<f32>(1.0, 0.2, 0.2), in.flash), color.a);
+
     return color;
 }
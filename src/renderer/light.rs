@@ -0,0 +1,495 @@
+//! 光源の描写に関するモジュール
+//!
+//! 光源は環境光の色で初期化されたライトバッファへ加算され、
+//! ベース・ブロック・エンティティの描写結果に乗算される。
+
+use std::num;
+
+use glam::*;
+
+use crate::aabb::*;
+use crate::{
+    game_loop,
+    renderer::{self, camera},
+};
+
+/// ライトバッファに使用するテクスチャのフォーマット[`wgpu::TextureFormat`]
+const LIGHT_FORMAT: wgpu::TextureFormat = wgpu::TextureFormat::Rgba16Float;
+
+/// 遮蔽を調べるタイルの範囲の一辺の大きさ
+const OCCLUSION_SIZE: u32 = 256;
+
+/// 1フレームに描写する光源の最大数
+const MAX_LIGHTS: usize = 1024;
+
+#[repr(C)]
+#[derive(Clone, Copy, bytemuck::Pod, bytemuck::Zeroable)]
+struct Vertex {
+    pub position: [f32; 2],
+    pub center: [f32; 2],
+    pub color: [f32; 3],
+    pub radius: f32,
+    pub occlusion: f32,
+}
+
+impl Vertex {
+    const ATTRIBUTES: &'static [wgpu::VertexAttribute] = &wgpu::vertex_attr_array![
+        0 => Float32x2, 1 => Float32x2, 2 => Float32x3, 3 => Float32, 4 => Float32
+    ];
+
+    #[inline]
+    fn layout<'a>() -> wgpu::VertexBufferLayout<'a> {
+        wgpu::VertexBufferLayout {
+            array_stride: std::mem::size_of::<Self>() as u64,
+            step_mode: wgpu::VertexStepMode::Vertex,
+            attributes: Self::ATTRIBUTES,
+        }
+    }
+}
+
+/// 遮蔽を調べるタイルの範囲
+#[repr(C)]
+#[derive(Clone, Copy, bytemuck::Pod, bytemuck::Zeroable)]
+struct Occlusion {
+    pub origin: [f32; 2],
+    pub size: [f32; 2],
+}
+
+pub struct LightRenderer {
+    vertices: Vec<Vertex>,
+    vertex_buffer: wgpu::Buffer,
+    indices: Vec<u32>,
+    index_buffer: wgpu::Buffer,
+    index_count: u32,
+    occlusion_texels: Vec<u8>,
+    occlusion_texture: wgpu::Texture,
+    occlusion_buffer: wgpu::Buffer,
+    bind_group: wgpu::BindGroup,
+    pipeline: wgpu::RenderPipeline,
+    light_view: wgpu::TextureView,
+    composite_bind_group_layout: wgpu::BindGroupLayout,
+    composite_bind_group: wgpu::BindGroup,
+    composite_pipeline: wgpu::RenderPipeline,
+    ambient: Vec3,
+}
+
+impl LightRenderer {
+    pub fn new(
+        rendering_state: &renderer::RenderingState,
+        camera_resource: &camera::CameraResource,
+    ) -> Self {
+        let device = &rendering_state.device;
+        let config = &rendering_state.config;
+
+        let vertex_buffer = device.create_buffer(&wgpu::BufferDescriptor {
+            label: None,
+            size: (std::mem::size_of::<Vertex>() * 4 * MAX_LIGHTS) as u64,
+            usage: wgpu::BufferUsages::VERTEX | wgpu::BufferUsages::COPY_DST,
+            mapped_at_creation: false,
+        });
+
+        let index_buffer = device.create_buffer(&wgpu::BufferDescriptor {
+            label: None,
+            size: (std::mem::size_of::<u32>() * 6 * MAX_LIGHTS) as u64,
+            usage: wgpu::BufferUsages::INDEX | wgpu::BufferUsages::COPY_DST,
+            mapped_at_creation: false,
+        });
+
+        let occlusion_texture = device.create_texture(&wgpu::TextureDescriptor {
+            label: None,
+            size: wgpu::Extent3d {
+                width: OCCLUSION_SIZE,
+                height: OCCLUSION_SIZE,
+                depth_or_array_layers: 1,
+            },
+            mip_level_count: 1,
+            sample_count: 1,
+            dimension: wgpu::TextureDimension::D2,
+            format: wgpu::TextureFormat::R8Unorm,
+            usage: wgpu::TextureUsages::TEXTURE_BINDING | wgpu::TextureUsages::COPY_DST,
+            view_formats: &[],
+        });
+        let occlusion_view = occlusion_texture.create_view(&wgpu::TextureViewDescriptor::default());
+
+        // NOTE: 線形補間により遮蔽物の境界をぼかし、柔らかい影にする。
+        let occlusion_sampler = device.create_sampler(&wgpu::SamplerDescriptor {
+            mag_filter: wgpu::FilterMode::Linear,
+            min_filter: wgpu::FilterMode::Linear,
+            ..Default::default()
+        });
+
+        let occlusion_buffer = device.create_buffer(&wgpu::BufferDescriptor {
+            label: None,
+            size: std::mem::size_of::<Occlusion>() as u64,
+            usage: wgpu::BufferUsages::UNIFORM | wgpu::BufferUsages::COPY_DST,
+            mapped_at_creation: false,
+        });
+
+        let bind_group_layout = device.create_bind_group_layout(&wgpu::BindGroupLayoutDescriptor {
+            label: None,
+            entries: &[
+                wgpu::BindGroupLayoutEntry {
+                    binding: 0,
+                    visibility: wgpu::ShaderStages::FRAGMENT,
+                    ty: wgpu::BindingType::Texture {
+                        sample_type: wgpu::TextureSampleType::Float { filterable: true },
+                        view_dimension: wgpu::TextureViewDimension::D2,
+                        multisampled: false,
+                    },
+                    count: None,
+                },
+                wgpu::BindGroupLayoutEntry {
+                    binding: 1,
+                    visibility: wgpu::ShaderStages::FRAGMENT,
+                    ty: wgpu::BindingType::Sampler(wgpu::SamplerBindingType::Filtering),
+                    count: None,
+                },
+                wgpu::BindGroupLayoutEntry {
+                    binding: 2,
+                    visibility: wgpu::ShaderStages::FRAGMENT,
+                    ty: wgpu::BindingType::Buffer {
+                        ty: wgpu::BufferBindingType::Uniform,
+                        has_dynamic_offset: false,
+                        min_binding_size: None,
+                    },
+                    count: None,
+                },
+            ],
+        });
+
+        let bind_group = device.create_bind_group(&wgpu::BindGroupDescriptor {
+            label: None,
+            layout: &bind_group_layout,
+            entries: &[
+                wgpu::BindGroupEntry {
+                    binding: 0,
+                    resource: wgpu::BindingResource::TextureView(&occlusion_view),
+                },
+                wgpu::BindGroupEntry {
+                    binding: 1,
+                    resource: wgpu::BindingResource::Sampler(&occlusion_sampler),
+                },
+                wgpu::BindGroupEntry {
+                    binding: 2,
+                    resource: occlusion_buffer.as_entire_binding(),
+                },
+            ],
+        });
+
+        let pipeline_layout = device.create_pipeline_layout(&wgpu::PipelineLayoutDescriptor {
+            label: None,
+            bind_group_layouts: &[&bind_group_layout, camera_resource.bind_group_layout()],
+            push_constant_ranges: &[],
+        });
+
+        // NOTE: 光源は加算で合成する。
+        let additive = wgpu::BlendComponent {
+            src_factor: wgpu::BlendFactor::One,
+            dst_factor: wgpu::BlendFactor::One,
+            operation: wgpu::BlendOperation::Add,
+        };
+
+        let shader = device.create_shader_module(wgpu::include_wgsl!("light.wgsl"));
+        let pipeline = device.create_render_pipeline(&wgpu::RenderPipelineDescriptor {
+            label: None,
+            layout: Some(&pipeline_layout),
+            vertex: wgpu::VertexState {
+                module: &shader,
+                entry_point: "vs_main",
+                buffers: &[Vertex::layout()],
+            },
+            fragment: Some(wgpu::FragmentState {
+                module: &shader,
+                entry_point: "fs_main",
+                targets: &[Some(wgpu::ColorTargetState {
+                    format: LIGHT_FORMAT,
+                    blend: Some(wgpu::BlendState {
+                        color: additive,
+                        alpha: additive,
+                    }),
+                    write_mask: wgpu::ColorWrites::ALL,
+                })],
+            }),
+            primitive: wgpu::PrimitiveState::default(),
+            depth_stencil: None,
+            multisample: wgpu::MultisampleState::default(),
+            multiview: None,
+        });
+
+        let composite_bind_group_layout =
+            device.create_bind_group_layout(&wgpu::BindGroupLayoutDescriptor {
+                label: None,
+                entries: &[wgpu::BindGroupLayoutEntry {
+                    binding: 0,
+                    visibility: wgpu::ShaderStages::FRAGMENT,
+                    ty: wgpu::BindingType::Texture {
+                        sample_type: wgpu::TextureSampleType::Float { filterable: false },
+                        view_dimension: wgpu::TextureViewDimension::D2,
+                        multisampled: false,
+                    },
+                    count: None,
+                }],
+            });
+
+        let light_view = create_light_texture(device, config.width, config.height)
+            .create_view(&wgpu::TextureViewDescriptor::default());
+        let composite_bind_group =
+            create_composite_bind_group(device, &composite_bind_group_layout, &light_view);
+
+        let composite_pipeline_layout =
+            device.create_pipeline_layout(&wgpu::PipelineLayoutDescriptor {
+                label: None,
+                bind_group_layouts: &[&composite_bind_group_layout],
+                push_constant_ranges: &[],
+            });
+
+        // NOTE: ライトバッファを描写結果に乗算する。
+        let multiply = wgpu::BlendState {
+            color: wgpu::BlendComponent {
+                src_factor: wgpu::BlendFactor::Dst,
+                dst_factor: wgpu::BlendFactor::Zero,
+                operation: wgpu::BlendOperation::Add,
+            },
+            alpha: wgpu::BlendComponent {
+                src_factor: wgpu::BlendFactor::Zero,
+                dst_factor: wgpu::BlendFactor::One,
+                operation: wgpu::BlendOperation::Add,
+            },
+        };
+
+        let composite_shader =
+            device.create_shader_module(wgpu::include_wgsl!("light_composite.wgsl"));
+        let composite_pipeline = device.create_render_pipeline(&wgpu::RenderPipelineDescriptor {
+            label: None,
+            layout: Some(&composite_pipeline_layout),
+            vertex: wgpu::VertexState {
+                module: &composite_shader,
+                entry_point: "vs_main",
+                buffers: &[],
+            },
+            fragment: Some(wgpu::FragmentState {
+                module: &composite_shader,
+                entry_point: "fs_main",
+                targets: &[Some(wgpu::ColorTargetState {
+                    format: config.format,
+                    blend: Some(multiply),
+                    write_mask: wgpu::ColorWrites::ALL,
+                })],
+            }),
+            primitive: wgpu::PrimitiveState::default(),
+            depth_stencil: None,
+            multisample: wgpu::MultisampleState::default(),
+            multiview: None,
+        });
+
+        Self {
+            vertices: vec![],
+            vertex_buffer,
+            indices: vec![],
+            index_buffer,
+            index_count: 0,
+            occlusion_texels: vec![0; (OCCLUSION_SIZE * OCCLUSION_SIZE) as usize],
+            occlusion_texture,
+            occlusion_buffer,
+            bind_group,
+            pipeline,
+            light_view,
+            composite_bind_group_layout,
+            composite_bind_group,
+            composite_pipeline,
+            ambient: Vec3::ONE,
+        }
+    }
+
+    pub fn resize(&mut self, rendering_state: &renderer::RenderingState) {
+        let device = &rendering_state.device;
+        let config = &rendering_state.config;
+        self.light_view = create_light_texture(device, config.width, config.height)
+            .create_view(&wgpu::TextureViewDescriptor::default());
+        self.composite_bind_group = create_composite_bind_group(
+            device,
+            &self.composite_bind_group_layout,
+            &self.light_view,
+        );
+    }
+
+    pub fn upload(
+        &mut self,
+        rendering_state: &mut renderer::RenderingState,
+        encoder: &mut wgpu::CommandEncoder,
+        extract: &game_loop::Extract,
+    ) {
+        let device = &rendering_state.device;
+        let queue = &rendering_state.queue;
+        let staging_belt = &mut rendering_state.staging_belt;
+
+        self.ambient = extract.ambient;
+
+        extract.lights.iter().take(MAX_LIGHTS).for_each(|light| {
+            let rect = aabb2(light.position - light.radius, light.position + light.radius);
+
+            let vertex_count = self.vertices.len() as u32;
+            self.indices.push(vertex_count);
+            self.indices.push(vertex_count + 1);
+            self.indices.push(vertex_count + 2);
+            self.indices.push(vertex_count + 2);
+            self.indices.push(vertex_count + 3);
+            self.indices.push(vertex_count);
+
+            let vertex = |position: Vec2| Vertex {
+                position: position.to_array(),
+                center: light.position.to_array(),
+                color: light.color.to_array(),
+                radius: light.radius,
+                occlusion: light.occlusion as u32 as f32,
+            };
+            self.vertices.push(vertex(vec2(rect.min.x, rect.min.y)));
+            self.vertices.push(vertex(vec2(rect.max.x, rect.min.y)));
+            self.vertices.push(vertex(vec2(rect.max.x, rect.max.y)));
+            self.vertices.push(vertex(vec2(rect.min.x, rect.max.y)));
+        });
+
+        let vertex_data = bytemuck::cast_slice(&self.vertices);
+        if let Some(size) = num::NonZeroU64::new(vertex_data.len() as u64) {
+            staging_belt
+                .write_buffer(encoder, &self.vertex_buffer, 0, size, device)
+                .copy_from_slice(vertex_data);
+        }
+        self.vertices.clear();
+
+        let index_data = bytemuck::cast_slice(&self.indices);
+        if let Some(size) = num::NonZeroU64::new(index_data.len() as u64) {
+            staging_belt
+                .write_buffer(encoder, &self.index_buffer, 0, size, device)
+                .copy_from_slice(index_data);
+        }
+        self.index_count = self.indices.len() as u32;
+        self.indices.clear();
+
+        // NOTE: 画面の中心を含むタイルの範囲に遮蔽物を書き込む。
+        let center = extract.matrix.inverse().project_point3(Vec3::ZERO).xy();
+        let origin = center.floor().as_ivec2() - (OCCLUSION_SIZE / 2) as i32;
+        let occlusion_rect = iaabb2(origin, origin + OCCLUSION_SIZE as i32);
+
+        self.occlusion_texels.fill(0);
+        extract.occluders.iter().for_each(|rect| {
+            let min = rect.min.max(occlusion_rect.min) - origin;
+            let max = rect.max.min(occlusion_rect.max) - origin;
+            (min.y..max.y).for_each(|y| {
+                let row = (y as u32 * OCCLUSION_SIZE) as usize;
+                self.occlusion_texels[row + min.x as usize..row + max.x as usize].fill(u8::MAX);
+            });
+        });
+
+        queue.write_texture(
+            self.occlusion_texture.as_image_copy(),
+            &self.occlusion_texels,
+            wgpu::ImageDataLayout {
+                offset: 0,
+                bytes_per_row: Some(OCCLUSION_SIZE),
+                rows_per_image: Some(OCCLUSION_SIZE),
+            },
+            self.occlusion_texture.size(),
+        );
+
+        let occlusion = Occlusion {
+            origin: origin.as_vec2().to_array(),
+            size: [OCCLUSION_SIZE as f32; 2],
+        };
+        if let Some(size) = num::NonZeroU64::new(self.occlusion_buffer.size()) {
+            staging_belt
+                .write_buffer(encoder, &self.occlusion_buffer, 0, size, device)
+                .copy_from_slice(bytemuck::cast_slice(&[occlusion]));
+        }
+    }
+
+    /// 環境光の色で初期化したライトバッファへ光源を加算する。
+    pub fn render(
+        &self,
+        encoder: &mut wgpu::CommandEncoder,
+        camera_resource: &camera::CameraResource,
+    ) {
+        let ambient = wgpu::Color {
+            r: self.ambient.x as f64,
+            g: self.ambient.y as f64,
+            b: self.ambient.z as f64,
+            a: 1.0,
+        };
+
+        let mut render_pass = encoder.begin_render_pass(&wgpu::RenderPassDescriptor {
+            label: None,
+            color_attachments: &[Some(wgpu::RenderPassColorAttachment {
+                view: &self.light_view,
+                resolve_target: None,
+                ops: wgpu::Operations {
+                    load: wgpu::LoadOp::Clear(ambient),
+                    store: wgpu::StoreOp::Store,
+                },
+            })],
+            depth_stencil_attachment: None,
+            occlusion_query_set: None,
+            timestamp_writes: None,
+        });
+
+        render_pass.set_pipeline(&self.pipeline);
+        render_pass.set_bind_group(0, &self.bind_group, &[]);
+        render_pass.set_bind_group(1, camera_resource.bind_group(), &[]);
+        render_pass.set_vertex_buffer(0, self.vertex_buffer.slice(..));
+        render_pass.set_index_buffer(self.index_buffer.slice(..), wgpu::IndexFormat::Uint32);
+        render_pass.draw_indexed(0..self.index_count, 0, 0..1);
+    }
+
+    /// ライトバッファを描写結果に乗算する。
+    pub fn composite(&self, encoder: &mut wgpu::CommandEncoder, frame_view: &wgpu::TextureView) {
+        let mut render_pass = encoder.begin_render_pass(&wgpu::RenderPassDescriptor {
+            label: None,
+            color_attachments: &[Some(wgpu::RenderPassColorAttachment {
+                view: frame_view,
+                resolve_target: None,
+                ops: wgpu::Operations {
+                    load: wgpu::LoadOp::Load,
+                    store: wgpu::StoreOp::Store,
+                },
+            })],
+            depth_stencil_attachment: None,
+            occlusion_query_set: None,
+            timestamp_writes: None,
+        });
+
+        render_pass.set_pipeline(&self.composite_pipeline);
+        render_pass.set_bind_group(0, &self.composite_bind_group, &[]);
+        render_pass.draw(0..3, 0..1);
+    }
+}
+
+fn create_light_texture(device: &wgpu::Device, width: u32, height: u32) -> wgpu::Texture {
+    device.create_texture(&wgpu::TextureDescriptor {
+        label: None,
+        size: wgpu::Extent3d {
+            width,
+            height,
+            depth_or_array_layers: 1,
+        },
+        mip_level_count: 1,
+        sample_count: 1,
+        dimension: wgpu::TextureDimension::D2,
+        format: LIGHT_FORMAT,
+        usage: wgpu::TextureUsages::RENDER_ATTACHMENT | wgpu::TextureUsages::TEXTURE_BINDING,
+        view_formats: &[],
+    })
+}
+
+fn create_composite_bind_group(
+    device: &wgpu::Device,
+    layout: &wgpu::BindGroupLayout,
+    light_view: &wgpu::TextureView,
+) -> wgpu::BindGroup {
+    device.create_bind_group(&wgpu::BindGroupDescriptor {
+        label: None,
+        layout,
+        entries: &[wgpu::BindGroupEntry {
+            binding: 0,
+            resource: wgpu::BindingResource::TextureView(light_view),
+        }],
+    })
+}
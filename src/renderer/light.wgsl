@@ -0,0 +1,74 @@
+struct Occlusion {
+    origin: vec2<f32>,
+    size: vec2<f32>,
+};
+
+@group(0) @binding(0)
+var occlusion_texture: texture_2d<f32>;
+@group(0) @binding(1)
+var occlusion_sampler: sampler;
+@group(0) @binding(2)
+var<uniform> occlusion: Occlusion;
+
+@group(1) @binding(0)
+var<uniform> view_matrix: mat4x4<f32>;
+
+struct VertexInput {
+    @location(0) position: vec2<f32>,
+    @location(1) center: vec2<f32>,
+    @location(2) color: vec3<f32>,
+    @location(3) radius: f32,
+    @location(4) occlusion: f32,
+};
+
+struct VertexOutput {
+    @builtin(position) clip_position: vec4<f32>,
+    @location(0) position: vec2<f32>,
+    @location(1) center: vec2<f32>,
+    @location(2) color: vec3<f32>,
+    @location(3) radius: f32,
+    @location(4) occlusion: f32,
+};
+
+@vertex
+fn vs_main(
+    vertex: VertexInput,
+) -> VertexOutput {
+    var out: VertexOutput;
+    out.clip_position = view_matrix * vec4<f32>(vertex.position, 0.0, 1.0);
+    out.position = vertex.position;
+    out.center = vertex.center;
+    out.color = vertex.color;
+    out.radius = vertex.radius;
+    out.occlusion = vertex.occlusion;
+    return out;
+}
+
+// 遮蔽を調べる標本の数
+const STEPS: i32 = 16;
+// 固体のブロック1タイルあたりの光の減衰
+const ABSORPTION: f32 = 2.0;
+
+@fragment
+fn fs_main(in: VertexOutput) -> @location(0) vec4<f32> {
+    let offset = in.center - in.position;
+    let distance = length(offset);
+
+    var intensity = clamp(1.0 - distance / in.radius, 0.0, 1.0);
+    intensity = intensity * intensity;
+
+    // NOTE: 光源へ向かって遮蔽物を積算する。(照らされる面が暗くならないように自身のタイルは除く)
+    if in.occlusion > 0.5 && distance > 0.5 {
+        let start = 0.5 / distance;
+        var blocked = 0.0;
+        for (var i = 0; i < STEPS; i++) {
+            let t = mix(start, 1.0, (f32(i) + 0.5) / f32(STEPS));
+            let uv = (in.position + offset * t - occlusion.origin) / occlusion.size;
+            blocked += textureSampleLevel(occlusion_texture, occlusion_sampler, uv, 0.0).r;
+        }
+        let step = distance * (1.0 - start) / f32(STEPS);
+        intensity *= exp(-blocked * step * ABSORPTION);
+    }
+
+    return vec4<f32>(in.color * intensity, 1.0);
+}
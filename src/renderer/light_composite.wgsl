@@ -0,0 +1,19 @@
+@group(0) @binding(0)
+var light_texture: texture_2d<f32>;
+
+// NOTE: 画面全体を覆う三角形
+@vertex
+fn vs_main(
+    @builtin(vertex_index) vertex_index: u32,
+) -> @builtin(position) vec4<f32> {
+    let uv = vec2<f32>(f32((vertex_index << 1u) & 2u), f32(vertex_index & 2u));
+    return vec4<f32>(uv * 2.0 - 1.0, 0.0, 1.0);
+}
+
+@fragment
+fn fs_main(@builtin(position) position: vec4<f32>) -> @location(0) vec4<f32> {
+    let light = textureLoad(light_texture, vec2<i32>(position.xy), 0);
+
+    // NOTE: 描写結果に乗算されるため、元の色より明るくはしない。
+    return vec4<f32>(min(light.rgb, vec3<f32>(1.0)), 1.0);
+}
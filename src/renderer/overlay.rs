@@ -0,0 +1,479 @@
+//! デバッグ表示・開発者コンソール・ホットバーの描写に関するモジュール
+//!
+//! 画面上の位置はCPU側で正規化デバイス座標へ変換し、光源の乗算の後に重ねる。
+//! 文字とアイテムのアイコンは1枚のテクスチャにまとめる。
+
+use std::num;
+
+use glam::*;
+use wgpu::util::DeviceExt;
+
+use crate::aabb::*;
+use crate::{assets, game_loop, renderer};
+
+/// 1フレームに描写する矩形の最大数
+const MAX_QUADS: usize = 16384;
+
+/// コンソールに表示する出力の行数
+const CONSOLE_LINES: usize = 16;
+
+/// 文字列の余白 (ピクセル)
+const PADDING: f32 = 4.0;
+
+/// 文字のテクスチャに並ぶ最初の文字
+const FIRST_CHAR: u32 = 0x20;
+
+/// 塗りつぶしに使用する文字 (テクスチャの最後の文字)
+const SOLID_CHAR: u32 = 0x7F;
+
+/// テクスチャ上のアイコンの大きさ (ピクセル)
+const ICON_SIZE: u32 = 32;
+
+/// ホットバーのスロットの大きさ (ピクセル)
+const SLOT_SIZE: f32 = 40.0;
+
+#[repr(C)]
+#[derive(Clone, Copy, bytemuck::Pod, bytemuck::Zeroable)]
+struct Vertex {
+    pub position: [f32; 2],
+    pub texcoord: [f32; 2],
+    pub color: [f32; 4],
+    /// テクスチャのアルファのみを使用するか (文字は`1.0`、アイコンは`0.0`)
+    pub mask: f32,
+}
+
+impl Vertex {
+    const ATTRIBUTES: &'static [wgpu::VertexAttribute] =
+        &wgpu::vertex_attr_array![0 => Float32x2, 1 => Float32x2, 2 => Float32x4, 3 => Float32];
+
+    #[inline]
+    fn layout<'a>() -> wgpu::VertexBufferLayout<'a> {
+        wgpu::VertexBufferLayout {
+            array_stride: std::mem::size_of::<Self>() as u64,
+            step_mode: wgpu::VertexStepMode::Vertex,
+            attributes: Self::ATTRIBUTES,
+        }
+    }
+}
+
+pub struct OverlayRenderer {
+    vertices: Vec<Vertex>,
+    vertex_buffer: wgpu::Buffer,
+    indices: Vec<u32>,
+    index_buffer: wgpu::Buffer,
+    index_count: u32,
+    /// 1文字の大きさ (ピクセル)
+    char_size: Vec2,
+    /// 文字の格子が占めるテクスチャ座標の範囲
+    font_extent: Vec2,
+    /// アイテムの仕様毎のアイコンのテクスチャ座標
+    icon_texcoords: Vec<Aabb2>,
+    bind_group: wgpu::BindGroup,
+    pipeline: wgpu::RenderPipeline,
+}
+
+impl OverlayRenderer {
+    pub fn new(
+        assets: std::rc::Rc<assets::Assets>,
+        rendering_state: &renderer::RenderingState,
+    ) -> Self {
+        let device = &rendering_state.device;
+        let queue = &rendering_state.queue;
+        let config = &rendering_state.config;
+
+        let vertex_buffer = device.create_buffer(&wgpu::BufferDescriptor {
+            label: None,
+            size: (std::mem::size_of::<Vertex>() * 4 * MAX_QUADS) as u64,
+            usage: wgpu::BufferUsages::VERTEX | wgpu::BufferUsages::COPY_DST,
+            mapped_at_creation: false,
+        });
+
+        let index_buffer = device.create_buffer(&wgpu::BufferDescriptor {
+            label: None,
+            size: (std::mem::size_of::<u32>() * 6 * MAX_QUADS) as u64,
+            usage: wgpu::BufferUsages::INDEX | wgpu::BufferUsages::COPY_DST,
+            mapped_at_creation: false,
+        });
+
+        // NOTE: 文字は横16文字・縦6文字の格子に並ぶ。
+        let font = image::open(&assets.font_texture_path).unwrap().to_rgba8();
+        let char_size = vec2(font.width() as f32 / 16.0, font.height() as f32 / 6.0);
+
+        // NOTE: アイコンは文字の格子の下に大きさを揃えて並べる。
+        let columns = (font.width() / ICON_SIZE).max(1);
+        let rows = (assets.item_specs.len() as u32).div_ceil(columns);
+        let mut image = image::RgbaImage::new(
+            font.width().max(columns * ICON_SIZE),
+            font.height() + rows * ICON_SIZE,
+        );
+        image::imageops::replace(&mut image, &font, 0, 0);
+
+        let image_size = vec2(image.width() as f32, image.height() as f32);
+        let font_extent = vec2(font.width() as f32, font.height() as f32) / image_size;
+        let icon_texcoords = assets
+            .item_specs
+            .iter()
+            .enumerate()
+            .map(|(i, spec)| {
+                let icon = image::open(&spec.icon_texture_path).unwrap().to_rgba8();
+                let icon = image::imageops::resize(
+                    &icon,
+                    ICON_SIZE,
+                    ICON_SIZE,
+                    image::imageops::FilterType::Triangle,
+                );
+                let min = uvec2(i as u32 % columns, i as u32 / columns) * ICON_SIZE
+                    + uvec2(0, font.height());
+                image::imageops::replace(&mut image, &icon, min.x as i64, min.y as i64);
+
+                let min = min.as_vec2();
+                aabb2(min, min + ICON_SIZE as f32) / image_size
+            })
+            .collect::<Vec<_>>();
+
+        let texture = device.create_texture_with_data(
+            queue,
+            &wgpu::TextureDescriptor {
+                label: None,
+                size: wgpu::Extent3d {
+                    width: image.width(),
+                    height: image.height(),
+                    depth_or_array_layers: 1,
+                },
+                mip_level_count: 1,
+                sample_count: 1,
+                dimension: wgpu::TextureDimension::D2,
+                format: wgpu::TextureFormat::Rgba8Unorm,
+                usage: wgpu::TextureUsages::TEXTURE_BINDING,
+                view_formats: &[],
+            },
+            wgpu::util::TextureDataOrder::default(),
+            &image,
+        );
+        let texture_view = texture.create_view(&wgpu::TextureViewDescriptor::default());
+        let sampler = device.create_sampler(&wgpu::SamplerDescriptor::default());
+
+        let bind_group_layout = device.create_bind_group_layout(&wgpu::BindGroupLayoutDescriptor {
+            label: None,
+            entries: &[
+                wgpu::BindGroupLayoutEntry {
+                    binding: 0,
+                    visibility: wgpu::ShaderStages::FRAGMENT,
+                    ty: wgpu::BindingType::Texture {
+                        sample_type: wgpu::TextureSampleType::Float { filterable: true },
+                        view_dimension: wgpu::TextureViewDimension::D2,
+                        multisampled: false,
+                    },
+                    count: None,
+                },
+                wgpu::BindGroupLayoutEntry {
+                    binding: 1,
+                    visibility: wgpu::ShaderStages::FRAGMENT,
+                    ty: wgpu::BindingType::Sampler(wgpu::SamplerBindingType::Filtering),
+                    count: None,
+                },
+            ],
+        });
+
+        let bind_group = device.create_bind_group(&wgpu::BindGroupDescriptor {
+            label: None,
+            layout: &bind_group_layout,
+            entries: &[
+                wgpu::BindGroupEntry {
+                    binding: 0,
+                    resource: wgpu::BindingResource::TextureView(&texture_view),
+                },
+                wgpu::BindGroupEntry {
+                    binding: 1,
+                    resource: wgpu::BindingResource::Sampler(&sampler),
+                },
+            ],
+        });
+
+        let pipeline_layout = device.create_pipeline_layout(&wgpu::PipelineLayoutDescriptor {
+            label: None,
+            bind_group_layouts: &[&bind_group_layout],
+            push_constant_ranges: &[],
+        });
+
+        let shader = device.create_shader_module(wgpu::include_wgsl!("overlay.wgsl"));
+        let pipeline = device.create_render_pipeline(&wgpu::RenderPipelineDescriptor {
+            label: None,
+            layout: Some(&pipeline_layout),
+            vertex: wgpu::VertexState {
+                module: &shader,
+                entry_point: "vs_main",
+                buffers: &[Vertex::layout()],
+            },
+            fragment: Some(wgpu::FragmentState {
+                module: &shader,
+                entry_point: "fs_main",
+                targets: &[Some(wgpu::ColorTargetState {
+                    format: config.format,
+                    blend: Some(wgpu::BlendState::ALPHA_BLENDING),
+                    write_mask: wgpu::ColorWrites::ALL,
+                })],
+            }),
+            primitive: wgpu::PrimitiveState::default(),
+            depth_stencil: None,
+            multisample: wgpu::MultisampleState::default(),
+            multiview: None,
+        });
+
+        Self {
+            vertices: vec![],
+            vertex_buffer,
+            indices: vec![],
+            index_buffer,
+            index_count: 0,
+            char_size,
+            font_extent,
+            icon_texcoords,
+            bind_group,
+            pipeline,
+        }
+    }
+
+    pub fn upload(
+        &mut self,
+        rendering_state: &mut renderer::RenderingState,
+        encoder: &mut wgpu::CommandEncoder,
+        extract: &game_loop::Extract,
+    ) {
+        let device = &rendering_state.device;
+        let config = &rendering_state.config;
+        let staging_belt = &mut rendering_state.staging_belt;
+
+        let screen_size = vec2(config.width as f32, config.height as f32);
+
+        // NOTE: 範囲の輪郭を1ピクセルの幅で描写する。
+        let pixel = 2.0 / screen_size;
+        extract.debug_rects.iter().for_each(|(rect, color)| {
+            let min = extract.matrix.project_point3(rect.min.extend(0.0)).xy();
+            let max = extract.matrix.project_point3(rect.max.extend(0.0)).xy();
+            let edges = [
+                aabb2(min, vec2(max.x, min.y + pixel.y)),
+                aabb2(vec2(min.x, max.y - pixel.y), max),
+                aabb2(min, vec2(min.x + pixel.x, max.y)),
+                aabb2(vec2(max.x - pixel.x, min.y), max),
+            ];
+            edges
+                .into_iter()
+                .for_each(|edge| self.push_solid(edge, *color));
+        });
+
+        // NOTE: 画面の上端から順に並べる。
+        let mut top = 0.0;
+
+        if let Some(console) = &extract.console {
+            let bottom = top + self.char_size.y * (CONSOLE_LINES + 1) as f32 + PADDING * 2.0;
+            let panel = aabb2(vec2(0.0, top), vec2(screen_size.x, bottom));
+            self.push_solid(to_ndc(panel, screen_size), vec4(0.0, 0.0, 0.0, 0.75));
+
+            let skip = console.output.len().saturating_sub(CONSOLE_LINES);
+            let lines = console.output.iter().skip(skip);
+            lines.enumerate().for_each(|(i, line)| {
+                let position = vec2(PADDING, top + PADDING + self.char_size.y * i as f32);
+                self.push_text(line, position, screen_size, Vec4::ONE);
+            });
+
+            let position = vec2(
+                PADDING,
+                top + PADDING + self.char_size.y * CONSOLE_LINES as f32,
+            );
+            let line = format!("> {}_", console.line);
+            self.push_text(&line, position, screen_size, vec4(1.0, 0.9, 0.4, 1.0));
+
+            top = bottom;
+        }
+
+        if !extract.debug_lines.is_empty() {
+            let width = extract
+                .debug_lines
+                .iter()
+                .map(|line| line.chars().count())
+                .max()
+                .unwrap_or(0) as f32
+                * self.char_size.x;
+            let height = extract.debug_lines.len() as f32 * self.char_size.y;
+            let panel = aabb2(
+                vec2(0.0, top),
+                vec2(width + PADDING * 2.0, top + height + PADDING * 2.0),
+            );
+            self.push_solid(to_ndc(panel, screen_size), vec4(0.0, 0.0, 0.0, 0.5));
+
+            extract
+                .debug_lines
+                .iter()
+                .enumerate()
+                .for_each(|(i, line)| {
+                    let position = vec2(PADDING, top + PADDING + self.char_size.y * i as f32);
+                    self.push_text(line, position, screen_size, Vec4::ONE);
+                });
+        }
+
+        if let Some(hotbar) = &extract.hotbar {
+            self.push_hotbar(hotbar, screen_size);
+        }
+
+        let vertex_data = bytemuck::cast_slice(&self.vertices);
+        if let Some(size) = num::NonZeroU64::new(vertex_data.len() as u64) {
+            staging_belt
+                .write_buffer(encoder, &self.vertex_buffer, 0, size, device)
+                .copy_from_slice(vertex_data);
+        }
+        self.vertices.clear();
+
+        let index_data = bytemuck::cast_slice(&self.indices);
+        if let Some(size) = num::NonZeroU64::new(index_data.len() as u64) {
+            staging_belt
+                .write_buffer(encoder, &self.index_buffer, 0, size, device)
+                .copy_from_slice(index_data);
+        }
+        self.index_count = self.indices.len() as u32;
+        self.indices.clear();
+    }
+
+    /// 描写結果の上に重ねる。
+    pub fn render(&self, encoder: &mut wgpu::CommandEncoder, frame_view: &wgpu::TextureView) {
+        if self.index_count == 0 {
+            return;
+        }
+
+        let mut render_pass = encoder.begin_render_pass(&wgpu::RenderPassDescriptor {
+            label: None,
+            color_attachments: &[Some(wgpu::RenderPassColorAttachment {
+                view: frame_view,
+                resolve_target: None,
+                ops: wgpu::Operations {
+                    load: wgpu::LoadOp::Load,
+                    store: wgpu::StoreOp::Store,
+                },
+            })],
+            depth_stencil_attachment: None,
+            occlusion_query_set: None,
+            timestamp_writes: None,
+        });
+
+        render_pass.set_pipeline(&self.pipeline);
+        render_pass.set_bind_group(0, &self.bind_group, &[]);
+        render_pass.set_vertex_buffer(0, self.vertex_buffer.slice(..));
+        render_pass.set_index_buffer(self.index_buffer.slice(..), wgpu::IndexFormat::Uint32);
+        render_pass.draw_indexed(0..self.index_count, 0, 0..1);
+    }
+
+    /// ホットバーを画面の下端の中央に描写する。
+    ///
+    /// 選択中のスロットは枠を強調し、2個以上のアイテムは右下に個数を描写する。
+    fn push_hotbar(&mut self, hotbar: &game_loop::player::HotbarView, screen_size: Vec2) {
+        let width = SLOT_SIZE * hotbar.slots.len() as f32;
+        let min = vec2(
+            (screen_size.x - width) * 0.5,
+            screen_size.y - SLOT_SIZE - PADDING * 2.0,
+        );
+        let panel = aabb2(min, min + vec2(width, SLOT_SIZE)).extends(PADDING);
+        self.push_solid(to_ndc(panel, screen_size), vec4(0.0, 0.0, 0.0, 0.5));
+
+        hotbar.slots.iter().enumerate().for_each(|(i, slot)| {
+            let slot_min = min + vec2(SLOT_SIZE * i as f32, 0.0);
+            let slot_rect = aabb2(slot_min, slot_min + SLOT_SIZE);
+
+            if i == hotbar.selected_slot {
+                self.push_solid(to_ndc(slot_rect, screen_size), vec4(1.0, 0.9, 0.4, 0.9));
+            }
+            let inner = slot_rect.extends(-2.0);
+            self.push_solid(to_ndc(inner, screen_size), vec4(0.2, 0.2, 0.2, 0.8));
+
+            let Some(stack) = slot else {
+                return;
+            };
+
+            let Some(&texcoord) = self.icon_texcoords.get(stack.item_spec_id) else {
+                return;
+            };
+            let icon_rect = slot_rect.extends(-PADDING);
+            self.push_quad(to_ndc(icon_rect, screen_size), texcoord, Vec4::ONE, 0.0);
+
+            if stack.count > 1 {
+                let text = stack.count.to_string();
+                let text_size = vec2(self.char_size.x * text.len() as f32, self.char_size.y);
+                let position = slot_rect.max - text_size - 2.0;
+                self.push_text(&text, position, screen_size, Vec4::ONE);
+            }
+        });
+    }
+
+    /// 文字列を左上の位置 (ピクセル) から描写する。
+    ///
+    /// 文字のテクスチャに無い文字は`?`として描写する。
+    fn push_text(&mut self, text: &str, position: Vec2, screen_size: Vec2, color: Vec4) {
+        text.chars().enumerate().for_each(|(i, c)| {
+            let code = match c as u32 {
+                code @ FIRST_CHAR..SOLID_CHAR => code,
+                _ => '?' as u32,
+            };
+            if code == ' ' as u32 {
+                return;
+            }
+            let min = position + vec2(self.char_size.x * i as f32, 0.0);
+            let rect = aabb2(min, min + self.char_size);
+            let texcoord = self.char_texcoord(code);
+            self.push_quad(to_ndc(rect, screen_size), texcoord, color, 1.0);
+        });
+    }
+
+    /// 正規化デバイス座標の範囲を塗りつぶす。
+    fn push_solid(&mut self, rect: Aabb2, color: Vec4) {
+        // NOTE: 隣の文字の混入を防ぐため、塗りつぶしの文字の中心のみを参照する。
+        let center = self.char_texcoord(SOLID_CHAR).center();
+        self.push_quad(rect, aabb2(center, center), color, 1.0);
+    }
+
+    /// 文字のテクスチャ座標を返す。
+    fn char_texcoord(&self, code: u32) -> Aabb2 {
+        let index = code - FIRST_CHAR;
+        let min = vec2((index % 16) as f32 / 16.0, (index / 16) as f32 / 6.0);
+        aabb2(min, min + vec2(1.0 / 16.0, 1.0 / 6.0)) * self.font_extent
+    }
+
+    /// 正規化デバイス座標の範囲に矩形を追加する。
+    ///
+    /// テクスチャ座標は下向きのため、上下を反転して割り当てる。
+    fn push_quad(&mut self, rect: Aabb2, texcoord: Aabb2, color: Vec4, mask: f32) {
+        if self.indices.len() / 6 >= MAX_QUADS {
+            return;
+        }
+
+        let vertex_count = self.vertices.len() as u32;
+        self.indices.push(vertex_count);
+        self.indices.push(vertex_count + 1);
+        self.indices.push(vertex_count + 2);
+        self.indices.push(vertex_count + 2);
+        self.indices.push(vertex_count + 3);
+        self.indices.push(vertex_count);
+
+        let color = color.to_array();
+        let mut vertex = |position: Vec2, texcoord: Vec2| {
+            self.vertices.push(Vertex {
+                position: position.to_array(),
+                texcoord: texcoord.to_array(),
+                color,
+                mask,
+            });
+        };
+        vertex(rect.min, vec2(texcoord.min.x, texcoord.max.y));
+        vertex(vec2(rect.max.x, rect.min.y), texcoord.max);
+        vertex(rect.max, vec2(texcoord.max.x, texcoord.min.y));
+        vertex(vec2(rect.min.x, rect.max.y), texcoord.min);
+    }
+}
+
+/// 画面上の範囲 (ピクセル、左上が原点) を正規化デバイス座標へ変換する。
+fn to_ndc(rect: Aabb2, screen_size: Vec2) -> Aabb2 {
+    let min = vec2(rect.min.x, rect.max.y) / screen_size;
+    let max = vec2(rect.max.x, rect.min.y) / screen_size;
+    aabb2(
+        vec2(min.x * 2.0 - 1.0, 1.0 - min.y * 2.0),
+        vec2(max.x * 2.0 - 1.0, 1.0 - max.y * 2.0),
+    )
+}
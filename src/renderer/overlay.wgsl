@@ -0,0 +1,38 @@
+@group(0) @binding(0)
+var overlay_texture: texture_2d<f32>;
+
+@group(0) @binding(1)
+var overlay_sampler: sampler;
+
+struct VertexInput {
+    @location(0) position: vec2<f32>,
+    @location(1) texcoord: vec2<f32>,
+    @location(2) color: vec4<f32>,
+    @location(3) mask: f32,
+};
+
+struct VertexOutput {
+    @builtin(position) clip_position: vec4<f32>,
+    @location(0) texcoord: vec2<f32>,
+    @location(1) color: vec4<f32>,
+    @location(2) mask: f32,
+};
+
+// NOTE: 位置は正規化デバイス座標で渡される。
+@vertex
+fn vs_main(in: VertexInput) -> VertexOutput {
+    var out: VertexOutput;
+    out.clip_position = vec4<f32>(in.position, 0.0, 1.0);
+    out.texcoord = in.texcoord;
+    out.color = in.color;
+    out.mask = in.mask;
+    return out;
+}
+
+@fragment
+fn fs_main(in: VertexOutput) -> @location(0) vec4<f32> {
+    let texel = textureSample(overlay_texture, overlay_sampler, in.texcoord);
+    // NOTE: 文字はアルファのみを使用し、アイコンはそのままの色を使用する。
+    let color = mix(texel, vec4<f32>(1.0, 1.0, 1.0, texel.a), in.mask);
+    return color * in.color;
+}
@@ -0,0 +1,232 @@
+//! 天候の描写に関するモジュール
+
+use std::num;
+
+use glam::*;
+
+use crate::{
+    assets, game_loop,
+    renderer::{self, camera},
+};
+
+/// 1フレームに描写する粒子の最大数
+const MAX_PARTICLES: u32 = 16384;
+
+/// 天候の描写に使用するデータ
+#[repr(C)]
+#[derive(Clone, Copy, Default, bytemuck::Pod, bytemuck::Zeroable)]
+struct Weather {
+    pub color: [f32; 4],
+    pub fog_color: [f32; 4],
+    pub origin: [f32; 2],
+    pub area: [f32; 2],
+    pub offset: [f32; 2],
+    pub size: [f32; 2],
+    pub direction: [f32; 2],
+    pub sway: f32,
+    pub time: f32,
+}
+
+pub struct WeatherRenderer {
+    assets: std::rc::Rc<assets::Assets>,
+    weather_buffer: wgpu::Buffer,
+    bind_group: wgpu::BindGroup,
+    particle_pipeline: wgpu::RenderPipeline,
+    particle_count: u32,
+    fog_pipeline: wgpu::RenderPipeline,
+}
+
+impl WeatherRenderer {
+    pub fn new(
+        assets: std::rc::Rc<assets::Assets>,
+        rendering_state: &renderer::RenderingState,
+        camera_resource: &camera::CameraResource,
+    ) -> Self {
+        let device = &rendering_state.device;
+        let config = &rendering_state.config;
+
+        let weather_buffer = device.create_buffer(&wgpu::BufferDescriptor {
+            label: None,
+            size: std::mem::size_of::<Weather>() as u64,
+            usage: wgpu::BufferUsages::UNIFORM | wgpu::BufferUsages::COPY_DST,
+            mapped_at_creation: false,
+        });
+
+        let bind_group_layout = device.create_bind_group_layout(&wgpu::BindGroupLayoutDescriptor {
+            label: None,
+            entries: &[wgpu::BindGroupLayoutEntry {
+                binding: 0,
+                visibility: wgpu::ShaderStages::VERTEX_FRAGMENT,
+                ty: wgpu::BindingType::Buffer {
+                    ty: wgpu::BufferBindingType::Uniform,
+                    has_dynamic_offset: false,
+                    min_binding_size: None,
+                },
+                count: None,
+            }],
+        });
+
+        let bind_group = device.create_bind_group(&wgpu::BindGroupDescriptor {
+            label: None,
+            layout: &bind_group_layout,
+            entries: &[wgpu::BindGroupEntry {
+                binding: 0,
+                resource: weather_buffer.as_entire_binding(),
+            }],
+        });
+
+        let shader = device.create_shader_module(wgpu::include_wgsl!("weather.wgsl"));
+
+        // NOTE: 粒子と霧は全ての描写対象より手前に重ねる。
+        let depth_stencil = wgpu::DepthStencilState {
+            format: camera::DEPTH_FORMAT,
+            depth_write_enabled: false,
+            depth_compare: wgpu::CompareFunction::Always,
+            stencil: wgpu::StencilState::default(),
+            bias: wgpu::DepthBiasState::default(),
+        };
+        let target = wgpu::ColorTargetState {
+            format: config.format,
+            blend: Some(wgpu::BlendState::ALPHA_BLENDING),
+            write_mask: wgpu::ColorWrites::ALL,
+        };
+
+        let particle_pipeline_layout =
+            device.create_pipeline_layout(&wgpu::PipelineLayoutDescriptor {
+                label: None,
+                bind_group_layouts: &[&bind_group_layout, camera_resource.bind_group_layout()],
+                push_constant_ranges: &[],
+            });
+
+        let particle_pipeline = device.create_render_pipeline(&wgpu::RenderPipelineDescriptor {
+            label: None,
+            layout: Some(&particle_pipeline_layout),
+            vertex: wgpu::VertexState {
+                module: &shader,
+                entry_point: "vs_particle",
+                buffers: &[],
+            },
+            fragment: Some(wgpu::FragmentState {
+                module: &shader,
+                entry_point: "fs_particle",
+                targets: &[Some(target.clone())],
+            }),
+            primitive: wgpu::PrimitiveState::default(),
+            depth_stencil: Some(depth_stencil.clone()),
+            multisample: wgpu::MultisampleState::default(),
+            multiview: None,
+        });
+
+        let fog_pipeline_layout = device.create_pipeline_layout(&wgpu::PipelineLayoutDescriptor {
+            label: None,
+            bind_group_layouts: &[&bind_group_layout],
+            push_constant_ranges: &[],
+        });
+
+        let fog_pipeline = device.create_render_pipeline(&wgpu::RenderPipelineDescriptor {
+            label: None,
+            layout: Some(&fog_pipeline_layout),
+            vertex: wgpu::VertexState {
+                module: &shader,
+                entry_point: "vs_fog",
+                buffers: &[],
+            },
+            fragment: Some(wgpu::FragmentState {
+                module: &shader,
+                entry_point: "fs_fog",
+                targets: &[Some(target)],
+            }),
+            primitive: wgpu::PrimitiveState::default(),
+            depth_stencil: Some(depth_stencil),
+            multisample: wgpu::MultisampleState::default(),
+            multiview: None,
+        });
+
+        Self {
+            assets,
+            weather_buffer,
+            bind_group,
+            particle_pipeline,
+            particle_count: 0,
+            fog_pipeline,
+        }
+    }
+
+    pub fn upload(
+        &mut self,
+        rendering_state: &mut renderer::RenderingState,
+        encoder: &mut wgpu::CommandEncoder,
+        extract: &game_loop::Extract,
+    ) {
+        let device = &rendering_state.device;
+        let staging_belt = &mut rendering_state.staging_belt;
+
+        let weather_spec = extract
+            .weather
+            .weather_spec_id
+            .map(|weather_spec_id| &self.assets.weather_specs[weather_spec_id]);
+        let intensity = extract.weather.intensity;
+
+        let mut weather = Weather::default();
+        self.particle_count = 0;
+
+        if let Some(particle_spec) = weather_spec.and_then(|spec| spec.particle.as_ref()) {
+            // NOTE: 画面に映る範囲を粒子の大きさの分だけ広げた範囲で粒子を折り返す。
+            let inverse = extract.matrix.inverse();
+            let min = inverse.project_point3(vec3(-1.0, -1.0, 0.0)).xy();
+            let max = inverse.project_point3(vec3(1.0, 1.0, 0.0)).xy();
+            let margin = particle_spec.size.max_element() + particle_spec.sway;
+            let origin = min.min(max) - margin;
+            let area = (max - min).abs() + margin * 2.0;
+
+            // NOTE: 精度を保つために移動量は倍精度で折り返す。
+            let offset = (particle_spec.velocity.as_dvec2() * extract.time)
+                .rem_euclid(area.as_dvec2())
+                .as_vec2();
+            let direction = particle_spec
+                .velocity
+                .try_normalize()
+                .unwrap_or(Vec2::NEG_Y);
+
+            let count = particle_spec.density * area.x * area.y * intensity;
+            self.particle_count = (count as u32).min(MAX_PARTICLES);
+
+            weather.color = particle_spec.color.to_array();
+            weather.origin = origin.to_array();
+            weather.area = area.to_array();
+            weather.offset = offset.to_array();
+            weather.size = particle_spec.size.to_array();
+            weather.direction = direction.to_array();
+            weather.sway = particle_spec.sway;
+            weather.time = (extract.time % 3600.0) as f32;
+        }
+
+        if let Some(fog_spec) = weather_spec.and_then(|spec| spec.fog.as_ref()) {
+            weather.fog_color = fog_spec
+                .color
+                .extend(fog_spec.density * intensity)
+                .to_array();
+        }
+
+        if let Some(size) = num::NonZeroU64::new(self.weather_buffer.size()) {
+            staging_belt
+                .write_buffer(encoder, &self.weather_buffer, 0, size, device)
+                .copy_from_slice(bytemuck::cast_slice(&[weather]));
+        }
+    }
+
+    pub fn render<'a>(
+        &'a self,
+        render_pass: &mut wgpu::RenderPass<'a>,
+        camera_resource: &'a camera::CameraResource,
+    ) {
+        render_pass.set_pipeline(&self.fog_pipeline);
+        render_pass.set_bind_group(0, &self.bind_group, &[]);
+        render_pass.draw(0..3, 0..1);
+
+        render_pass.set_pipeline(&self.particle_pipeline);
+        render_pass.set_bind_group(0, &self.bind_group, &[]);
+        render_pass.set_bind_group(1, camera_resource.bind_group(), &[]);
+        render_pass.draw(0..6, 0..self.particle_count);
+    }
+}
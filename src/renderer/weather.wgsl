@@ -0,0 +1,86 @@
+struct Weather {
+    color: vec4<f32>,
+    fog_color: vec4<f32>,
+    origin: vec2<f32>,
+    area: vec2<f32>,
+    offset: vec2<f32>,
+    size: vec2<f32>,
+    direction: vec2<f32>,
+    sway: f32,
+    time: f32,
+};
+
+@group(0) @binding(0)
+var<uniform> weather: Weather;
+
+@group(1) @binding(0)
+var<uniform> view_matrix: mat4x4<f32>;
+
+struct ParticleOutput {
+    @builtin(position) clip_position: vec4<f32>,
+    @location(0) local: vec2<f32>,
+};
+
+fn hash(x: u32) -> u32 {
+    let state = x * 747796405u + 2891336453u;
+    let word = ((state >> ((state >> 28u) + 4u)) ^ state) * 277803737u;
+    return (word >> 22u) ^ word;
+}
+
+// `0.0`から`1.0`の乱数
+fn random(x: u32) -> f32 {
+    return f32(hash(x) >> 8u) / 16777216.0;
+}
+
+// NOTE: 粒子は乱数で決まる位置から一定の速度で動き、範囲の端で反対側へ折り返す。
+@vertex
+fn vs_particle(
+    @builtin(vertex_index) vertex_index: u32,
+    @builtin(instance_index) instance_index: u32,
+) -> ParticleOutput {
+    let seed = vec2<f32>(random(instance_index * 2u), random(instance_index * 2u + 1u));
+    let phase = random(instance_index ^ 0x68bc21ebu) * 6.2831853;
+
+    let sway = vec2<f32>(sin(weather.time * 1.7 + phase) * weather.sway, 0.0);
+    let position = seed * weather.area + weather.offset + sway - weather.origin;
+    let center = weather.origin + position - weather.area * floor(position / weather.area);
+
+    var corners = array<vec2<f32>, 6>(
+        vec2<f32>(-0.5, -0.5),
+        vec2<f32>(0.5, -0.5),
+        vec2<f32>(0.5, 0.5),
+        vec2<f32>(0.5, 0.5),
+        vec2<f32>(-0.5, 0.5),
+        vec2<f32>(-0.5, -0.5),
+    );
+    let local = corners[vertex_index];
+
+    // NOTE: 長さ方向を進行方向へ向ける。
+    let side = vec2<f32>(-weather.direction.y, weather.direction.x);
+    let world = center + side * local.x * weather.size.x + weather.direction * local.y * weather.size.y;
+
+    var out: ParticleOutput;
+    out.clip_position = view_matrix * vec4<f32>(world, 0.0, 1.0);
+    out.local = local;
+    return out;
+}
+
+@fragment
+fn fs_particle(in: ParticleOutput) -> @location(0) vec4<f32> {
+    let alpha = weather.color.a * smoothstep(1.0, 0.6, length(in.local * 2.0));
+    return vec4<f32>(weather.color.rgb, alpha);
+}
+
+// NOTE: 画面全体を覆う三角形
+@vertex
+fn vs_fog(
+    @builtin(vertex_index) vertex_index: u32,
+) -> @builtin(position) vec4<f32> {
+    let uv = vec2<f32>(f32((vertex_index << 1u) & 2u), f32(vertex_index & 2u));
+    return vec4<f32>(uv * 2.0 - 1.0, 0.0, 1.0);
+}
+
+@fragment
+fn fs_fog() -> @location(0) vec4<f32> {
+    return weather.fog_color;
+}